@@ -0,0 +1,65 @@
+## Validation errors
+
+invalid-subscriber-name = { $name } is not a valid subscriber name.
+invalid-subscriber-email = { $email } is not a valid email address.
+
+## Subscription errors
+
+subscribe-error-validation = Validation error: { $reason }
+subscribe-error-unknown-list = The mailing list { $list } does not exist.
+subscribe-error-store-token = Failed to store the subscription token.
+subscribe-error-send-email = Failed to send the confirmation email.
+subscribe-error-pool = Failed to acquire a database connection.
+subscribe-error-insert-subscriber = Failed to save the new subscriber.
+subscribe-error-transaction-commit = Failed to commit the database transaction.
+
+## Request bodies
+
+request-error-invalid-body = The request body is invalid.
+request-error-missing-field = This field is required.
+
+## Rate limiting
+
+rate-limit-exceeded = Too many requests. Please try again in { $seconds } seconds.
+
+## Subscription confirmation
+
+confirm-error-unknown-token = The confirmation link is invalid.
+confirm-error-expired-token = This confirmation link has expired. Click the button below and we will send a new confirmation email to your inbox.
+confirm-error-invalid-status = This subscription can no longer be confirmed. Please subscribe again.
+confirm-error-invalid-subscriber = The stored subscriber details are invalid.
+confirm-error-database = A database error occurred while confirming the subscription.
+confirm-error-render-page = Failed to render the page.
+confirm-expired-title = Confirmation link expired
+confirm-expired-resend-button = Resend confirmation email
+confirmation-resent-title = Confirmation email sent
+confirmation-resent-message = A new confirmation email is on its way, please check your inbox.
+
+## Unsubscribe
+
+unsubscribe-page-title = Unsubscribe from our newsletter
+unsubscribe-page-prompt = Once confirmed, you will no longer receive our newsletter.
+unsubscribe-page-button = Unsubscribe
+unsubscribe-page-done = You have been unsubscribed and will no longer receive our newsletter.
+unsubscribe-error-invalid-token = The unsubscribe link is invalid.
+unsubscribe-error-database = A database error occurred while processing the unsubscribe request.
+unsubscribe-error-render-page = Failed to render the unsubscribe page.
+
+## Emails
+
+email-footer = This email was sent automatically, please do not reply.
+email-unsubscribe-link = Unsubscribe
+email-unsubscribe-text = To unsubscribe, visit:
+
+confirmation-subject = Please confirm your subscription
+confirmation-heading = Welcome to our newsletter, { $name }!
+confirmation-instruction = Click the link below to confirm your subscription to { $list }:
+confirmation-link = Confirm subscription
+
+welcome-subject = You're subscribed, welcome aboard!
+welcome-heading = { $name }, your subscription is confirmed
+welcome-body = Thanks for subscribing. Every newsletter issue we publish will be sent to this address.
+
+unsubscribe-confirmation-subject = You have unsubscribed
+unsubscribe-confirmation-heading = { $name }, you have been unsubscribed
+unsubscribe-confirmation-body = We will no longer send newsletter issues to this address. Thanks for reading.
@@ -0,0 +1,65 @@
+## 校验错误
+
+invalid-subscriber-name = 订阅者姓名无效: { $name }
+invalid-subscriber-email = { $email } 不是一个有效的邮箱
+
+## 订阅错误
+
+subscribe-error-validation = 验证错误: { $reason }
+subscribe-error-unknown-list = 邮件列表 { $list } 不存在
+subscribe-error-store-token = 存储令牌错误
+subscribe-error-send-email = 发送邮件错误
+subscribe-error-pool = 数据库连接池错误
+subscribe-error-insert-subscriber = 插入订阅者错误
+subscribe-error-transaction-commit = 事务提交错误
+
+## 请求体
+
+request-error-invalid-body = 请求体格式无效
+request-error-missing-field = 缺少该字段
+
+## 限流
+
+rate-limit-exceeded = 请求过于频繁, 请在 { $seconds } 秒后重试
+
+## 确认订阅
+
+confirm-error-unknown-token = 确认链接无效
+confirm-error-expired-token = 该确认链接已过期。点击下方按钮, 我们会向您的邮箱重新发送一封确认邮件。
+confirm-error-invalid-status = 该订阅当前无法确认, 请重新订阅
+confirm-error-invalid-subscriber = 存储的订阅者信息无效
+confirm-error-database = 确认订阅时发生数据库错误
+confirm-error-render-page = 页面渲染失败
+confirm-expired-title = 确认链接已过期
+confirm-expired-resend-button = 重新发送确认邮件
+confirmation-resent-title = 确认邮件已发送
+confirmation-resent-message = 新的确认邮件已发送, 请查收。
+
+## 退订
+
+unsubscribe-page-title = 退订新闻邮件
+unsubscribe-page-prompt = 确认后您将不再收到我们的新闻邮件。
+unsubscribe-page-button = 确认退订
+unsubscribe-page-done = 您已成功退订, 不会再收到我们的新闻邮件。
+unsubscribe-error-invalid-token = 退订链接无效
+unsubscribe-error-database = 处理退订请求时发生数据库错误
+unsubscribe-error-render-page = 退订页面渲染失败
+
+## 邮件
+
+email-footer = 此邮件由系统自动发送, 请勿直接回复。
+email-unsubscribe-link = 退订
+email-unsubscribe-text = 如需退订, 请访问:
+
+confirmation-subject = 请确认您的订阅
+confirmation-heading = { $name }, 欢迎订阅我们的新闻邮件
+confirmation-instruction = 请点击以下链接确认您对「{ $list }」的订阅：
+confirmation-link = 确认订阅
+
+welcome-subject = 订阅成功, 欢迎加入!
+welcome-heading = { $name }, 您的订阅已确认
+welcome-body = 感谢您的订阅, 之后发布的新闻邮件都会发送到这个邮箱。
+
+unsubscribe-confirmation-subject = 您已退订
+unsubscribe-confirmation-heading = { $name }, 您已成功退订
+unsubscribe-confirmation-body = 我们不会再向这个邮箱发送新闻邮件。感谢您一直以来的关注。
@@ -4,6 +4,29 @@ mod m20220101_000001_create_table;
 mod m20250921_232007_add_status_to_subscriptions;
 mod m20250921_232411_make_status_not_null_in_subscriptions;
 mod m20250921_232715_create_subscription_tokens_table;
+mod m20250922_090000_create_users_table;
+mod m20250923_080000_create_sessions_table;
+mod m20250925_210000_create_idempotency_table;
+mod m20250927_100000_create_newsletter_issues_and_delivery_queue;
+mod m20251001_090000_add_locale_to_subscriptions;
+mod m20251003_100000_add_expiry_to_subscription_tokens;
+mod m20251005_090000_add_status_check_to_subscriptions;
+mod m20251007_090000_create_rate_limit_buckets_table;
+mod m20251009_090000_cascade_subscription_tokens_on_delete;
+mod m20251011_090000_create_lists_and_memberships;
+mod m20251013_090000_add_schedule_to_newsletter_issues;
+mod m20251015_090000_create_email_deliveries_and_events;
+mod m20251017_090000_add_click_tracking;
+mod m20251019_090000_create_email_webhook_events;
+mod m20251021_090000_create_suppressions;
+mod m20251023_090000_add_canonical_email_to_subscriptions;
+mod m20251025_090000_add_status_changed_at_to_subscriptions;
+
+/// 迁移中冻结的规则快照，只供应用的测试确认它们与当前规则一致
+#[doc(hidden)]
+pub mod snapshots {
+    pub use crate::m20251023_090000_add_canonical_email_to_subscriptions::alias_key;
+}
 
 pub struct Migrator;
 
@@ -15,6 +38,23 @@ impl MigratorTrait for Migrator {
             Box::new(m20250921_232007_add_status_to_subscriptions::Migration),
             Box::new(m20250921_232411_make_status_not_null_in_subscriptions::Migration),
             Box::new(m20250921_232715_create_subscription_tokens_table::Migration),
+            Box::new(m20250922_090000_create_users_table::Migration),
+            Box::new(m20250923_080000_create_sessions_table::Migration),
+            Box::new(m20250925_210000_create_idempotency_table::Migration),
+            Box::new(m20250927_100000_create_newsletter_issues_and_delivery_queue::Migration),
+            Box::new(m20251001_090000_add_locale_to_subscriptions::Migration),
+            Box::new(m20251003_100000_add_expiry_to_subscription_tokens::Migration),
+            Box::new(m20251005_090000_add_status_check_to_subscriptions::Migration),
+            Box::new(m20251007_090000_create_rate_limit_buckets_table::Migration),
+            Box::new(m20251009_090000_cascade_subscription_tokens_on_delete::Migration),
+            Box::new(m20251011_090000_create_lists_and_memberships::Migration),
+            Box::new(m20251013_090000_add_schedule_to_newsletter_issues::Migration),
+            Box::new(m20251015_090000_create_email_deliveries_and_events::Migration),
+            Box::new(m20251017_090000_add_click_tracking::Migration),
+            Box::new(m20251019_090000_create_email_webhook_events::Migration),
+            Box::new(m20251021_090000_create_suppressions::Migration),
+            Box::new(m20251023_090000_add_canonical_email_to_subscriptions::Migration),
+            Box::new(m20251025_090000_add_status_changed_at_to_subscriptions::Migration),
         ]
     }
 }
@@ -0,0 +1,34 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            "
+                CREATE TABLE sessions (
+                    id TEXT PRIMARY KEY,
+                    data JSONB NOT NULL,
+                    expiry_date timestamptz NOT NULL
+                );
+                CREATE INDEX sessions_expiry_date_idx ON sessions (expiry_date);
+            ",
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            "
+                DROP TABLE sessions;
+            ",
+        )
+        .await?;
+        Ok(())
+    }
+}
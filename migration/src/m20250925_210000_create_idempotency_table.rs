@@ -0,0 +1,37 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            "
+                CREATE TABLE idempotency (
+                    user_id UUID NOT NULL REFERENCES users(user_id),
+                    idempotency_key TEXT NOT NULL,
+                    response_status_code SMALLINT NULL,
+                    response_headers JSONB NULL,
+                    response_body BYTEA NULL,
+                    created_at timestamptz NOT NULL,
+                    PRIMARY KEY (user_id, idempotency_key)
+                );
+            ",
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            "
+                DROP TABLE idempotency;
+            ",
+        )
+        .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,44 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            "
+                CREATE TABLE newsletter_issues (
+                    newsletter_issue_id UUID PRIMARY KEY,
+                    title TEXT NOT NULL,
+                    text_content TEXT NOT NULL,
+                    html_content TEXT NOT NULL,
+                    published_at timestamptz NOT NULL
+                );
+                CREATE TABLE issue_delivery_queue (
+                    newsletter_issue_id UUID NOT NULL
+                        REFERENCES newsletter_issues(newsletter_issue_id),
+                    subscriber_email TEXT NOT NULL,
+                    n_retries INT NOT NULL DEFAULT 0,
+                    execute_after timestamptz NOT NULL DEFAULT now(),
+                    PRIMARY KEY (newsletter_issue_id, subscriber_email)
+                );
+            ",
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            "
+                DROP TABLE issue_delivery_queue;
+                DROP TABLE newsletter_issues;
+            ",
+        )
+        .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,29 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            "
+                ALTER TABLE subscriptions ADD COLUMN locale TEXT NOT NULL DEFAULT 'zh-CN';
+            ",
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            "
+                ALTER TABLE subscriptions DROP COLUMN locale;
+            ",
+        )
+        .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,35 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        // 已有的令牌从迁移时起再保留一天
+        db.execute_unprepared(
+            "
+                ALTER TABLE subscription_tokens
+                    ADD COLUMN created_at timestamptz NOT NULL DEFAULT now(),
+                    ADD COLUMN expires_at timestamptz NOT NULL DEFAULT now() + interval '1 day';
+                ALTER TABLE subscription_tokens ALTER COLUMN expires_at DROP DEFAULT;
+            ",
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            "
+                ALTER TABLE subscription_tokens
+                    DROP COLUMN created_at,
+                    DROP COLUMN expires_at;
+            ",
+        )
+        .await?;
+        Ok(())
+    }
+}
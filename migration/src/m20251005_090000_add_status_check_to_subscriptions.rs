@@ -0,0 +1,32 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            "
+                ALTER TABLE subscriptions
+                    ADD CONSTRAINT subscriptions_status_check CHECK (
+                        status IN ('pending_confirmation', 'confirmed', 'unsubscribed', 'bounced', 'complained')
+                    );
+            ",
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            "
+                ALTER TABLE subscriptions DROP CONSTRAINT subscriptions_status_check;
+            ",
+        )
+        .await?;
+        Ok(())
+    }
+}
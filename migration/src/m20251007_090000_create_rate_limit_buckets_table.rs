@@ -0,0 +1,34 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            "
+                CREATE TABLE rate_limit_buckets (
+                    key TEXT NOT NULL,
+                    tokens DOUBLE PRECISION NOT NULL,
+                    updated_at timestamptz NOT NULL,
+                    PRIMARY KEY (key)
+                );
+            ",
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            "
+                DROP TABLE rate_limit_buckets;
+            ",
+        )
+        .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,36 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        // 删除订阅者时一并删除其确认令牌
+        db.execute_unprepared(
+            "
+                ALTER TABLE subscription_tokens
+                    DROP CONSTRAINT subscription_tokens_subscriber_id_fkey,
+                    ADD CONSTRAINT subscription_tokens_subscriber_id_fkey
+                        FOREIGN KEY (subscriber_id) REFERENCES subscriptions(id) ON DELETE CASCADE;
+            ",
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            "
+                ALTER TABLE subscription_tokens
+                    DROP CONSTRAINT subscription_tokens_subscriber_id_fkey,
+                    ADD CONSTRAINT subscription_tokens_subscriber_id_fkey
+                        FOREIGN KEY (subscriber_id) REFERENCES subscriptions(id);
+            ",
+        )
+        .await?;
+        Ok(())
+    }
+}
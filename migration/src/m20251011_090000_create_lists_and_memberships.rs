@@ -0,0 +1,68 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        // 已有的订阅者和令牌全部归入默认列表 `newsletter`，订阅状态沿用原来的状态
+        db.execute_unprepared(
+            "
+                CREATE TABLE lists (
+                    list_id UUID PRIMARY KEY,
+                    slug TEXT NOT NULL UNIQUE,
+                    name TEXT NOT NULL,
+                    created_at timestamptz NOT NULL DEFAULT now()
+                );
+                INSERT INTO lists (list_id, slug, name)
+                VALUES ('0b6f6a4c-5d1e-4c3a-9f7e-2a8d4e6b1c90', 'newsletter', 'Newsletter');
+
+                CREATE TABLE list_memberships (
+                    list_id UUID NOT NULL REFERENCES lists(list_id) ON DELETE CASCADE,
+                    subscriber_id UUID NOT NULL REFERENCES subscriptions(id) ON DELETE CASCADE,
+                    status TEXT NOT NULL CONSTRAINT list_memberships_status_check CHECK (
+                        status IN ('pending_confirmation', 'confirmed', 'unsubscribed', 'bounced', 'complained')
+                    ),
+                    created_at timestamptz NOT NULL DEFAULT now(),
+                    PRIMARY KEY (list_id, subscriber_id)
+                );
+                INSERT INTO list_memberships (list_id, subscriber_id, status, created_at)
+                SELECT '0b6f6a4c-5d1e-4c3a-9f7e-2a8d4e6b1c90', id, status, subscribed_at
+                FROM subscriptions;
+
+                ALTER TABLE subscription_tokens
+                    ADD COLUMN list_id UUID REFERENCES lists(list_id) ON DELETE CASCADE;
+                UPDATE subscription_tokens SET list_id = '0b6f6a4c-5d1e-4c3a-9f7e-2a8d4e6b1c90';
+                ALTER TABLE subscription_tokens ALTER COLUMN list_id SET NOT NULL;
+
+                CREATE TABLE newsletter_issue_lists (
+                    newsletter_issue_id UUID NOT NULL
+                        REFERENCES newsletter_issues(newsletter_issue_id) ON DELETE CASCADE,
+                    list_id UUID NOT NULL REFERENCES lists(list_id),
+                    PRIMARY KEY (newsletter_issue_id, list_id)
+                );
+                INSERT INTO newsletter_issue_lists (newsletter_issue_id, list_id)
+                SELECT newsletter_issue_id, '0b6f6a4c-5d1e-4c3a-9f7e-2a8d4e6b1c90'
+                FROM newsletter_issues;
+            ",
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            "
+                DROP TABLE newsletter_issue_lists;
+                ALTER TABLE subscription_tokens DROP COLUMN list_id;
+                DROP TABLE list_memberships;
+                DROP TABLE lists;
+            ",
+        )
+        .await?;
+        Ok(())
+    }
+}
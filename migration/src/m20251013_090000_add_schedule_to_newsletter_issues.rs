@@ -0,0 +1,41 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        // 已有的新闻邮件都已经创建了投递任务
+        db.execute_unprepared(
+            "
+                ALTER TABLE newsletter_issues
+                    ADD COLUMN status TEXT NOT NULL DEFAULT 'enqueued'
+                        CONSTRAINT newsletter_issues_status_check CHECK (
+                            status IN ('scheduled', 'enqueued', 'cancelled')
+                        ),
+                    ADD COLUMN scheduled_at timestamptz;
+                ALTER TABLE newsletter_issues ALTER COLUMN status DROP DEFAULT;
+                CREATE INDEX newsletter_issues_due_idx
+                    ON newsletter_issues (scheduled_at) WHERE status = 'scheduled';
+            ",
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            "
+                DROP INDEX newsletter_issues_due_idx;
+                ALTER TABLE newsletter_issues
+                    DROP COLUMN status,
+                    DROP COLUMN scheduled_at;
+            ",
+        )
+        .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,52 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        // 已有的新闻邮件发送时没有跟踪像素
+        db.execute_unprepared(
+            "
+                ALTER TABLE newsletter_issues ADD COLUMN track_opens BOOLEAN NOT NULL DEFAULT FALSE;
+                ALTER TABLE newsletter_issues ALTER COLUMN track_opens DROP DEFAULT;
+
+                CREATE TABLE email_deliveries (
+                    delivery_id UUID PRIMARY KEY,
+                    newsletter_issue_id UUID NOT NULL
+                        REFERENCES newsletter_issues(newsletter_issue_id) ON DELETE CASCADE,
+                    subscriber_id UUID NOT NULL REFERENCES subscriptions(id) ON DELETE CASCADE,
+                    sent_at timestamptz NOT NULL
+                );
+                CREATE INDEX email_deliveries_issue_idx ON email_deliveries (newsletter_issue_id);
+
+                CREATE TABLE email_events (
+                    event_id BIGSERIAL PRIMARY KEY,
+                    delivery_id UUID NOT NULL
+                        REFERENCES email_deliveries(delivery_id) ON DELETE CASCADE,
+                    kind TEXT NOT NULL CONSTRAINT email_events_kind_check CHECK (kind IN ('open')),
+                    occurred_at timestamptz NOT NULL DEFAULT now()
+                );
+                CREATE UNIQUE INDEX email_events_one_open_per_delivery_idx
+                    ON email_events (delivery_id) WHERE kind = 'open';
+            ",
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            "
+                DROP TABLE email_events;
+                DROP TABLE email_deliveries;
+                ALTER TABLE newsletter_issues DROP COLUMN track_opens;
+            ",
+        )
+        .await?;
+        Ok(())
+    }
+}
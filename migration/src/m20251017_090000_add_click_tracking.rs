@@ -0,0 +1,43 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        // 点击事件记录被点击的原始链接，打开事件没有链接
+        db.execute_unprepared(
+            "
+                ALTER TABLE newsletter_issues ADD COLUMN track_clicks BOOLEAN NOT NULL DEFAULT FALSE;
+                ALTER TABLE newsletter_issues ALTER COLUMN track_clicks DROP DEFAULT;
+
+                ALTER TABLE email_events
+                    DROP CONSTRAINT email_events_kind_check,
+                    ADD CONSTRAINT email_events_kind_check CHECK (kind IN ('open', 'click')),
+                    ADD COLUMN url TEXT,
+                    ADD CONSTRAINT email_events_url_check CHECK ((kind = 'click') = (url IS NOT NULL));
+            ",
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            "
+                DELETE FROM email_events WHERE kind = 'click';
+                ALTER TABLE email_events
+                    DROP CONSTRAINT email_events_url_check,
+                    DROP COLUMN url,
+                    DROP CONSTRAINT email_events_kind_check,
+                    ADD CONSTRAINT email_events_kind_check CHECK (kind IN ('open'));
+                ALTER TABLE newsletter_issues DROP COLUMN track_clicks;
+            ",
+        )
+        .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,38 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        // 原样保存邮件服务商推送的退信和投诉事件，删除订阅者后事件仍然保留
+        db.execute_unprepared(
+            "
+                CREATE TABLE email_webhook_events (
+                    event_id BIGSERIAL PRIMARY KEY,
+                    subscriber_id UUID REFERENCES subscriptions(id) ON DELETE SET NULL,
+                    email TEXT NOT NULL,
+                    kind TEXT NOT NULL CONSTRAINT email_webhook_events_kind_check CHECK (
+                        kind IN ('hard_bounce', 'soft_bounce', 'complaint', 'other')
+                    ),
+                    status_changed BOOLEAN NOT NULL,
+                    payload JSONB NOT NULL,
+                    received_at timestamptz NOT NULL DEFAULT now()
+                );
+                CREATE INDEX email_webhook_events_subscriber_idx
+                    ON email_webhook_events (subscriber_id, event_id);
+            ",
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        db.execute_unprepared("DROP TABLE email_webhook_events;")
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,37 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        // 已经投诉过的地址直接加入抑制列表
+        db.execute_unprepared(
+            "
+                CREATE TABLE suppressions (
+                    email TEXT PRIMARY KEY,
+                    reason TEXT NOT NULL,
+                    source TEXT NOT NULL CONSTRAINT suppressions_source_check CHECK (
+                        source IN ('admin', 'webhook')
+                    ),
+                    created_at timestamptz NOT NULL DEFAULT now()
+                );
+                INSERT INTO suppressions (email, reason, source)
+                SELECT DISTINCT lower(btrim(email)), 'complaint', 'webhook'
+                FROM subscriptions
+                WHERE status = 'complained'
+                ON CONFLICT DO NOTHING;
+            ",
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        db.execute_unprepared("DROP TABLE suppressions;").await?;
+        Ok(())
+    }
+}
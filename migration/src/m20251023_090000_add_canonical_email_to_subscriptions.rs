@@ -0,0 +1,224 @@
+use sea_orm_migration::{
+    prelude::*,
+    sea_orm::{ConnectionTrait, DbBackend, Statement},
+};
+
+/// 每条 UPDATE 语句写回的行数
+const UPDATE_BATCH_SIZE: usize = 1000;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        // 原来的校验只允许 ASCII 本地部分且不含空白，因此只需要把域名转为小写；
+        // 唯一约束改为建在规范形式上，`email` 本身不再需要唯一
+        db.execute_unprepared(
+            "
+                ALTER TABLE subscriptions
+                    DROP CONSTRAINT subscriptions_email_key,
+                    ADD COLUMN canonical_email TEXT,
+                    ADD COLUMN alias_key TEXT;
+                UPDATE subscriptions
+                SET email = split_part(email, '@', 1) || '@' || lower(split_part(email, '@', 2)),
+                    canonical_email = lower(email);
+            ",
+        )
+        .await?;
+
+        // 国际化域名需要转换为 punycode，这一步无法在 SQL 中完成
+        let rows = db
+            .query_all(Statement::from_string(
+                DbBackend::Postgres,
+                "SELECT id::text AS id, email FROM subscriptions WHERE octet_length(email) <> char_length(email)",
+            ))
+            .await?;
+        for row in rows {
+            let id: String = row.try_get("", "id")?;
+            let email: String = row.try_get("", "email")?;
+            let Some((local, domain)) = email.rsplit_once('@') else {
+                continue;
+            };
+            let Ok(domain) = idna::domain_to_ascii(domain) else {
+                continue;
+            };
+            db.execute(Statement::from_sql_and_values(
+                DbBackend::Postgres,
+                "UPDATE subscriptions SET email = $2, canonical_email = $3 WHERE id = $1::uuid",
+                [
+                    id.into(),
+                    format!("{}@{}", local, domain).into(),
+                    format!("{}@{}", local.to_lowercase(), domain).into(),
+                ],
+            ))
+            .await?;
+        }
+
+        // 同一个规范地址只保留一个订阅者：优先保留已投诉、已退信或已退订的记录，避免再次向其发送邮件，
+        // 其次保留最早订阅的记录；其余记录的列表成员关系、令牌和投递记录合并到保留的记录上。
+        // 保留的记录已经停止投递时，合并过来的列表成员关系也使用它的状态
+        db.execute_unprepared(
+            "
+                CREATE TEMPORARY TABLE duplicate_subscriptions AS
+                SELECT id, survivor_id
+                FROM (
+                    SELECT id, first_value(id) OVER (
+                        PARTITION BY canonical_email
+                        ORDER BY
+                            CASE status
+                                WHEN 'complained' THEN 0
+                                WHEN 'bounced' THEN 1
+                                WHEN 'unsubscribed' THEN 2
+                                WHEN 'confirmed' THEN 3
+                                ELSE 4
+                            END,
+                            subscribed_at,
+                            id
+                    ) AS survivor_id
+                    FROM subscriptions
+                ) ranked
+                WHERE id <> survivor_id;
+
+                INSERT INTO list_memberships (list_id, subscriber_id, status, created_at)
+                SELECT
+                    m.list_id,
+                    d.survivor_id,
+                    CASE
+                        WHEN s.status IN ('pending_confirmation', 'confirmed') THEN m.status
+                        ELSE s.status
+                    END,
+                    m.created_at
+                FROM list_memberships m
+                JOIN duplicate_subscriptions d ON d.id = m.subscriber_id
+                JOIN subscriptions s ON s.id = d.survivor_id
+                ON CONFLICT DO NOTHING;
+                UPDATE subscription_tokens t SET subscriber_id = d.survivor_id
+                FROM duplicate_subscriptions d WHERE t.subscriber_id = d.id;
+                UPDATE email_deliveries e SET subscriber_id = d.survivor_id
+                FROM duplicate_subscriptions d WHERE e.subscriber_id = d.id;
+                UPDATE email_webhook_events e SET subscriber_id = d.survivor_id
+                FROM duplicate_subscriptions d WHERE e.subscriber_id = d.id;
+                DELETE FROM subscriptions s USING duplicate_subscriptions d WHERE s.id = d.id;
+                DROP TABLE duplicate_subscriptions;
+
+                DELETE FROM issue_delivery_queue q
+                USING issue_delivery_queue o
+                WHERE q.newsletter_issue_id = o.newsletter_issue_id
+                    AND lower(q.subscriber_email) = lower(o.subscriber_email)
+                    AND q.subscriber_email > o.subscriber_email;
+            ",
+        )
+        .await?;
+
+        // 别名键在 Rust 中计算，每批用一条 UPDATE ... FROM (VALUES ...) 写回
+        let rows = db
+            .query_all(Statement::from_string(
+                DbBackend::Postgres,
+                "SELECT id::text AS id, canonical_email FROM subscriptions",
+            ))
+            .await?;
+        let alias_keys = rows
+            .iter()
+            .map(|row| {
+                let id: String = row.try_get("", "id")?;
+                let canonical_email: String = row.try_get("", "canonical_email")?;
+                Ok((id, alias_key(&canonical_email)))
+            })
+            .collect::<Result<Vec<_>, DbErr>>()?;
+        for batch in alias_keys.chunks(UPDATE_BATCH_SIZE) {
+            let values = (0..batch.len())
+                .map(|i| format!("(${}::uuid, ${})", 2 * i + 1, 2 * i + 2))
+                .collect::<Vec<_>>()
+                .join(", ");
+            db.execute(Statement::from_sql_and_values(
+                DbBackend::Postgres,
+                format!(
+                    "
+                        UPDATE subscriptions s SET alias_key = v.alias_key
+                        FROM (VALUES {}) AS v (id, alias_key)
+                        WHERE s.id = v.id
+                    ",
+                    values
+                ),
+                batch
+                    .iter()
+                    .flat_map(|(id, alias_key)| [id.as_str().into(), alias_key.as_str().into()]),
+            ))
+            .await?;
+        }
+
+        db.execute_unprepared(
+            "
+                ALTER TABLE subscriptions
+                    ALTER COLUMN canonical_email SET NOT NULL,
+                    ALTER COLUMN alias_key SET NOT NULL;
+                CREATE UNIQUE INDEX subscriptions_canonical_email_idx ON subscriptions (canonical_email);
+                CREATE INDEX subscriptions_alias_key_idx ON subscriptions (alias_key);
+            ",
+        )
+        .await?;
+
+        // 抑制列表按同样的规范形式存储
+        let rows = db
+            .query_all(Statement::from_string(
+                DbBackend::Postgres,
+                "SELECT email FROM suppressions WHERE octet_length(email) <> char_length(email)",
+            ))
+            .await?;
+        for row in rows {
+            let email: String = row.try_get("", "email")?;
+            let Some((local, domain)) = email.rsplit_once('@') else {
+                continue;
+            };
+            let Ok(domain) = idna::domain_to_ascii(domain) else {
+                continue;
+            };
+            db.execute(Statement::from_sql_and_values(
+                DbBackend::Postgres,
+                "
+                    WITH removed AS (DELETE FROM suppressions WHERE email = $1 RETURNING *)
+                    INSERT INTO suppressions (email, reason, source, created_at)
+                    SELECT $2, reason, source, created_at FROM removed
+                    ON CONFLICT DO NOTHING
+                ",
+                [email.clone().into(), format!("{}@{}", local, domain).into()],
+            ))
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            "
+                DROP INDEX subscriptions_alias_key_idx;
+                DROP INDEX subscriptions_canonical_email_idx;
+                ALTER TABLE subscriptions
+                    DROP COLUMN alias_key,
+                    DROP COLUMN canonical_email,
+                    ADD CONSTRAINT subscriptions_email_key UNIQUE (email);
+            ",
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+/// 编写迁移时应用中 `email_alias_key` 规则的快照：去掉本地部分中 `+` 之后的标签；
+/// Gmail 地址还会去掉本地部分中的点，并统一使用 `gmail.com`
+///
+/// 迁移不依赖应用 crate，并且发布后的迁移结果不能随应用代码变化，因此这里保留一份冻结的副本，
+/// 应用中的测试保证它与当前的 `email_alias_key` 一致。规则以后改变时应新增迁移重新计算，而不是修改这里
+pub fn alias_key(canonical_email: &str) -> String {
+    let Some((local, domain)) = canonical_email.rsplit_once('@') else {
+        return canonical_email.to_string();
+    };
+    let local = local.split('+').next().unwrap_or_default();
+    match domain {
+        "gmail.com" | "googlemail.com" => format!("{}@gmail.com", local.replace('.', "")),
+        _ => format!("{}@{}", local, domain),
+    }
+}
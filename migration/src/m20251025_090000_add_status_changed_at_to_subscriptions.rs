@@ -0,0 +1,56 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        // 状态变化的时间由触发器维护，任何修改状态的语句都会更新它；
+        // 已有订阅者取最近一次由邮件事件引起的状态变化时间，没有时取订阅时间
+        db.execute_unprepared(
+            "
+                ALTER TABLE subscriptions ADD COLUMN status_changed_at timestamptz;
+                UPDATE subscriptions s SET status_changed_at = COALESCE(
+                    (
+                        SELECT max(e.received_at)
+                        FROM email_webhook_events e
+                        WHERE e.subscriber_id = s.id AND e.status_changed
+                    ),
+                    s.subscribed_at
+                );
+                ALTER TABLE subscriptions
+                    ALTER COLUMN status_changed_at SET NOT NULL,
+                    ALTER COLUMN status_changed_at SET DEFAULT now();
+
+                CREATE FUNCTION subscriptions_touch_status_changed_at() RETURNS trigger AS $$
+                BEGIN
+                    NEW.status_changed_at := now();
+                    RETURN NEW;
+                END;
+                $$ LANGUAGE plpgsql;
+                CREATE TRIGGER subscriptions_status_changed_at
+                    BEFORE UPDATE OF status ON subscriptions
+                    FOR EACH ROW
+                    WHEN (OLD.status IS DISTINCT FROM NEW.status)
+                    EXECUTE FUNCTION subscriptions_touch_status_changed_at();
+            ",
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            "
+                DROP TRIGGER subscriptions_status_changed_at ON subscriptions;
+                DROP FUNCTION subscriptions_touch_status_changed_at();
+                ALTER TABLE subscriptions DROP COLUMN status_changed_at;
+            ",
+        )
+        .await?;
+        Ok(())
+    }
+}
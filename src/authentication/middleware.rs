@@ -0,0 +1,43 @@
+use std::ops::Deref;
+
+use axum::{
+    extract::Request,
+    middleware::Next,
+    response::{IntoResponse, Redirect, Response},
+};
+
+use crate::{session_state::TypedSession, utils::e500};
+
+/// 已登录管理员的用户 ID，由 [`reject_anonymous_users`] 写入请求扩展
+#[derive(Copy, Clone, Debug)]
+pub struct UserId(uuid::Uuid);
+
+impl std::fmt::Display for UserId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl Deref for UserId {
+    type Target = uuid::Uuid;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/// 拒绝未登录的请求，并将其重定向到登录页
+pub async fn reject_anonymous_users(
+    session: TypedSession,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    match session.get_user_id().await {
+        Ok(Some(user_id)) => {
+            request.extensions_mut().insert(UserId(user_id));
+            next.run(request).await
+        }
+        Ok(None) => Redirect::to("/login").into_response(),
+        Err(e) => e500(e),
+    }
+}
@@ -0,0 +1,8 @@
+mod middleware;
+mod password;
+
+pub use middleware::{UserId, reject_anonymous_users};
+pub use password::{
+    AuthError, CreateUserError, Credentials, compute_password_hash, create_user,
+    validate_credentials,
+};
@@ -0,0 +1,229 @@
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+
+use argon2::{
+    Algorithm, Argon2, Params, PasswordHash, PasswordHasher, PasswordVerifier, Version,
+    password_hash::{SaltString, rand_core::OsRng},
+};
+use sea_orm::{
+    ActiveValue::Set, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter,
+    sea_query::OnConflict,
+};
+use secrecy::{ExposeSecret, SecretString};
+
+use crate::{entities::users, routes::error_chain_fmt, telemetry::spawn_blocking_with_tracing};
+
+pub struct Credentials {
+    pub username: String,
+    pub password: SecretString,
+}
+
+/// 校验用户名和密码，成功时返回用户 ID
+///
+/// 用户不存在时也会对一个假的哈希值做一次校验，避免通过响应耗时来探测用户名是否存在
+#[tracing::instrument(name = "校验用户凭证", skip(credentials, db))]
+pub async fn validate_credentials(
+    credentials: Credentials,
+    db: &DatabaseConnection,
+) -> Result<uuid::Uuid, AuthError> {
+    let mut user_id = None;
+    let mut expected_password_hash = SecretString::from(
+        "$argon2id$v=19$m=15000,t=2,p=1$\
+        gZiV/M1gPc22ElAH/Jh1Hw$\
+        CWOrkoo7oJBQ/iyh7uJ0LO2aLEfrHwTWllSAxT0zRno",
+    );
+
+    if let Some((stored_user_id, stored_password_hash)) =
+        get_stored_credentials(&credentials.username, db)
+            .await
+            .map_err(AuthError::QueryError)?
+    {
+        user_id = Some(stored_user_id);
+        expected_password_hash = stored_password_hash;
+    }
+
+    spawn_blocking_with_tracing(move || {
+        verify_password_hash(expected_password_hash, credentials.password)
+    })
+    .await
+    .map_err(AuthError::JoinError)??;
+
+    user_id.ok_or(AuthError::InvalidCredentials)
+}
+
+#[tracing::instrument(name = "校验密码哈希", skip(expected_password_hash, password_candidate))]
+fn verify_password_hash(
+    expected_password_hash: SecretString,
+    password_candidate: SecretString,
+) -> Result<(), AuthError> {
+    let expected_password_hash =
+        PasswordHash::new(expected_password_hash.expose_secret()).map_err(AuthError::HashError)?;
+
+    Argon2::default()
+        .verify_password(
+            password_candidate.expose_secret().as_bytes(),
+            &expected_password_hash,
+        )
+        .map_err(|e| match e {
+            argon2::password_hash::Error::Password => AuthError::InvalidCredentials,
+            other => AuthError::HashError(other),
+        })
+}
+
+#[tracing::instrument(name = "获取存储的用户凭证", skip(username, db))]
+async fn get_stored_credentials(
+    username: &str,
+    db: &DatabaseConnection,
+) -> Result<Option<(uuid::Uuid, SecretString)>, DbErr> {
+    let user = users::Entity::find()
+        .filter(users::Column::Username.eq(username))
+        .one(db)
+        .await?;
+
+    Ok(user.map(|u| (u.user_id, SecretString::from(u.password_hash))))
+}
+
+/// 使用 Argon2id 计算 PHC 格式的密码哈希
+pub fn compute_password_hash(
+    password: SecretString,
+) -> Result<SecretString, argon2::password_hash::Error> {
+    let salt = SaltString::generate(&mut OsRng);
+    let password_hash = Argon2::new(
+        Algorithm::Argon2id,
+        Version::V0x13,
+        Params::new(15000, 2, 1, None)?,
+    )
+    .hash_password(password.expose_secret().as_bytes(), &salt)?
+    .to_string();
+
+    Ok(SecretString::from(password_hash))
+}
+
+/// 创建一个新用户，用户名已被占用时返回 [`CreateUserError::UsernameTaken`]
+#[tracing::instrument(name = "创建用户", skip(credentials, db), fields(username = %credentials.username))]
+pub async fn create_user(
+    credentials: Credentials,
+    db: &DatabaseConnection,
+) -> Result<uuid::Uuid, CreateUserError> {
+    let password = credentials.password;
+    let password_hash = spawn_blocking_with_tracing(move || compute_password_hash(password))
+        .await
+        .map_err(CreateUserError::JoinError)?
+        .map_err(CreateUserError::HashError)?;
+
+    let user_id = uuid::Uuid::new_v4();
+    let rows_affected = users::Entity::insert(users::ActiveModel {
+        user_id: Set(user_id),
+        username: Set(credentials.username),
+        password_hash: Set(password_hash.expose_secret().to_string()),
+    })
+    .on_conflict(
+        OnConflict::column(users::Column::Username)
+            .do_nothing()
+            .to_owned(),
+    )
+    .exec_without_returning(db)
+    .await
+    .map_err(CreateUserError::QueryError)?;
+    if rows_affected == 0 {
+        return Err(CreateUserError::UsernameTaken);
+    }
+    Ok(user_id)
+}
+
+pub enum AuthError {
+    InvalidCredentials,
+    QueryError(DbErr),
+    HashError(argon2::password_hash::Error),
+    JoinError(tokio::task::JoinError),
+}
+
+impl Display for AuthError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AuthError::InvalidCredentials => write!(f, "用户名或密码错误"),
+            AuthError::QueryError(_) => write!(f, "查询用户凭证失败"),
+            AuthError::HashError(_) => write!(f, "密码哈希校验失败"),
+            AuthError::JoinError(_) => write!(f, "密码校验任务执行失败"),
+        }
+    }
+}
+
+impl Debug for AuthError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl Error for AuthError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            AuthError::InvalidCredentials => None,
+            AuthError::QueryError(e) => Some(e),
+            AuthError::HashError(e) => Some(e),
+            AuthError::JoinError(e) => Some(e),
+        }
+    }
+}
+
+pub enum CreateUserError {
+    UsernameTaken,
+    QueryError(DbErr),
+    HashError(argon2::password_hash::Error),
+    JoinError(tokio::task::JoinError),
+}
+
+impl Display for CreateUserError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CreateUserError::UsernameTaken => write!(f, "用户名已存在"),
+            CreateUserError::QueryError(_) => write!(f, "保存用户失败"),
+            CreateUserError::HashError(_) => write!(f, "计算密码哈希失败"),
+            CreateUserError::JoinError(_) => write!(f, "密码哈希任务执行失败"),
+        }
+    }
+}
+
+impl Debug for CreateUserError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl Error for CreateUserError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CreateUserError::UsernameTaken => None,
+            CreateUserError::QueryError(e) => Some(e),
+            CreateUserError::HashError(e) => Some(e),
+            CreateUserError::JoinError(e) => Some(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claim::{assert_err, assert_ok};
+    use secrecy::SecretString;
+
+    use super::{compute_password_hash, verify_password_hash};
+
+    #[test]
+    fn a_hash_is_stored_in_phc_format() {
+        let hash = compute_password_hash(SecretString::from("password")).unwrap();
+        let hash = secrecy::ExposeSecret::expose_secret(&hash).to_string();
+        assert!(hash.starts_with("$argon2id$v=19$m=15000,t=2,p=1$"));
+    }
+
+    #[test]
+    fn the_right_password_is_accepted() {
+        let hash = compute_password_hash(SecretString::from("password")).unwrap();
+        assert_ok!(verify_password_hash(hash, SecretString::from("password")));
+    }
+
+    #[test]
+    fn a_wrong_password_is_rejected() {
+        let hash = compute_password_hash(SecretString::from("password")).unwrap();
+        assert_err!(verify_password_hash(hash, SecretString::from("wrong")));
+    }
+}
@@ -1,27 +1,188 @@
+use std::sync::Arc;
 use std::time::Duration;
 
-use sea_orm::ConnectOptions;
-use secrecy::{ExposeSecret, SecretBox};
+use sea_orm::{ConnectOptions, DatabaseConnection};
+use secrecy::{ExposeSecret, SecretBox, SecretString};
+use serde::Deserialize as _;
+use serde::de::{Deserializer, Error as _};
 use serde_aux::field_attributes::deserialize_number_from_string;
 
-#[derive(serde::Deserialize)]
+use crate::{
+    email_client::{
+        EmailClient, EmailTransport, FileEmailTransport, HttpApiEmailTransport,
+        InMemoryEmailTransport, SmtpEmailTransport,
+    },
+    rate_limit::{
+        InMemoryRateLimitStore, PostgresRateLimitStore, RateLimit, RateLimitStore, RateLimiter,
+    },
+};
+
+#[derive(serde::Deserialize, Clone)]
 pub struct Settings {
     pub database: DatabaseSettings,
     pub application: ApplicationSettings,
     pub email_client: EmailClientSettings,
+    pub templates: TemplateSettings,
+    pub rate_limit: RateLimitSettings,
+    pub tracking: TrackingSettings,
+    pub email_events_webhook: EmailEventsWebhookSettings,
 }
 
-#[derive(serde::Deserialize)]
+#[derive(serde::Deserialize, Clone)]
 pub struct EmailClientSettings {
+    #[serde(default)]
+    pub kind: EmailTransportKind,
+    pub sender_email: String,
     pub base_url: String,
     pub smtp_password: String,
     pub smtp_username: String,
+    /// 单次 SMTP 操作（连接、读写）的超时时间
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub smtp_timeout_milliseconds: u64,
+    /// SMTP 连接池中保持的最大连接数
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub smtp_pool_max_size: u32,
+    /// `kind` 为 `http_api` 时随请求发送的认证令牌
+    pub api_authorization_token: SecretString,
+    /// HTTP API 请求的超时时间
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub api_timeout_milliseconds: u64,
+    /// `kind` 为 `file` 时邮件写入的 maildir 目录
+    pub file_directory: Option<String>,
+}
+
+#[derive(serde::Deserialize, Clone)]
+pub struct TemplateSettings {
+    /// 默认邮件模板所在目录
+    pub directory: String,
+    /// 可选的覆盖目录，其中的同名模板会替换默认模板
+    pub override_directory: Option<String>,
+}
+
+/// 邮件发送方式
+#[derive(serde::Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EmailTransportKind {
+    #[default]
+    Smtp,
+    HttpApi,
+    InMemory,
+    File,
+}
+
+impl EmailClientSettings {
+    /// 按 `kind` 创建邮件客户端，SMTP 服务器地址无效时返回错误
+    pub fn client(self) -> Result<EmailClient, lettre::transport::smtp::Error> {
+        let transport: Arc<dyn EmailTransport> = match self.kind {
+            EmailTransportKind::Smtp => Arc::new(SmtpEmailTransport::new(
+                self.smtp_username,
+                SecretString::from(self.smtp_password),
+                &self.base_url,
+                Duration::from_millis(self.smtp_timeout_milliseconds),
+                self.smtp_pool_max_size,
+            )?),
+            EmailTransportKind::HttpApi => Arc::new(HttpApiEmailTransport::new(
+                self.base_url,
+                self.api_authorization_token,
+                Duration::from_millis(self.api_timeout_milliseconds),
+            )),
+            EmailTransportKind::InMemory => Arc::new(InMemoryEmailTransport::default()),
+            EmailTransportKind::File => Arc::new(FileEmailTransport::new(
+                self.file_directory.unwrap_or_else(|| "mail".into()),
+            )),
+        };
+        Ok(EmailClient::new(self.sender_email, transport))
+    }
+}
+
+#[derive(serde::Deserialize, Clone)]
+pub struct RateLimitSettings {
+    #[serde(default)]
+    pub kind: RateLimitStoreKind,
+    /// 同一个客户端 IP 最多连续请求的次数
+    #[serde(deserialize_with = "deserialize_positive_number_from_string")]
+    pub per_ip_capacity: u32,
+    /// 客户端 IP 的请求次数在多长时间内恢复
+    #[serde(deserialize_with = "deserialize_positive_number_from_string")]
+    pub per_ip_period_seconds: u64,
+    /// 同一个邮箱在一个周期内最多收到的确认邮件数量
+    #[serde(deserialize_with = "deserialize_positive_number_from_string")]
+    pub per_email_capacity: u32,
+    #[serde(deserialize_with = "deserialize_positive_number_from_string")]
+    pub per_email_period_seconds: u64,
+    /// 部署在反向代理之后时使用 `X-Forwarded-For` 的最后一项识别客户端 IP
+    #[serde(default)]
+    pub trust_forwarded_for: bool,
+}
+
+/// 与 `deserialize_number_from_string` 相同，但拒绝 0：
+/// 容量为 0 的令牌桶永远取不到令牌，周期为 0 时补充速率无法计算
+fn deserialize_positive_number_from_string<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: std::str::FromStr + serde::Deserialize<'de> + Default + PartialEq,
+    <T as std::str::FromStr>::Err: std::fmt::Display,
+{
+    let value: T = deserialize_number_from_string(deserializer)?;
+    if value == T::default() {
+        return Err(D::Error::custom("限流的容量和周期必须大于 0"));
+    }
+    Ok(value)
+}
+
+/// 限流状态的存储方式
+#[derive(serde::Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RateLimitStoreKind {
+    #[default]
+    InMemory,
+    Postgres,
+}
+
+impl RateLimitSettings {
+    pub fn limiter(self, db: DatabaseConnection) -> RateLimiter {
+        let store: Arc<dyn RateLimitStore> = match self.kind {
+            RateLimitStoreKind::InMemory => Arc::new(InMemoryRateLimitStore::default()),
+            RateLimitStoreKind::Postgres => Arc::new(PostgresRateLimitStore::new(db)),
+        };
+        RateLimiter::new(
+            store,
+            RateLimit {
+                capacity: self.per_ip_capacity,
+                period: Duration::from_secs(self.per_ip_period_seconds),
+            },
+            RateLimit {
+                capacity: self.per_email_capacity,
+                period: Duration::from_secs(self.per_email_period_seconds),
+            },
+            self.trust_forwarded_for,
+        )
+    }
+}
+
+/// 邮件跟踪的全局开关，关闭后所有新闻邮件都不跟踪，不论发布时如何选择
+#[derive(serde::Deserialize, Clone, Copy, Debug)]
+pub struct TrackingSettings {
+    /// 在新闻邮件中注入打开跟踪像素并记录打开事件
+    pub open_tracking_enabled: bool,
+    /// 把新闻邮件 HTML 正文中的链接改写为跳转链接并记录点击事件
+    pub click_tracking_enabled: bool,
+}
+
+/// 邮件服务商推送退信和投诉事件的 webhook
+#[derive(serde::Deserialize, Clone)]
+pub struct EmailEventsWebhookSettings {
+    /// 与服务商共享的密钥，请求体的 HMAC-SHA256 签名放在 `X-Webhook-Signature` 请求头中
+    pub secret: SecretString,
+    /// 自订阅者最近一次状态变化以来累计收到多少次软退信后把订阅者标记为退信
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub soft_bounce_threshold: u32,
 }
 
-#[derive(serde::Deserialize)]
+#[derive(serde::Deserialize, Clone)]
 pub struct DatabaseSettings {
     pub username: String,
-    pub password: SecretBox<String>,
+    pub password: SecretString,
     #[serde(deserialize_with = "deserialize_number_from_string")]
     pub port: u16,
     pub host: String,
@@ -29,12 +190,43 @@ pub struct DatabaseSettings {
     pub require_ssl: bool,
 }
 
-#[derive(serde::Deserialize)]
+#[derive(serde::Deserialize, Clone)]
 pub struct ApplicationSettings {
     #[serde(deserialize_with = "deserialize_number_from_string")]
     pub port: u16,
     pub host: String,
     pub base_url: String,
+    /// 用于签名并加密会话 cookie、签名退订令牌的密钥，至少 64 字节
+    #[serde(deserialize_with = "deserialize_hmac_secret")]
+    pub hmac_secret: SecretString,
+    /// 订阅确认令牌的有效期
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub subscription_token_ttl_seconds: u64,
+    /// 检查计划发送的新闻邮件是否到期的间隔
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub newsletter_scheduler_interval_seconds: u64,
+}
+
+/// 会话 cookie 的密钥至少需要 64 字节，在加载配置时拒绝过短的密钥，而不是等到启动服务时才失败
+fn deserialize_hmac_secret<'de, D>(deserializer: D) -> Result<SecretString, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let secret = SecretString::deserialize(deserializer)?;
+    if secret.expose_secret().len() < 64 {
+        return Err(D::Error::custom("hmac_secret 至少需要 64 字节"));
+    }
+    Ok(secret)
+}
+
+impl ApplicationSettings {
+    pub fn subscription_token_ttl(&self) -> Duration {
+        Duration::from_secs(self.subscription_token_ttl_seconds)
+    }
+
+    pub fn newsletter_scheduler_interval(&self) -> Duration {
+        Duration::from_secs(self.newsletter_scheduler_interval_seconds)
+    }
 }
 
 pub fn get_configuration() -> Result<Settings, config::ConfigError> {
@@ -152,3 +344,58 @@ impl DatabaseSettings {
         format!("{}?sslmode={}", str, ssl_mode)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{ApplicationSettings, RateLimitSettings};
+
+    fn rate_limit_settings(capacity: &str, period_seconds: &str) -> serde_json::Value {
+        serde_json::json!({
+            "per_ip_capacity": capacity,
+            "per_ip_period_seconds": period_seconds,
+            "per_email_capacity": 3,
+            "per_email_period_seconds": 3600,
+        })
+    }
+
+    #[test]
+    fn valid_rate_limits_are_accepted() {
+        let settings = rate_limit_settings("10", "60");
+        assert!(serde_json::from_value::<RateLimitSettings>(settings).is_ok());
+    }
+
+    #[test]
+    fn a_zero_capacity_is_rejected() {
+        let settings = rate_limit_settings("0", "60");
+        assert!(serde_json::from_value::<RateLimitSettings>(settings).is_err());
+    }
+
+    #[test]
+    fn a_zero_period_is_rejected() {
+        let settings = rate_limit_settings("10", "0");
+        assert!(serde_json::from_value::<RateLimitSettings>(settings).is_err());
+    }
+
+    fn application_settings(hmac_secret: &str) -> serde_json::Value {
+        serde_json::json!({
+            "port": 8000,
+            "host": "127.0.0.1",
+            "base_url": "http://127.0.0.1",
+            "hmac_secret": hmac_secret,
+            "subscription_token_ttl_seconds": 86400,
+            "newsletter_scheduler_interval_seconds": 60,
+        })
+    }
+
+    #[test]
+    fn a_64_byte_hmac_secret_is_accepted() {
+        let settings = application_settings(&"a".repeat(64));
+        assert!(serde_json::from_value::<ApplicationSettings>(settings).is_ok());
+    }
+
+    #[test]
+    fn a_short_hmac_secret_is_rejected() {
+        let settings = application_settings(&"a".repeat(63));
+        assert!(serde_json::from_value::<ApplicationSettings>(settings).is_err());
+    }
+}
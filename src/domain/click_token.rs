@@ -0,0 +1,109 @@
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use hmac::{Hmac, Mac};
+use secrecy::{ExposeSecret, SecretString};
+use sha2::Sha256;
+
+/// 点击跟踪令牌，格式为 `<投递 ID>.<base64url 编码的目标地址>.<HMAC-SHA256 签名>`
+///
+/// 目标地址由签名保护，篡改过的令牌无法通过验证，跳转接口因此不会成为开放重定向
+#[derive(Debug)]
+pub struct ClickToken(String);
+
+impl ClickToken {
+    pub fn generate(delivery_id: uuid::Uuid, url: &str, secret: &SecretString) -> Self {
+        let payload = format!("{}.{}", delivery_id.simple(), URL_SAFE_NO_PAD.encode(url));
+        let signature = hex::encode(mac(&payload, secret).finalize().into_bytes());
+        Self(format!("{}.{}", payload, signature))
+    }
+
+    /// 验证签名，成功时返回令牌对应的投递 ID 和目标地址
+    pub fn verify(token: &str, secret: &SecretString) -> Option<(uuid::Uuid, String)> {
+        let (payload, signature) = token.rsplit_once('.')?;
+        let signature = hex::decode(signature).ok()?;
+        mac(payload, secret).verify_slice(&signature).ok()?;
+        let (delivery_id, url) = payload.split_once('.')?;
+        let delivery_id = uuid::Uuid::parse_str(delivery_id).ok()?;
+        let url = String::from_utf8(URL_SAFE_NO_PAD.decode(url).ok()?).ok()?;
+        Some((delivery_id, url))
+    }
+}
+
+impl AsRef<str> for ClickToken {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+/// 签名内容带有用途前缀，与退订令牌的签名区分开
+fn mac(payload: &str, secret: &SecretString) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.expose_secret().as_bytes())
+        .expect("HMAC 可以接受任意长度的密钥");
+    mac.update(b"click.");
+    mac.update(payload.as_bytes());
+    mac
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+    use claim::assert_none;
+    use secrecy::SecretString;
+
+    use super::ClickToken;
+
+    fn secret() -> SecretString {
+        SecretString::from("secret")
+    }
+
+    #[test]
+    fn a_generated_token_is_verified() {
+        let delivery_id = uuid::Uuid::new_v4();
+        let url = "https://example.com/post?id=1&lang=zh#top";
+        let token = ClickToken::generate(delivery_id, url, &secret());
+
+        assert_eq!(
+            ClickToken::verify(token.as_ref(), &secret()),
+            Some((delivery_id, url.to_string()))
+        );
+    }
+
+    #[test]
+    fn a_token_signed_with_another_secret_is_rejected() {
+        let token = ClickToken::generate(
+            uuid::Uuid::new_v4(),
+            "https://example.com",
+            &SecretString::from("other"),
+        );
+
+        assert_none!(ClickToken::verify(token.as_ref(), &secret()));
+    }
+
+    #[test]
+    fn a_token_with_a_replaced_url_is_rejected() {
+        let token = ClickToken::generate(uuid::Uuid::new_v4(), "https://example.com", &secret());
+        let (delivery_id, rest) = token.as_ref().split_once('.').unwrap();
+        let (_, signature) = rest.split_once('.').unwrap();
+        let forged = format!(
+            "{}.{}.{}",
+            delivery_id,
+            URL_SAFE_NO_PAD.encode("https://evil.example"),
+            signature
+        );
+
+        assert_none!(ClickToken::verify(&forged, &secret()));
+    }
+
+    #[test]
+    fn malformed_tokens_are_rejected() {
+        for token in [
+            "",
+            "abc",
+            "abc.def",
+            "..",
+            "not-a-uuid.aGk.00",
+            "https://evil.example",
+        ] {
+            assert_none!(ClickToken::verify(token, &secret()));
+        }
+    }
+}
@@ -0,0 +1,56 @@
+use std::fmt::{Display, Formatter};
+
+use sea_orm::entity::prelude::*;
+
+/// 收件人与邮件交互产生的事件类型，数据库中以文本存储并由 CHECK 约束限定取值
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    EnumIter,
+    DeriveActiveEnum,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::None)")]
+#[serde(rename_all = "snake_case")]
+pub enum EmailEventKind {
+    /// 加载了邮件中的跟踪像素，每次投递只记录一次
+    #[sea_orm(string_value = "open")]
+    Open,
+    /// 点击了经过改写的链接，每次点击都会记录
+    #[sea_orm(string_value = "click")]
+    Click,
+}
+
+impl EmailEventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EmailEventKind::Open => "open",
+            EmailEventKind::Click => "click",
+        }
+    }
+}
+
+impl Display for EmailEventKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sea_orm::{ActiveEnum, Iterable};
+
+    use super::EmailEventKind;
+
+    #[test]
+    fn database_values_match_display() {
+        for kind in EmailEventKind::iter() {
+            assert_eq!(kind.to_value(), kind.to_string());
+        }
+    }
+}
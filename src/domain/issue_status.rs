@@ -0,0 +1,61 @@
+use std::fmt::{Display, Formatter};
+
+use sea_orm::entity::prelude::*;
+
+/// 新闻邮件的发送状态，数据库中以文本存储并由 CHECK 约束限定取值
+///
+/// 立即发布的新闻邮件直接进入 `Enqueued`；计划发送的新闻邮件在到期前为 `Scheduled`，
+/// 此时可以修改发送时间或取消，到期后由调度任务创建投递任务并进入 `Enqueued`
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    EnumIter,
+    DeriveActiveEnum,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::None)")]
+#[serde(rename_all = "snake_case")]
+pub enum IssueStatus {
+    #[sea_orm(string_value = "scheduled")]
+    Scheduled,
+    /// 已经为订阅者创建了投递任务，发送已经开始
+    #[sea_orm(string_value = "enqueued")]
+    Enqueued,
+    #[sea_orm(string_value = "cancelled")]
+    Cancelled,
+}
+
+impl IssueStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IssueStatus::Scheduled => "scheduled",
+            IssueStatus::Enqueued => "enqueued",
+            IssueStatus::Cancelled => "cancelled",
+        }
+    }
+}
+
+impl Display for IssueStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sea_orm::{ActiveEnum, Iterable};
+
+    use super::IssueStatus;
+
+    #[test]
+    fn database_values_match_display() {
+        for status in IssueStatus::iter() {
+            assert_eq!(status.to_value(), status.to_string());
+        }
+    }
+}
@@ -0,0 +1,59 @@
+/// 未指定列表时订阅和发布使用的默认列表，由迁移创建
+pub const DEFAULT_LIST_SLUG: &str = "newsletter";
+
+/// 邮件列表的标识，出现在订阅表单和发布接口中
+///
+/// 只允许小写字母、数字和连字符，且不能以连字符开头或结尾，最长 64 个字符
+#[derive(Debug)]
+pub struct ListSlug(String);
+
+impl ListSlug {
+    pub fn parse(s: String) -> Result<Self, String> {
+        let is_valid_length = (1..=64).contains(&s.len());
+        let has_valid_characters = s
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+        let has_valid_edges = !s.starts_with('-') && !s.ends_with('-');
+        if is_valid_length && has_valid_characters && has_valid_edges {
+            Ok(Self(s))
+        } else {
+            Err(format!("{} 不是有效的列表标识", s))
+        }
+    }
+}
+
+impl AsRef<str> for ListSlug {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claim::{assert_err, assert_ok};
+
+    use super::{DEFAULT_LIST_SLUG, ListSlug};
+
+    #[test]
+    fn the_default_slug_is_valid() {
+        assert_ok!(ListSlug::parse(DEFAULT_LIST_SLUG.to_string()));
+    }
+
+    #[test]
+    fn lowercase_letters_digits_and_hyphens_are_accepted() {
+        assert_ok!(ListSlug::parse("release-notes-2025".to_string()));
+    }
+
+    #[test]
+    fn invalid_slugs_are_rejected() {
+        for slug in ["", "Weekly", "weekly news", "weekly_news", "-weekly", "weekly-", "周刊"] {
+            assert_err!(ListSlug::parse(slug.to_string()));
+        }
+    }
+
+    #[test]
+    fn a_slug_longer_than_64_characters_is_rejected() {
+        assert_ok!(ListSlug::parse("a".repeat(64)));
+        assert_err!(ListSlug::parse("a".repeat(65)));
+    }
+}
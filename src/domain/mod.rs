@@ -1,7 +1,25 @@
+mod click_token;
+mod email_event_kind;
+mod issue_status;
+mod list_slug;
 mod new_subscriber;
 mod subscriber_name;
 mod subscriber_email;
+mod subscription_status;
+mod suppression_source;
+mod unsubscribe_token;
+mod validation_error;
+mod webhook_event_kind;
 
+pub use click_token::ClickToken;
+pub use email_event_kind::EmailEventKind;
+pub use issue_status::IssueStatus;
+pub use list_slug::{DEFAULT_LIST_SLUG, ListSlug};
 pub use new_subscriber::NewSubscriber;
 pub use subscriber_name::SubscriberName;
-pub use subscriber_email::SubscriberEmail;
+pub use subscriber_email::{SubscriberEmail, email_alias_key, normalize_email};
+pub use subscription_status::{StatusTransitionError, SubscriptionStatus};
+pub use suppression_source::SuppressionSource;
+pub use unsubscribe_token::UnsubscribeToken;
+pub use validation_error::ValidationError;
+pub use webhook_event_kind::WebhookEventKind;
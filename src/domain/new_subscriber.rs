@@ -1,6 +1,8 @@
 use crate::domain::{subscriber_name::SubscriberName, SubscriberEmail};
+use crate::i18n::Locale;
 
 pub struct NewSubscriber {
     pub email: SubscriberEmail,
     pub name: SubscriberName,
-}
\ No newline at end of file
+    pub locale: Locale,
+}
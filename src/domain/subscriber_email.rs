@@ -1,19 +1,59 @@
 use validator::ValidateEmail;
 
+use super::ValidationError;
+
 
 #[derive(Debug)]
 pub struct SubscriberEmail(String);
 
 impl SubscriberEmail {
-    pub fn parse(s: String) -> Result<SubscriberEmail, String> {
-        if s.validate_email() {
-            Ok(Self(s))
-        } else {
-            Err(format!("{} 不是一个有效的邮箱", s))
+    /// 去掉首尾空白，并把域名转为小写的 ASCII 形式（国际化域名转换为 punycode），本地部分保持原样
+    pub fn parse(s: String) -> Result<SubscriberEmail, ValidationError> {
+        let normalized = s
+            .trim()
+            .rsplit_once('@')
+            .and_then(|(local, domain)| Some(format!("{}@{}", local, ascii_domain(domain)?)));
+        match normalized {
+            Some(email) if email.validate_email() => Ok(Self(email)),
+            _ => Err(ValidationError::InvalidEmail(s)),
         }
     }
 }
 
+fn ascii_domain(domain: &str) -> Option<String> {
+    idna::domain_to_ascii(domain).ok()
+}
+
+/// 地址的规范形式，订阅者按它去重，抑制列表也按它存储和查找
+///
+/// 在 [`SubscriberEmail::parse`] 的基础上把本地部分也转为小写
+pub fn normalize_email(email: &str) -> String {
+    let email = email.trim();
+    match email.rsplit_once('@') {
+        Some((local, domain)) => format!(
+            "{}@{}",
+            local.to_lowercase(),
+            ascii_domain(domain).unwrap_or_else(|| domain.to_lowercase())
+        ),
+        None => email.to_lowercase(),
+    }
+}
+
+/// 忽略服务商别名后的地址，只用于提示可能重复的订阅者
+///
+/// 去掉本地部分中 `+` 之后的标签；Gmail 地址还会去掉本地部分中的点，并统一使用 `gmail.com`
+pub fn email_alias_key(email: &str) -> String {
+    let canonical = normalize_email(email);
+    let Some((local, domain)) = canonical.rsplit_once('@') else {
+        return canonical;
+    };
+    let local = local.split('+').next().unwrap_or_default();
+    match domain {
+        "gmail.com" | "googlemail.com" => format!("{}@gmail.com", local.replace('.', "")),
+        _ => format!("{}@{}", local, domain),
+    }
+}
+
 impl AsRef<str> for SubscriberEmail {
     fn as_ref(&self) -> &str {
         &self.0
@@ -24,7 +64,7 @@ impl AsRef<str> for SubscriberEmail {
 mod tests {
     use claim::assert_err;
     use fake::{ faker::internet::en::SafeEmail, Fake};
-    use crate::domain::SubscriberEmail;
+    use crate::domain::{SubscriberEmail, email_alias_key, normalize_email};
 
     #[test]
     fn empty_string_is_rejected() {
@@ -44,6 +84,71 @@ mod tests {
         assert_err!(SubscriberEmail::parse(email));
     }
 
+    #[test]
+    fn whitespace_is_trimmed_and_the_domain_is_lowercased() {
+        let email = SubscriberEmail::parse("  Ursula@Example.COM\n".to_string()).unwrap();
+        assert_eq!(email.as_ref(), "Ursula@example.com");
+    }
+
+    #[test]
+    fn international_domains_are_converted_to_punycode() {
+        let email = SubscriberEmail::parse("ursula@Bücher.example".to_string()).unwrap();
+        assert_eq!(email.as_ref(), "ursula@xn--bcher-kva.example");
+    }
+
+    #[test]
+    fn canonical_email_ignores_case_and_domain_encoding() {
+        for email in [
+            "ursula@xn--bcher-kva.example",
+            "Ursula@BÜCHER.example",
+            " URSULA@bücher.example ",
+        ] {
+            assert_eq!(normalize_email(email), "ursula@xn--bcher-kva.example");
+        }
+    }
+
+    #[test]
+    fn alias_key_strips_plus_tags() {
+        assert_eq!(
+            email_alias_key("ursula+news@example.com"),
+            "ursula@example.com"
+        );
+        assert_eq!(
+            email_alias_key("ur.sula@example.com"),
+            "ur.sula@example.com"
+        );
+    }
+
+    #[test]
+    fn alias_key_ignores_dots_in_gmail_addresses() {
+        for email in [
+            "Ursula.Le.Guin@gmail.com",
+            "ursulaleguin+books@googlemail.com",
+        ] {
+            assert_eq!(email_alias_key(email), "ursulaleguin@gmail.com");
+        }
+    }
+
+    #[test]
+    fn the_migration_snapshot_of_the_alias_rule_matches_the_application() {
+        for email in [
+            "ursula@example.com",
+            "Ursula+News@Example.com",
+            "ur.sula@example.com",
+            "Ursula.Le.Guin@gmail.com",
+            "ursulaleguin+books@googlemail.com",
+            "ursula@Bücher.example",
+            "no-at-sign",
+        ] {
+            assert_eq!(
+                migration::snapshots::alias_key(&normalize_email(email)),
+                email_alias_key(email),
+                "{}",
+                email
+            );
+        }
+    }
+
     #[derive(Debug, Clone)]
     struct ValidEmailFixture(pub String);
 
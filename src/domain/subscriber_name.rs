@@ -1,16 +1,18 @@
 use unicode_segmentation::UnicodeSegmentation;
 
+use super::ValidationError;
+
 #[derive(Debug)]
 pub struct SubscriberName(String);
 
 impl SubscriberName {
-    pub fn parse(s: String) -> Result<Self, String> {
+    pub fn parse(s: String) -> Result<Self, ValidationError> {
         let is_empty_or_whitespace = s.trim().is_empty();
         let is_too_long = s.graphemes(true).count() > 256;
         let forbidden_characters = ['/', '(', ')', '"', '<', '>', '\\', '{', '}'];
         let contains_forbidden_characters = s.chars().any(|c| forbidden_characters.contains(&c));
         if is_empty_or_whitespace || is_too_long || contains_forbidden_characters {
-            Err(format!("订阅者姓名无效: {}", s))
+            Err(ValidationError::InvalidName(s))
         }else {
             Ok(Self(s))
         }
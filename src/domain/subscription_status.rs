@@ -0,0 +1,124 @@
+use std::fmt::{Display, Formatter};
+
+use sea_orm::entity::prelude::*;
+
+/// 订阅状态，数据库中以文本存储并由 CHECK 约束限定取值
+///
+/// 状态只能按 [`SubscriptionStatus::can_transition_to`] 中的规则变化：
+/// 待确认 → 已确认 → 已退订 → 重新订阅（回到待确认），
+/// 已确认或待确认的订阅者也可能因退信或投诉而停止投递
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, EnumIter, DeriveActiveEnum, serde::Serialize, serde::Deserialize,
+)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::None)")]
+#[serde(rename_all = "snake_case")]
+pub enum SubscriptionStatus {
+    #[sea_orm(string_value = "pending_confirmation")]
+    PendingConfirmation,
+    #[sea_orm(string_value = "confirmed")]
+    Confirmed,
+    #[sea_orm(string_value = "unsubscribed")]
+    Unsubscribed,
+    #[sea_orm(string_value = "bounced")]
+    Bounced,
+    #[sea_orm(string_value = "complained")]
+    Complained,
+}
+
+impl SubscriptionStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SubscriptionStatus::PendingConfirmation => "pending_confirmation",
+            SubscriptionStatus::Confirmed => "confirmed",
+            SubscriptionStatus::Unsubscribed => "unsubscribed",
+            SubscriptionStatus::Bounced => "bounced",
+            SubscriptionStatus::Complained => "complained",
+        }
+    }
+
+    pub fn can_transition_to(&self, next: SubscriptionStatus) -> bool {
+        use SubscriptionStatus::*;
+        matches!(
+            (self, next),
+            // 待确认时重新订阅会重新发送确认邮件
+            (PendingConfirmation, PendingConfirmation | Confirmed | Unsubscribed | Bounced | Complained)
+                | (Confirmed, Unsubscribed | Bounced | Complained)
+                // 退订或退信后可以重新订阅，但需要再次确认邮箱
+                | (Unsubscribed | Bounced, PendingConfirmation)
+        )
+    }
+
+    pub fn transition_to(
+        self,
+        next: SubscriptionStatus,
+    ) -> Result<SubscriptionStatus, StatusTransitionError> {
+        if self.can_transition_to(next) {
+            Ok(next)
+        } else {
+            Err(StatusTransitionError { from: self, to: next })
+        }
+    }
+}
+
+impl Display for SubscriptionStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// 订阅状态不允许从 `from` 变为 `to`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StatusTransitionError {
+    pub from: SubscriptionStatus,
+    pub to: SubscriptionStatus,
+}
+
+impl Display for StatusTransitionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "订阅状态不能从 {} 变为 {}", self.from, self.to)
+    }
+}
+
+impl std::error::Error for StatusTransitionError {}
+
+#[cfg(test)]
+mod tests {
+    use claim::{assert_err, assert_ok};
+    use sea_orm::{ActiveEnum, Iterable};
+
+    use super::SubscriptionStatus::{self, *};
+
+    #[test]
+    fn the_subscription_lifecycle_is_allowed() {
+        let status = PendingConfirmation;
+        let status = status.transition_to(Confirmed).unwrap();
+        let status = status.transition_to(Unsubscribed).unwrap();
+        let status = status.transition_to(PendingConfirmation).unwrap();
+        assert_ok!(status.transition_to(Confirmed));
+    }
+
+    #[test]
+    fn an_unsubscribed_subscriber_cannot_be_confirmed_directly() {
+        assert_err!(Unsubscribed.transition_to(Confirmed));
+    }
+
+    #[test]
+    fn a_confirmed_subscriber_cannot_go_back_to_pending() {
+        assert_err!(Confirmed.transition_to(PendingConfirmation));
+        assert_err!(Confirmed.transition_to(Confirmed));
+    }
+
+    #[test]
+    fn a_complaint_is_final() {
+        for next in SubscriptionStatus::iter() {
+            assert_err!(Complained.transition_to(next));
+        }
+    }
+
+    #[test]
+    fn database_values_match_display() {
+        for status in SubscriptionStatus::iter() {
+            assert_eq!(status.to_value(), status.to_string());
+        }
+    }
+}
@@ -0,0 +1,56 @@
+use std::fmt::{Display, Formatter};
+
+use sea_orm::entity::prelude::*;
+
+/// 地址被加入抑制列表的途径，数据库中以文本存储并由 CHECK 约束限定取值
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    EnumIter,
+    DeriveActiveEnum,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::None)")]
+#[serde(rename_all = "snake_case")]
+pub enum SuppressionSource {
+    /// 管理员手动添加，例如法律要求或角色账号
+    #[sea_orm(string_value = "admin")]
+    Admin,
+    /// 邮件服务商推送的硬退信或投诉
+    #[sea_orm(string_value = "webhook")]
+    Webhook,
+}
+
+impl SuppressionSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SuppressionSource::Admin => "admin",
+            SuppressionSource::Webhook => "webhook",
+        }
+    }
+}
+
+impl Display for SuppressionSource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sea_orm::{ActiveEnum, Iterable};
+
+    use super::SuppressionSource;
+
+    #[test]
+    fn database_values_match_display() {
+        for source in SuppressionSource::iter() {
+            assert_eq!(source.to_value(), source.to_string());
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use hmac::{Hmac, Mac};
+use secrecy::{ExposeSecret, SecretString};
+use sha2::Sha256;
+
+/// 退订令牌，格式为 `<订阅者 ID>.<HMAC-SHA256 签名>`
+///
+/// 令牌无需存储，验证签名即可确认它由本服务为该订阅者生成
+#[derive(Debug)]
+pub struct UnsubscribeToken(String);
+
+impl UnsubscribeToken {
+    pub fn generate(subscriber_id: uuid::Uuid, secret: &SecretString) -> Self {
+        let subscriber_id = subscriber_id.simple().to_string();
+        let signature = hex::encode(mac(&subscriber_id, secret).finalize().into_bytes());
+        Self(format!("{}.{}", subscriber_id, signature))
+    }
+
+    /// 验证签名，成功时返回令牌对应的订阅者 ID
+    pub fn verify(token: &str, secret: &SecretString) -> Option<uuid::Uuid> {
+        let (subscriber_id, signature) = token.split_once('.')?;
+        let signature = hex::decode(signature).ok()?;
+        mac(subscriber_id, secret).verify_slice(&signature).ok()?;
+        uuid::Uuid::parse_str(subscriber_id).ok()
+    }
+}
+
+impl AsRef<str> for UnsubscribeToken {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+/// 签名内容带有用途前缀，与点击跟踪令牌的签名区分开
+fn mac(subscriber_id: &str, secret: &SecretString) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.expose_secret().as_bytes())
+        .expect("HMAC 可以接受任意长度的密钥");
+    mac.update(b"unsubscribe.");
+    mac.update(subscriber_id.as_bytes());
+    mac
+}
+
+#[cfg(test)]
+mod tests {
+    use claim::assert_none;
+    use secrecy::SecretString;
+
+    use super::UnsubscribeToken;
+
+    fn secret() -> SecretString {
+        SecretString::from("secret")
+    }
+
+    #[test]
+    fn a_generated_token_is_verified() {
+        let subscriber_id = uuid::Uuid::new_v4();
+        let token = UnsubscribeToken::generate(subscriber_id, &secret());
+
+        assert_eq!(
+            UnsubscribeToken::verify(token.as_ref(), &secret()),
+            Some(subscriber_id)
+        );
+    }
+
+    #[test]
+    fn a_token_signed_with_another_secret_is_rejected() {
+        let token = UnsubscribeToken::generate(uuid::Uuid::new_v4(), &SecretString::from("other"));
+
+        assert_none!(UnsubscribeToken::verify(token.as_ref(), &secret()));
+    }
+
+    #[test]
+    fn a_token_for_another_subscriber_is_rejected() {
+        let token = UnsubscribeToken::generate(uuid::Uuid::new_v4(), &secret());
+        let (_, signature) = token.as_ref().split_once('.').unwrap();
+        let forged = format!("{}.{}", uuid::Uuid::new_v4().simple(), signature);
+
+        assert_none!(UnsubscribeToken::verify(&forged, &secret()));
+    }
+
+    #[test]
+    fn malformed_tokens_are_rejected() {
+        for token in ["", "abc", "abc.def", ".", "not-a-uuid.00"] {
+            assert_none!(UnsubscribeToken::verify(token, &secret()));
+        }
+    }
+}
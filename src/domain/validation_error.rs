@@ -0,0 +1,46 @@
+use std::fmt::{Display, Formatter};
+
+use fluent_bundle::FluentArgs;
+
+use crate::i18n::{Locale, Localize};
+
+/// 订阅者输入未通过校验，携带原始输入以便在错误信息中展示
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationError {
+    InvalidName(String),
+    InvalidEmail(String),
+}
+
+impl ValidationError {
+    /// 出错的输入字段名
+    pub fn field(&self) -> &'static str {
+        match self {
+            ValidationError::InvalidName(_) => "name",
+            ValidationError::InvalidEmail(_) => "email",
+        }
+    }
+}
+
+impl Localize for ValidationError {
+    fn localize(&self, locale: Locale) -> String {
+        let mut args = FluentArgs::new();
+        match self {
+            ValidationError::InvalidName(name) => {
+                args.set("name", name.as_str());
+                locale.translate("invalid-subscriber-name", Some(&args))
+            }
+            ValidationError::InvalidEmail(email) => {
+                args.set("email", email.as_str());
+                locale.translate("invalid-subscriber-email", Some(&args))
+            }
+        }
+    }
+}
+
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.localize(Locale::default()))
+    }
+}
+
+impl std::error::Error for ValidationError {}
@@ -0,0 +1,103 @@
+use std::fmt::{Display, Formatter};
+
+use sea_orm::entity::prelude::*;
+
+/// 邮件服务商推送的事件类型，数据库中以文本存储并由 CHECK 约束限定取值
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    EnumIter,
+    DeriveActiveEnum,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::None)")]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEventKind {
+    /// 地址不存在等永久性退信，立即停止投递
+    #[sea_orm(string_value = "hard_bounce")]
+    HardBounce,
+    /// 邮箱已满等暂时性退信，累计达到阈值后才停止投递
+    #[sea_orm(string_value = "soft_bounce")]
+    SoftBounce,
+    /// 收件人把邮件标记为垃圾邮件
+    #[sea_orm(string_value = "complaint")]
+    Complaint,
+    /// 自动回复等不影响投递的事件，只保存不处理
+    #[sea_orm(string_value = "other")]
+    Other,
+}
+
+impl WebhookEventKind {
+    /// 按 Postmark 风格的 `RecordType` 和退信 `Type` 字段归类事件
+    pub fn classify(record_type: &str, bounce_type: Option<&str>) -> Self {
+        match (record_type, bounce_type) {
+            ("SpamComplaint", _) => WebhookEventKind::Complaint,
+            ("Bounce", Some("HardBounce" | "BadEmailAddress")) => WebhookEventKind::HardBounce,
+            ("Bounce", Some("SoftBounce" | "Transient" | "DnsError")) => {
+                WebhookEventKind::SoftBounce
+            }
+            _ => WebhookEventKind::Other,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEventKind::HardBounce => "hard_bounce",
+            WebhookEventKind::SoftBounce => "soft_bounce",
+            WebhookEventKind::Complaint => "complaint",
+            WebhookEventKind::Other => "other",
+        }
+    }
+}
+
+impl Display for WebhookEventKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sea_orm::{ActiveEnum, Iterable};
+
+    use super::WebhookEventKind;
+
+    #[test]
+    fn database_values_match_display() {
+        for kind in WebhookEventKind::iter() {
+            assert_eq!(kind.to_value(), kind.to_string());
+        }
+    }
+
+    #[test]
+    fn provider_events_are_classified() {
+        let cases = [
+            ("Bounce", Some("HardBounce"), WebhookEventKind::HardBounce),
+            (
+                "Bounce",
+                Some("BadEmailAddress"),
+                WebhookEventKind::HardBounce,
+            ),
+            ("Bounce", Some("SoftBounce"), WebhookEventKind::SoftBounce),
+            ("Bounce", Some("Transient"), WebhookEventKind::SoftBounce),
+            ("Bounce", Some("AutoResponder"), WebhookEventKind::Other),
+            ("Bounce", None, WebhookEventKind::Other),
+            ("SpamComplaint", None, WebhookEventKind::Complaint),
+            ("Delivery", None, WebhookEventKind::Other),
+        ];
+        for (record_type, bounce_type, expected) in cases {
+            assert_eq!(
+                WebhookEventKind::classify(record_type, bounce_type),
+                expected,
+                "{} {:?}",
+                record_type,
+                bounce_type
+            );
+        }
+    }
+}
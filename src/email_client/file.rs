@@ -0,0 +1,78 @@
+use std::path::PathBuf;
+
+use super::{Email, EmailError, EmailTransport};
+
+/// 按 maildir 格式把邮件写入本地目录
+///
+/// 邮件先写入 `tmp/`，完整写入后再移动到 `new/`，邮件客户端不会读到写了一半的文件
+#[derive(Debug, Clone)]
+pub struct FileEmailTransport {
+    directory: PathBuf,
+}
+
+impl FileEmailTransport {
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailTransport for FileEmailTransport {
+    async fn send(&self, email: &Email) -> Result<(), EmailError> {
+        let message = email.to_message()?;
+
+        let tmp_directory = self.directory.join("tmp");
+        let new_directory = self.directory.join("new");
+        tokio::fs::create_dir_all(&tmp_directory)
+            .await
+            .map_err(EmailError::IoError)?;
+        tokio::fs::create_dir_all(&new_directory)
+            .await
+            .map_err(EmailError::IoError)?;
+
+        let file_name = format!("{}.eml", uuid::Uuid::new_v4());
+        let tmp_path = tmp_directory.join(&file_name);
+        tokio::fs::write(&tmp_path, message.formatted())
+            .await
+            .map_err(EmailError::IoError)?;
+        tokio::fs::rename(&tmp_path, new_directory.join(&file_name))
+            .await
+            .map_err(EmailError::IoError)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claim::assert_ok;
+
+    use super::FileEmailTransport;
+    use crate::email_client::{Email, EmailTransport};
+
+    #[tokio::test]
+    async fn emails_are_written_to_the_new_directory() {
+        let directory = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        let transport = FileEmailTransport::new(&directory);
+        let email = Email {
+            from: "sender@example.com".into(),
+            to: "recipient@example.com".into(),
+            subject: "Subject".into(),
+            html_content: "<p>Hello</p>".into(),
+            text_content: "Hello".into(),
+        };
+
+        assert_ok!(transport.send(&email).await);
+
+        let mut entries = std::fs::read_dir(directory.join("new")).unwrap();
+        let path = entries.next().unwrap().unwrap().path();
+        let content = std::fs::read_to_string(path).unwrap();
+        assert!(content.contains("To: recipient@example.com"));
+        assert!(content.contains("Subject: Subject"));
+        assert_eq!(std::fs::read_dir(directory.join("tmp")).unwrap().count(), 0);
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
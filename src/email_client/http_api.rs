@@ -0,0 +1,190 @@
+use std::time::Duration;
+
+use reqwest::Client;
+use secrecy::{ExposeSecret, SecretString};
+
+use super::{Email, EmailError, EmailTransport};
+
+/// 通过 HTTP API（Postmark 风格）发送邮件
+#[derive(Debug)]
+pub struct HttpApiEmailTransport {
+    http_client: Client,
+    base_url: String,
+    authorization_token: SecretString,
+}
+
+impl HttpApiEmailTransport {
+    pub const TOKEN_HEADER: &'static str = "X-Postmark-Server-Token";
+
+    pub fn new(base_url: String, authorization_token: SecretString, timeout: Duration) -> Self {
+        let http_client = Client::builder()
+            .timeout(timeout)
+            .build()
+            .expect("构建 HTTP 客户端失败");
+
+        Self {
+            http_client,
+            base_url,
+            authorization_token,
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct SendEmailRequest<'a> {
+    from: &'a str,
+    to: &'a str,
+    subject: &'a str,
+    html_body: &'a str,
+    text_body: &'a str,
+}
+
+#[async_trait::async_trait]
+impl EmailTransport for HttpApiEmailTransport {
+    async fn send(&self, email: &Email) -> Result<(), EmailError> {
+        let url = format!("{}/email", self.base_url.trim_end_matches('/'));
+        let request_body = SendEmailRequest {
+            from: &email.from,
+            to: &email.to,
+            subject: &email.subject,
+            html_body: &email.html_content,
+            text_body: &email.text_content,
+        };
+
+        self.http_client
+            .post(&url)
+            .header(Self::TOKEN_HEADER, self.authorization_token.expose_secret())
+            .json(&request_body)
+            .send()
+            .await
+            .map_err(EmailError::HttpError)?
+            .error_for_status()
+            .map_err(EmailError::HttpError)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use claim::{assert_err, assert_ok};
+    use fake::{
+        Fake,
+        faker::{
+            internet::en::SafeEmail,
+            lorem::en::{Paragraph, Sentence},
+        },
+    };
+    use secrecy::SecretString;
+    use wiremock::{
+        Mock, MockServer, Request, ResponseTemplate,
+        matchers::{any, header, header_exists, method, path},
+    };
+
+    use super::HttpApiEmailTransport;
+    use crate::email_client::{Email, EmailTransport};
+
+    struct SendEmailBodyMatcher;
+
+    impl wiremock::Match for SendEmailBodyMatcher {
+        fn matches(&self, request: &Request) -> bool {
+            let result: Result<serde_json::Value, _> = serde_json::from_slice(&request.body);
+            if let Ok(body) = result {
+                body.get("From").is_some()
+                    && body.get("To").is_some()
+                    && body.get("Subject").is_some()
+                    && body.get("HtmlBody").is_some()
+                    && body.get("TextBody").is_some()
+            } else {
+                false
+            }
+        }
+    }
+
+    fn email() -> Email {
+        Email {
+            from: SafeEmail().fake(),
+            to: SafeEmail().fake(),
+            subject: Sentence(1..2).fake(),
+            html_content: Paragraph(1..10).fake(),
+            text_content: Paragraph(1..10).fake(),
+        }
+    }
+
+    fn transport(base_url: String) -> HttpApiEmailTransport {
+        HttpApiEmailTransport::new(
+            base_url,
+            SecretString::from("my-token"),
+            Duration::from_millis(200),
+        )
+    }
+
+    #[tokio::test]
+    async fn send_fires_a_request_to_base_url() {
+        let mock_server = MockServer::start().await;
+        let transport = transport(mock_server.uri());
+
+        Mock::given(header_exists(HttpApiEmailTransport::TOKEN_HEADER))
+            .and(header(HttpApiEmailTransport::TOKEN_HEADER, "my-token"))
+            .and(header("Content-Type", "application/json"))
+            .and(path("/email"))
+            .and(method("POST"))
+            .and(SendEmailBodyMatcher)
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let _ = transport.send(&email()).await;
+    }
+
+    #[tokio::test]
+    async fn send_succeeds_if_the_server_returns_200() {
+        let mock_server = MockServer::start().await;
+        let transport = transport(mock_server.uri());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = transport.send(&email()).await;
+
+        assert_ok!(outcome);
+    }
+
+    #[tokio::test]
+    async fn send_fails_if_the_server_returns_500() {
+        let mock_server = MockServer::start().await;
+        let transport = transport(mock_server.uri());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = transport.send(&email()).await;
+
+        assert_err!(outcome);
+    }
+
+    #[tokio::test]
+    async fn send_times_out_if_the_server_takes_too_long() {
+        let mock_server = MockServer::start().await;
+        let transport = transport(mock_server.uri());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(180)))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = transport.send(&email()).await;
+
+        assert_err!(outcome);
+    }
+}
@@ -0,0 +1,26 @@
+use std::sync::{Arc, Mutex};
+
+use super::{Email, EmailError, EmailTransport};
+
+/// 只把邮件记录在内存中，供测试断言和本地开发使用
+///
+/// 克隆出的实例共享同一份记录
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryEmailTransport {
+    messages: Arc<Mutex<Vec<Email>>>,
+}
+
+impl InMemoryEmailTransport {
+    /// 返回目前为止发送过的所有邮件
+    pub fn messages(&self) -> Vec<Email> {
+        self.messages.lock().unwrap().clone()
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailTransport for InMemoryEmailTransport {
+    async fn send(&self, email: &Email) -> Result<(), EmailError> {
+        self.messages.lock().unwrap().push(email.clone());
+        Ok(())
+    }
+}
@@ -0,0 +1,222 @@
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+use std::sync::Arc;
+
+use lettre::{
+    Message,
+    message::{MultiPart, SinglePart},
+};
+
+use crate::{domain::SubscriberEmail, routes::error_chain_fmt, suppression_list::SuppressionList};
+
+mod file;
+mod http_api;
+mod in_memory;
+mod smtp;
+
+pub use file::FileEmailTransport;
+pub use http_api::HttpApiEmailTransport;
+pub use in_memory::InMemoryEmailTransport;
+pub use smtp::SmtpEmailTransport;
+
+/// 一封待发送的邮件，与具体的发送方式无关
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Email {
+    pub from: String,
+    pub to: String,
+    pub subject: String,
+    pub html_content: String,
+    pub text_content: String,
+}
+
+impl Email {
+    /// 构建 RFC 5322 格式的邮件，供 SMTP 和文件后端使用
+    fn to_message(&self) -> Result<Message, EmailError> {
+        let message = Message::builder()
+            .from(self.from.parse().map_err(EmailError::AddressError)?)
+            .to(self.to.parse().map_err(EmailError::AddressError)?)
+            .subject(&self.subject)
+            .multipart(
+                MultiPart::alternative()
+                    .singlepart(SinglePart::plain(self.text_content.clone()))
+                    .singlepart(SinglePart::html(self.html_content.clone())),
+            )
+            .map_err(EmailError::BuildError)?;
+        Ok(message)
+    }
+}
+
+/// 邮件的实际发送方式
+#[async_trait::async_trait]
+pub trait EmailTransport: Debug + Send + Sync {
+    async fn send(&self, email: &Email) -> Result<(), EmailError>;
+}
+
+#[derive(Debug, Clone)]
+pub struct EmailClient {
+    sender: String,
+    transport: Arc<dyn EmailTransport>,
+    suppressions: Option<SuppressionList>,
+}
+
+impl EmailClient {
+    pub fn new(sender: String, transport: Arc<dyn EmailTransport>) -> Self {
+        Self {
+            sender,
+            transport,
+            suppressions: None,
+        }
+    }
+
+    /// 发送前检查收件人是否在抑制列表中，在列表中时返回 [`EmailError::Suppressed`] 而不发送
+    pub fn with_suppression_list(mut self, suppressions: SuppressionList) -> Self {
+        self.suppressions = Some(suppressions);
+        self
+    }
+
+    pub async fn send_email(
+        &self,
+        recipient: SubscriberEmail,
+        subject: &str,
+        html_content: &str,
+        text_content: &str,
+    ) -> Result<(), EmailError> {
+        if let Some(suppressions) = &self.suppressions
+            && suppressions
+                .contains(recipient.as_ref())
+                .await
+                .map_err(EmailError::SuppressionCheckError)?
+        {
+            tracing::info!("收件人在抑制列表中, 不发送邮件");
+            return Err(EmailError::Suppressed);
+        }
+
+        let email = Email {
+            from: self.sender.clone(),
+            to: recipient.as_ref().to_string(),
+            subject: subject.to_string(),
+            html_content: html_content.to_string(),
+            text_content: text_content.to_string(),
+        };
+
+        self.transport.send(&email).await
+    }
+
+    /// 发送确认、欢迎、退订等事务性邮件
+    ///
+    /// 与 [`EmailClient::send_email`] 不同，收件人在抑制列表中时按设计跳过并返回 `Ok(())`，
+    /// 调用方无需区分被抑制和已发送
+    pub async fn send_transactional_email(
+        &self,
+        recipient: SubscriberEmail,
+        subject: &str,
+        html_content: &str,
+        text_content: &str,
+    ) -> Result<(), EmailError> {
+        match self
+            .send_email(recipient, subject, html_content, text_content)
+            .await
+        {
+            Err(EmailError::Suppressed) => Ok(()),
+            outcome => outcome,
+        }
+    }
+}
+
+pub enum EmailError {
+    AddressError(lettre::address::AddressError),
+    BuildError(lettre::error::Error),
+    IoError(std::io::Error),
+    SmtpError(lettre::transport::smtp::Error),
+    HttpError(reqwest::Error),
+    TemplateError(tera::Error),
+    /// 收件人在抑制列表中，邮件没有发送
+    Suppressed,
+    SuppressionCheckError(sea_orm::DbErr),
+}
+
+impl Display for EmailError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EmailError::AddressError(_) => write!(f, "邮箱地址无效"),
+            EmailError::BuildError(_) => write!(f, "构建邮件失败"),
+            EmailError::IoError(_) => write!(f, "写入邮件文件失败"),
+            EmailError::SmtpError(_) => write!(f, "通过 SMTP 发送邮件失败"),
+            EmailError::HttpError(_) => write!(f, "通过 HTTP API 发送邮件失败"),
+            EmailError::TemplateError(_) => write!(f, "渲染邮件模板失败"),
+            EmailError::Suppressed => write!(f, "收件人在抑制列表中"),
+            EmailError::SuppressionCheckError(_) => write!(f, "检查抑制列表失败"),
+        }
+    }
+}
+
+impl Debug for EmailError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl Error for EmailError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            EmailError::AddressError(e) => Some(e),
+            EmailError::BuildError(e) => Some(e),
+            EmailError::IoError(e) => Some(e),
+            EmailError::SmtpError(e) => Some(e),
+            EmailError::HttpError(e) => Some(e),
+            EmailError::TemplateError(e) => Some(e),
+            EmailError::Suppressed => None,
+            EmailError::SuppressionCheckError(e) => Some(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use claim::assert_ok;
+    use fake::{
+        Fake,
+        faker::{
+            internet::en::SafeEmail,
+            lorem::en::{Paragraph, Sentence},
+        },
+    };
+
+    use super::{EmailClient, InMemoryEmailTransport};
+    use crate::domain::SubscriberEmail;
+
+    fn subject() -> String {
+        Sentence(1..2).fake()
+    }
+
+    fn content() -> String {
+        Paragraph(1..10).fake()
+    }
+
+    fn email() -> SubscriberEmail {
+        SubscriberEmail::parse(SafeEmail().fake()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn send_email_hands_the_message_to_the_transport() {
+        let transport = Arc::new(InMemoryEmailTransport::default());
+        let sender: String = SafeEmail().fake();
+        let email_client = EmailClient::new(sender.clone(), transport.clone());
+        let recipient = email();
+        let recipient_address = recipient.as_ref().to_string();
+        let subject = subject();
+
+        let outcome = email_client
+            .send_email(recipient, &subject, &content(), &content())
+            .await;
+
+        assert_ok!(outcome);
+        let messages = transport.messages();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].from, sender);
+        assert_eq!(messages[0].to, recipient_address);
+        assert_eq!(messages[0].subject, subject);
+    }
+}
@@ -0,0 +1,83 @@
+use std::time::Duration;
+
+use lettre::{
+    AsyncSmtpTransport, AsyncTransport, Tokio1Executor,
+    transport::smtp::{PoolConfig, authentication::Credentials},
+};
+use secrecy::{ExposeSecret, SecretString};
+
+use super::{Email, EmailError, EmailTransport};
+
+/// 通过 SMTP 中继异步发送邮件，连接由连接池复用
+#[derive(Debug)]
+pub struct SmtpEmailTransport {
+    smtp_transport: AsyncSmtpTransport<Tokio1Executor>,
+}
+
+impl SmtpEmailTransport {
+    pub fn new(
+        username: String,
+        password: SecretString,
+        base_url: &str,
+        timeout: Duration,
+        pool_max_size: u32,
+    ) -> Result<Self, lettre::transport::smtp::Error> {
+        let creds = Credentials::new(username, password.expose_secret().to_string());
+
+        let mailer = AsyncSmtpTransport::<Tokio1Executor>::relay(base_url)?
+            .credentials(creds)
+            .timeout(Some(timeout))
+            .pool_config(PoolConfig::new().max_size(pool_max_size))
+            .build();
+
+        Ok(Self {
+            smtp_transport: mailer,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailTransport for SmtpEmailTransport {
+    async fn send(&self, email: &Email) -> Result<(), EmailError> {
+        let message = email.to_message()?;
+        self.smtp_transport
+            .send(message)
+            .await
+            .map_err(EmailError::SmtpError)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use claim::assert_err;
+    use secrecy::SecretString;
+
+    use super::SmtpEmailTransport;
+    use crate::email_client::{Email, EmailTransport};
+
+    #[tokio::test]
+    async fn send_fails_instead_of_panicking_if_the_server_is_unreachable() {
+        let transport = SmtpEmailTransport::new(
+            "user@example.com".into(),
+            SecretString::from("password"),
+            "127.0.0.1",
+            Duration::from_millis(200),
+            1,
+        )
+        .unwrap();
+        let email = Email {
+            from: "sender@example.com".into(),
+            to: "recipient@example.com".into(),
+            subject: "subject".into(),
+            html_content: "<p>content</p>".into(),
+            text_content: "content".into(),
+        };
+
+        let outcome = transport.send(&email).await;
+
+        assert_err!(outcome);
+    }
+}
@@ -0,0 +1,17 @@
+use sea_orm::entity::prelude::*;
+
+/// 一封新闻邮件发给一个订阅者的记录，在邮件发送成功后写入
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "email_deliveries")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub delivery_id: uuid::Uuid,
+    pub newsletter_issue_id: uuid::Uuid,
+    pub subscriber_id: uuid::Uuid,
+    pub sent_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
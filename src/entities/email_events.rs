@@ -0,0 +1,20 @@
+use sea_orm::entity::prelude::*;
+
+use crate::domain::EmailEventKind;
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "email_events")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub event_id: i64,
+    pub delivery_id: uuid::Uuid,
+    pub kind: EmailEventKind,
+    pub occurred_at: DateTimeUtc,
+    /// 点击事件中被点击的原始链接
+    pub url: Option<String>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
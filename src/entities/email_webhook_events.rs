@@ -0,0 +1,24 @@
+use sea_orm::entity::prelude::*;
+
+use crate::domain::WebhookEventKind;
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "email_webhook_events")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub event_id: i64,
+    /// 事件中的地址不属于任何订阅者时为空
+    pub subscriber_id: Option<uuid::Uuid>,
+    pub email: String,
+    pub kind: WebhookEventKind,
+    /// 该事件是否改变了订阅者的状态，软退信从最近一次状态变化之后开始累计
+    pub status_changed: bool,
+    /// 服务商推送的原始 JSON
+    pub payload: Json,
+    pub received_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
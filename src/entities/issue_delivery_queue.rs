@@ -0,0 +1,17 @@
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "issue_delivery_queue")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub newsletter_issue_id: uuid::Uuid,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub subscriber_email: String,
+    pub n_retries: i32,
+    pub execute_after: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,19 @@
+use sea_orm::entity::prelude::*;
+
+use crate::domain::SubscriptionStatus;
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "list_memberships")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub list_id: uuid::Uuid,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub subscriber_id: uuid::Uuid,
+    pub status: SubscriptionStatus,
+    pub created_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
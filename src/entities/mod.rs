@@ -1,2 +1,15 @@
+pub mod email_deliveries;
+pub mod email_events;
+pub mod email_webhook_events;
+pub mod idempotency;
+pub mod issue_delivery_queue;
+pub mod list_memberships;
+pub mod lists;
+pub mod newsletter_issue_lists;
+pub mod newsletter_issues;
+pub mod rate_limit_buckets;
+pub mod sessions;
 pub mod subscriptions;
+pub mod suppressions;
 pub mod subscription_tokens;
+pub mod users;
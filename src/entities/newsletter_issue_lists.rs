@@ -0,0 +1,15 @@
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "newsletter_issue_lists")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub newsletter_issue_id: uuid::Uuid,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub list_id: uuid::Uuid,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,27 @@
+use sea_orm::entity::prelude::*;
+
+use crate::domain::IssueStatus;
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "newsletter_issues")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub newsletter_issue_id: uuid::Uuid,
+    pub title: String,
+    pub text_content: String,
+    pub html_content: String,
+    /// 开始发送的时间，计划发送的新闻邮件在开始发送前为计划时间
+    pub published_at: DateTimeUtc,
+    pub status: IssueStatus,
+    /// 立即发布的新闻邮件为空
+    pub scheduled_at: Option<DateTimeUtc>,
+    /// 是否在邮件中注入打开跟踪像素，还受全局配置 `tracking.open_tracking_enabled` 控制
+    pub track_opens: bool,
+    /// 是否把 HTML 正文中的链接改写为点击跟踪链接，还受全局配置 `tracking.click_tracking_enabled` 控制
+    pub track_clicks: bool,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
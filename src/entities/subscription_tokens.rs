@@ -1,11 +1,15 @@
 use sea_orm::entity::prelude::*;
 
 #[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
-#[sea_orm(table_name = "subscriptions_tokens")]
+#[sea_orm(table_name = "subscription_tokens")]
 pub struct Model {
     #[sea_orm(primary_key)]
     pub subscription_token: String,
     pub subscriber_id: uuid::Uuid,
+    /// 令牌确认的是订阅者在该列表中的订阅
+    pub list_id: uuid::Uuid,
+    pub created_at: DateTimeUtc,
+    pub expires_at: DateTimeUtc,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -1,14 +1,23 @@
 use sea_orm::entity::prelude::*;
 
+use crate::domain::SubscriptionStatus;
+
 #[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
 #[sea_orm(table_name = "subscriptions")]
 pub struct Model {
     #[sea_orm(primary_key)]
     pub id: uuid::Uuid,
     pub email: String,
+    /// 经过 [`crate::domain::normalize_email`] 规范化的地址，唯一
+    pub canonical_email: String,
+    /// 经过 [`crate::domain::email_alias_key`] 处理的地址，用于提示可能重复的订阅者
+    pub alias_key: String,
     pub name: String,
     pub subscribed_at: DateTimeUtc,
-    pub status: String,
+    pub status: SubscriptionStatus,
+    /// 最近一次状态变化的时间，由数据库触发器维护
+    pub status_changed_at: DateTimeUtc,
+    pub locale: String,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
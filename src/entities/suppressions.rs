@@ -0,0 +1,19 @@
+use sea_orm::entity::prelude::*;
+
+use crate::domain::SuppressionSource;
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "suppressions")]
+pub struct Model {
+    /// 经过 [`crate::domain::normalize_email`] 规范化的地址
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub email: String,
+    pub reason: String,
+    pub source: SuppressionSource,
+    pub created_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,209 @@
+use std::fmt::{Display, Formatter};
+
+use axum::{
+    body::Body,
+    http::{HeaderMap, HeaderValue, header},
+    response::{IntoResponse, Response},
+};
+use fluent_bundle::{FluentArgs, FluentResource, concurrent::FluentBundle};
+use once_cell::sync::Lazy;
+use unic_langid::LanguageIdentifier;
+
+/// 系统支持的语言，默认为简体中文
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Locale {
+    #[default]
+    ZhCn,
+    En,
+}
+
+impl Locale {
+    pub const ALL: [Locale; 2] = [Locale::ZhCn, Locale::En];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Locale::ZhCn => "zh-CN",
+            Locale::En => "en",
+        }
+    }
+
+    /// 解析语言标签，只比较主语言子标签，例如 `en-US` 会解析为 [`Locale::En`]
+    pub fn parse(tag: &str) -> Option<Self> {
+        let language = tag.trim().split(['-', '_']).next()?.to_ascii_lowercase();
+        match language.as_str() {
+            "zh" => Some(Locale::ZhCn),
+            "en" => Some(Locale::En),
+            _ => None,
+        }
+    }
+
+    /// 按权重从 `Accept-Language` 请求头中选出第一个支持的语言
+    pub fn from_accept_language(value: &str) -> Option<Self> {
+        let mut candidates: Vec<(&str, f32)> = value
+            .split(',')
+            .filter_map(|item| {
+                let mut parts = item.split(';');
+                let tag = parts.next()?.trim();
+                let quality = parts
+                    .find_map(|param| param.trim().strip_prefix("q="))
+                    .map_or(Some(1.0), |q| q.trim().parse::<f32>().ok())?;
+                (!tag.is_empty() && quality > 0.0).then_some((tag, quality))
+            })
+            .collect();
+        candidates.sort_by(|a, b| b.1.total_cmp(&a.1));
+        candidates.into_iter().find_map(|(tag, _)| Self::parse(tag))
+    }
+
+    /// 依次使用显式指定的语言和 `Accept-Language` 请求头，都不可用时使用默认语言
+    pub fn negotiate(explicit: Option<&str>, headers: &HeaderMap) -> Self {
+        explicit
+            .and_then(Self::parse)
+            .or_else(|| {
+                headers
+                    .get(header::ACCEPT_LANGUAGE)
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(Self::from_accept_language)
+            })
+            .unwrap_or_default()
+    }
+
+    /// 从 Fluent 目录中取出翻译后的文本，缺少对应条目时返回条目名
+    pub fn translate(&self, key: &str, args: Option<&FluentArgs>) -> String {
+        let bundle = CATALOGUE.bundle(*self);
+        let Some(pattern) = bundle.get_message(key).and_then(|message| message.value()) else {
+            tracing::warn!(locale = self.as_str(), key, "缺少翻译条目");
+            return key.to_string();
+        };
+
+        let mut errors = vec![];
+        let text = bundle.format_pattern(pattern, args, &mut errors);
+        if !errors.is_empty() {
+            tracing::warn!(locale = self.as_str(), key, ?errors, "翻译条目格式化失败");
+        }
+        text.into_owned()
+    }
+}
+
+impl Display for Locale {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+struct Catalogue {
+    zh_cn: FluentBundle<FluentResource>,
+    en: FluentBundle<FluentResource>,
+}
+
+impl Catalogue {
+    fn bundle(&self, locale: Locale) -> &FluentBundle<FluentResource> {
+        match locale {
+            Locale::ZhCn => &self.zh_cn,
+            Locale::En => &self.en,
+        }
+    }
+}
+
+static CATALOGUE: Lazy<Catalogue> = Lazy::new(|| Catalogue {
+    zh_cn: bundle(Locale::ZhCn, include_str!("../locales/zh-CN/main.ftl")),
+    en: bundle(Locale::En, include_str!("../locales/en/main.ftl")),
+});
+
+fn bundle(locale: Locale, source: &str) -> FluentBundle<FluentResource> {
+    let language: LanguageIdentifier = locale.as_str().parse().expect("无效的语言标签");
+    let resource = FluentResource::try_new(source.to_string()).expect("Fluent 文件解析失败");
+    let mut bundle = FluentBundle::new_concurrent(vec![language]);
+    // 邮件和纯文本响应中不需要 Unicode 双向隔离字符
+    bundle.set_use_isolating(false);
+    bundle.add_resource(resource).expect("Fluent 条目重复");
+    bundle
+}
+
+/// 可以按语言渲染给用户看的错误
+pub trait Localize {
+    fn localize(&self, locale: Locale) -> String;
+}
+
+/// 把错误和请求方的语言绑定在一起，响应体使用该语言的错误信息
+pub struct Localized<E> {
+    pub locale: Locale,
+    pub error: E,
+}
+
+impl<E> Localized<E> {
+    pub fn new(locale: Locale, error: E) -> Self {
+        Self { locale, error }
+    }
+}
+
+impl<E: Localize + IntoResponse> IntoResponse for Localized<E> {
+    fn into_response(self) -> Response {
+        let message = self.error.localize(self.locale);
+        let (mut parts, _) = self.error.into_response().into_parts();
+        parts.headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("text/plain; charset=utf-8"),
+        );
+        parts.headers.insert(
+            header::CONTENT_LANGUAGE,
+            HeaderValue::from_static(self.locale.as_str()),
+        );
+        Response::from_parts(parts, Body::from(message))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{HeaderMap, HeaderValue, header};
+    use fluent_bundle::FluentArgs;
+
+    use super::Locale;
+
+    #[test]
+    fn accept_language_picks_the_highest_weighted_supported_language() {
+        assert_eq!(
+            Locale::from_accept_language("fr-CH, fr;q=0.9, en;q=0.8, zh-CN;q=0.7"),
+            Some(Locale::En)
+        );
+        assert_eq!(
+            Locale::from_accept_language("en;q=0.5, zh-TW"),
+            Some(Locale::ZhCn)
+        );
+        assert_eq!(Locale::from_accept_language("fr, de;q=0.5"), None);
+        assert_eq!(Locale::from_accept_language("en;q=0"), None);
+    }
+
+    #[test]
+    fn an_explicit_locale_takes_precedence_over_accept_language() {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ACCEPT_LANGUAGE, HeaderValue::from_static("en-US"));
+
+        assert_eq!(Locale::negotiate(Some("zh-CN"), &headers), Locale::ZhCn);
+        assert_eq!(Locale::negotiate(Some("klingon"), &headers), Locale::En);
+        assert_eq!(Locale::negotiate(None, &HeaderMap::new()), Locale::ZhCn);
+    }
+
+    #[test]
+    fn every_locale_translates_every_key_of_the_default_catalogue() {
+        let source = include_str!("../locales/zh-CN/main.ftl");
+        let keys = source
+            .lines()
+            .filter(|line| !line.starts_with('#') && !line.starts_with(' '))
+            .filter_map(|line| line.split_once(" = ").map(|(key, _)| key));
+        for key in keys {
+            for locale in Locale::ALL {
+                assert_ne!(locale.translate(key, None), key, "{} 缺少 {}", locale, key);
+            }
+        }
+    }
+
+    #[test]
+    fn arguments_are_interpolated_without_isolation_marks() {
+        let mut args = FluentArgs::new();
+        args.set("name", "le guin");
+
+        let text = Locale::En.translate("welcome-heading", Some(&args));
+
+        assert_eq!(text, "le guin, your subscription is confirmed");
+    }
+}
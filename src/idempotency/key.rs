@@ -0,0 +1,79 @@
+use axum::http::HeaderMap;
+
+/// 客户端提供的幂等键，按管理员用户隔离
+#[derive(Debug)]
+pub struct IdempotencyKey(String);
+
+impl IdempotencyKey {
+    pub const HEADER_NAME: &'static str = "Idempotency-Key";
+
+    /// 优先读取 `Idempotency-Key` 请求头，没有时使用表单字段
+    pub fn from_request(headers: &HeaderMap, form_value: Option<String>) -> Result<Self, String> {
+        let header_value = headers
+            .get(Self::HEADER_NAME)
+            .map(|v| {
+                v.to_str()
+                    .map(|s| s.to_string())
+                    .map_err(|_| "Idempotency-Key 请求头不是合法的字符串".to_string())
+            })
+            .transpose()?;
+
+        header_value
+            .or(form_value)
+            .ok_or_else(|| "缺少幂等键".to_string())?
+            .try_into()
+    }
+}
+
+impl TryFrom<String> for IdempotencyKey {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        if s.is_empty() {
+            return Err("幂等键不能为空".into());
+        }
+        let max_length = 50;
+        if s.len() >= max_length {
+            return Err(format!("幂等键长度必须小于 {} 个字符", max_length));
+        }
+        Ok(Self(s))
+    }
+}
+
+impl AsRef<str> for IdempotencyKey {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderMap;
+    use claim::{assert_err, assert_ok};
+
+    use super::IdempotencyKey;
+
+    #[test]
+    fn empty_key_is_rejected() {
+        assert_err!(IdempotencyKey::try_from("".to_string()));
+    }
+
+    #[test]
+    fn a_key_with_50_characters_is_rejected() {
+        assert_err!(IdempotencyKey::try_from("a".repeat(50)));
+    }
+
+    #[test]
+    fn missing_header_and_form_field_is_rejected() {
+        assert_err!(IdempotencyKey::from_request(&HeaderMap::new(), None));
+    }
+
+    #[test]
+    fn the_header_takes_precedence_over_the_form_field() {
+        let mut headers = HeaderMap::new();
+        headers.insert(IdempotencyKey::HEADER_NAME, "from-header".parse().unwrap());
+        let key = IdempotencyKey::from_request(&headers, Some("from-form".into()));
+        assert_ok!(&key);
+        assert_eq!(key.unwrap().as_ref(), "from-header");
+    }
+}
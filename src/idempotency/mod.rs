@@ -0,0 +1,5 @@
+mod key;
+mod persistence;
+
+pub use key::IdempotencyKey;
+pub use persistence::{IdempotencyError, NextAction, save_response, try_processing};
@@ -0,0 +1,191 @@
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+
+use axum::{
+    body::{Body, to_bytes},
+    http::{HeaderName, HeaderValue, StatusCode},
+    response::Response,
+};
+use sea_orm::{
+    ActiveValue::Set, ColumnTrait, DatabaseConnection, DatabaseTransaction, DbErr, EntityTrait,
+    QueryFilter, TransactionTrait, sea_query::{Expr, OnConflict},
+};
+
+use super::IdempotencyKey;
+use crate::{entities::idempotency, routes::error_chain_fmt};
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct HeaderPair {
+    name: String,
+    value: Vec<u8>,
+}
+
+pub enum NextAction {
+    /// 首次处理该请求，处理完成后需要调用 [`save_response`] 提交事务
+    StartProcessing(DatabaseTransaction),
+    ReturnSavedResponse(Response),
+}
+
+/// 尝试占用幂等键
+///
+/// 插入语句会在事务中持有该键的行锁，并发的重复请求会阻塞在插入上，
+/// 直到第一个请求提交了保存的响应，随后直接返回该响应
+#[tracing::instrument(name = "尝试占用幂等键", skip(db))]
+pub async fn try_processing(
+    db: &DatabaseConnection,
+    idempotency_key: &IdempotencyKey,
+    user_id: uuid::Uuid,
+) -> Result<NextAction, IdempotencyError> {
+    let txn = db.begin().await.map_err(IdempotencyError::DbError)?;
+
+    let row = idempotency::ActiveModel {
+        user_id: Set(user_id),
+        idempotency_key: Set(idempotency_key.as_ref().to_string()),
+        response_status_code: Set(None),
+        response_headers: Set(None),
+        response_body: Set(None),
+        created_at: Set(chrono::Utc::now()),
+    };
+    let n_inserted_rows = idempotency::Entity::insert(row)
+        .on_conflict(
+            OnConflict::columns([
+                idempotency::Column::UserId,
+                idempotency::Column::IdempotencyKey,
+            ])
+            .do_nothing()
+            .to_owned(),
+        )
+        .exec_without_returning(&txn)
+        .await
+        .map_err(IdempotencyError::DbError)?;
+
+    if n_inserted_rows > 0 {
+        return Ok(NextAction::StartProcessing(txn));
+    }
+
+    txn.rollback().await.map_err(IdempotencyError::DbError)?;
+    let saved_response = get_saved_response(db, idempotency_key, user_id)
+        .await?
+        .ok_or(IdempotencyError::RequestInProgress)?;
+    Ok(NextAction::ReturnSavedResponse(saved_response))
+}
+
+#[tracing::instrument(name = "获取保存的响应", skip(db))]
+async fn get_saved_response(
+    db: &DatabaseConnection,
+    idempotency_key: &IdempotencyKey,
+    user_id: uuid::Uuid,
+) -> Result<Option<Response>, IdempotencyError> {
+    let saved = idempotency::Entity::find_by_id((user_id, idempotency_key.as_ref().to_string()))
+        .filter(idempotency::Column::ResponseStatusCode.is_not_null())
+        .one(db)
+        .await
+        .map_err(IdempotencyError::DbError)?;
+
+    let Some(saved) = saved else {
+        return Ok(None);
+    };
+
+    let status_code = saved
+        .response_status_code
+        .and_then(|code| u16::try_from(code).ok())
+        .and_then(|code| StatusCode::from_u16(code).ok())
+        .ok_or_else(|| IdempotencyError::InvalidSavedResponse("状态码无效".into()))?;
+    let headers: Vec<HeaderPair> = serde_json::from_value(saved.response_headers.unwrap_or_default())
+        .map_err(|e| IdempotencyError::InvalidSavedResponse(e.to_string()))?;
+
+    let mut response = Response::builder().status(status_code);
+    for HeaderPair { name, value } in headers {
+        let name = HeaderName::try_from(name)
+            .map_err(|e| IdempotencyError::InvalidSavedResponse(e.to_string()))?;
+        let value = HeaderValue::from_bytes(&value)
+            .map_err(|e| IdempotencyError::InvalidSavedResponse(e.to_string()))?;
+        response = response.header(name, value);
+    }
+
+    response
+        .body(Body::from(saved.response_body.unwrap_or_default()))
+        .map(Some)
+        .map_err(|e| IdempotencyError::InvalidSavedResponse(e.to_string()))
+}
+
+/// 保存响应并提交 [`try_processing`] 开启的事务
+#[tracing::instrument(name = "保存响应", skip(txn, response))]
+pub async fn save_response(
+    txn: DatabaseTransaction,
+    idempotency_key: &IdempotencyKey,
+    user_id: uuid::Uuid,
+    response: Response,
+) -> Result<Response, IdempotencyError> {
+    let (parts, body) = response.into_parts();
+    let body = to_bytes(body, usize::MAX)
+        .await
+        .map_err(IdempotencyError::BodyError)?;
+
+    let status_code = parts.status.as_u16() as i16;
+    let headers: Vec<HeaderPair> = parts
+        .headers
+        .iter()
+        .map(|(name, value)| HeaderPair {
+            name: name.as_str().to_owned(),
+            value: value.as_bytes().to_owned(),
+        })
+        .collect();
+    let headers = serde_json::to_value(headers)
+        .map_err(|e| IdempotencyError::InvalidSavedResponse(e.to_string()))?;
+
+    idempotency::Entity::update_many()
+        .col_expr(
+            idempotency::Column::ResponseStatusCode,
+            Expr::value(status_code),
+        )
+        .col_expr(idempotency::Column::ResponseHeaders, Expr::value(headers))
+        .col_expr(
+            idempotency::Column::ResponseBody,
+            Expr::value(body.to_vec()),
+        )
+        .filter(idempotency::Column::UserId.eq(user_id))
+        .filter(idempotency::Column::IdempotencyKey.eq(idempotency_key.as_ref()))
+        .exec(&txn)
+        .await
+        .map_err(IdempotencyError::DbError)?;
+    txn.commit().await.map_err(IdempotencyError::DbError)?;
+
+    Ok(Response::from_parts(parts, Body::from(body)))
+}
+
+pub enum IdempotencyError {
+    DbError(DbErr),
+    BodyError(axum::Error),
+    InvalidSavedResponse(String),
+    RequestInProgress,
+}
+
+impl Display for IdempotencyError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IdempotencyError::DbError(_) => write!(f, "读写幂等记录时发生数据库错误"),
+            IdempotencyError::BodyError(_) => write!(f, "读取响应体失败"),
+            IdempotencyError::InvalidSavedResponse(e) => write!(f, "保存的响应无效: {}", e),
+            IdempotencyError::RequestInProgress => write!(f, "相同幂等键的请求仍在处理中"),
+        }
+    }
+}
+
+impl Debug for IdempotencyError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl Error for IdempotencyError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            IdempotencyError::DbError(e) => Some(e),
+            IdempotencyError::BodyError(e) => Some(e),
+            IdempotencyError::InvalidSavedResponse(_) | IdempotencyError::RequestInProgress => {
+                None
+            }
+        }
+    }
+}
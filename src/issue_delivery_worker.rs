@@ -0,0 +1,280 @@
+use std::time::Duration;
+
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::Set, ColumnTrait, Database, DatabaseConnection,
+    DatabaseTransaction, DbErr, EntityTrait, IntoActiveModel, ModelTrait, QueryFilter, QueryOrder,
+    QuerySelect, TransactionTrait,
+    sea_query::{LockBehavior, LockType},
+};
+use tracing::{Span, field::display};
+
+use crate::{
+    configuration::{Settings, TrackingSettings},
+    domain::{SubscriberEmail, SubscriptionStatus, normalize_email},
+    email_client::{EmailClient, EmailError},
+    entities::{email_deliveries, issue_delivery_queue, newsletter_issues, subscriptions},
+    i18n::Locale,
+    routes::{
+        tracking::{open_tracking_url, track_links},
+        unsubscribe::unsubscribe_link,
+    },
+    startup::{ApplicationBaseUrl, HmacSecret},
+    suppression_list::SuppressionList,
+    templates::{EmailTemplate, EmailTemplates},
+};
+
+/// 单个投递任务的最大重试次数，超过后放弃该任务
+const MAX_RETRIES: i32 = 5;
+
+pub enum ExecutionOutcome {
+    TaskCompleted,
+    EmptyQueue,
+}
+
+/// 投递新闻邮件所需的依赖
+pub struct DeliveryContext {
+    pub db: DatabaseConnection,
+    pub email_client: EmailClient,
+    pub templates: EmailTemplates,
+    pub base_url: ApplicationBaseUrl,
+    pub hmac_secret: HmacSecret,
+    pub tracking: TrackingSettings,
+}
+
+pub async fn run_worker_until_stopped(configuration: Settings) -> Result<(), std::io::Error> {
+    let db = Database::connect(configuration.database.with_db())
+        .await
+        .map_err(std::io::Error::other)?;
+    let templates =
+        EmailTemplates::new(&configuration.templates).map_err(std::io::Error::other)?;
+    let email_client = configuration
+        .email_client
+        .client()
+        .map_err(std::io::Error::other)?
+        .with_suppression_list(SuppressionList::new(db.clone()));
+    let context = DeliveryContext {
+        db,
+        email_client,
+        templates,
+        base_url: ApplicationBaseUrl(configuration.application.base_url),
+        hmac_secret: HmacSecret::derive(&configuration.application.hmac_secret),
+        tracking: configuration.tracking,
+    };
+    worker_loop(context).await.map_err(std::io::Error::other)
+}
+
+async fn worker_loop(context: DeliveryContext) -> Result<(), DbErr> {
+    loop {
+        match try_execute_task(&context).await {
+            Ok(ExecutionOutcome::EmptyQueue) => {
+                tokio::time::sleep(Duration::from_secs(10)).await;
+            }
+            Err(_) => {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+            Ok(ExecutionOutcome::TaskCompleted) => {}
+        }
+    }
+}
+
+/// 从队列中取出一个到期的投递任务并发送邮件
+///
+/// 使用 `FOR UPDATE SKIP LOCKED` 加锁，多个 worker 可以并发消费同一个队列。
+/// 入队之后才退订的订阅者不会再收到这封邮件
+#[tracing::instrument(
+    skip_all,
+    fields(newsletter_issue_id = tracing::field::Empty, subscriber_email = tracing::field::Empty),
+    err
+)]
+pub async fn try_execute_task(context: &DeliveryContext) -> Result<ExecutionOutcome, DbErr> {
+    let Some((txn, task)) = dequeue_task(&context.db).await? else {
+        return Ok(ExecutionOutcome::EmptyQueue);
+    };
+    Span::current()
+        .record("newsletter_issue_id", display(task.newsletter_issue_id))
+        .record("subscriber_email", display(&task.subscriber_email));
+
+    let Some(subscriber) = get_confirmed_subscriber(&txn, &task.subscriber_email).await? else {
+        tracing::info!("订阅者已退订或不存在, 跳过投递");
+        delete_task(txn, task).await?;
+        return Ok(ExecutionOutcome::TaskCompleted);
+    };
+
+    match SubscriberEmail::parse(task.subscriber_email.clone()) {
+        Ok(email) => {
+            let issue = get_issue(&txn, task.newsletter_issue_id).await?;
+            let delivery_id = uuid::Uuid::new_v4();
+            match send_issue(context, email, &subscriber, &issue, delivery_id).await {
+                Ok(()) => {
+                    record_delivery(&txn, delivery_id, &issue, &subscriber).await?;
+                    delete_task(txn, task).await?
+                }
+                Err(EmailError::Suppressed) => {
+                    tracing::info!("订阅者在抑制列表中, 跳过投递");
+                    delete_task(txn, task).await?;
+                }
+                Err(e) => {
+                    tracing::error!(
+                        error.cause_chain = ?e,
+                        error.message = %e,
+                        "向已确认的订阅者投递新闻邮件失败",
+                    );
+                    retry_later(txn, task).await?;
+                }
+            }
+        }
+        Err(e) => {
+            tracing::error!(
+                error.cause_chain = ?e,
+                "跳过一个已确认的订阅者, 其存储的邮箱无效",
+            );
+            delete_task(txn, task).await?;
+        }
+    }
+
+    Ok(ExecutionOutcome::TaskCompleted)
+}
+
+#[tracing::instrument(skip_all)]
+async fn dequeue_task(
+    db: &DatabaseConnection,
+) -> Result<Option<(DatabaseTransaction, issue_delivery_queue::Model)>, DbErr> {
+    let txn = db.begin().await?;
+    let task = issue_delivery_queue::Entity::find()
+        .filter(issue_delivery_queue::Column::ExecuteAfter.lte(chrono::Utc::now()))
+        .order_by_asc(issue_delivery_queue::Column::ExecuteAfter)
+        .lock_with_behavior(LockType::Update, LockBehavior::SkipLocked)
+        .one(&txn)
+        .await?;
+
+    Ok(task.map(|task| (txn, task)))
+}
+
+#[tracing::instrument(skip_all)]
+async fn delete_task(
+    txn: DatabaseTransaction,
+    task: issue_delivery_queue::Model,
+) -> Result<(), DbErr> {
+    task.delete(&txn).await?;
+    txn.commit().await
+}
+
+/// 发送失败时按指数退避推迟任务，重试次数耗尽后丢弃
+#[tracing::instrument(skip_all)]
+async fn retry_later(
+    txn: DatabaseTransaction,
+    task: issue_delivery_queue::Model,
+) -> Result<(), DbErr> {
+    let n_retries = task.n_retries + 1;
+    if n_retries > MAX_RETRIES {
+        tracing::error!(n_retries = task.n_retries, "投递任务重试次数已耗尽, 放弃投递");
+        return delete_task(txn, task).await;
+    }
+
+    let backoff = chrono::Duration::seconds(2_i64.pow(n_retries as u32) * 30);
+    let mut task = task.into_active_model();
+    task.n_retries = Set(n_retries);
+    task.execute_after = Set(chrono::Utc::now() + backoff);
+    task.update(&txn).await?;
+    txn.commit().await
+}
+
+/// 使用订阅者的语言渲染新闻邮件，并附上该订阅者的退订链接
+///
+/// 新闻邮件和全局配置都开启了打开跟踪时，注入指向本次投递的跟踪像素；
+/// 开启了点击跟踪时，把 HTML 正文中的链接改写为带签名的跳转链接
+async fn send_issue(
+    context: &DeliveryContext,
+    recipient: SubscriberEmail,
+    subscriber: &subscriptions::Model,
+    issue: &newsletter_issues::Model,
+    delivery_id: uuid::Uuid,
+) -> Result<(), EmailError> {
+    let mut template_context = tera::Context::new();
+    template_context.insert("title", &issue.title);
+    if issue.track_clicks && context.tracking.click_tracking_enabled {
+        template_context.insert(
+            "html_content",
+            &track_links(
+                &issue.html_content,
+                &context.base_url,
+                &context.hmac_secret.click,
+                delivery_id,
+            ),
+        );
+    } else {
+        template_context.insert("html_content", &issue.html_content);
+    }
+    template_context.insert("text_content", &issue.text_content);
+    template_context.insert(
+        "unsubscribe_link",
+        &unsubscribe_link(
+            &context.base_url,
+            &context.hmac_secret.unsubscribe,
+            subscriber.id,
+        ),
+    );
+    if issue.track_opens && context.tracking.open_tracking_enabled {
+        template_context.insert(
+            "open_tracking_url",
+            &open_tracking_url(&context.base_url, delivery_id),
+        );
+    }
+    let locale = Locale::parse(&subscriber.locale).unwrap_or_default();
+    let email = context
+        .templates
+        .render(EmailTemplate::Newsletter, locale, &template_context)
+        .map_err(EmailError::TemplateError)?;
+
+    context
+        .email_client
+        .send_email(
+            recipient,
+            &email.subject,
+            &email.html_content,
+            &email.text_content,
+        )
+        .await
+}
+
+/// 记录发送成功的投递，打开等事件都关联到这条记录
+#[tracing::instrument(skip_all)]
+async fn record_delivery(
+    txn: &DatabaseTransaction,
+    delivery_id: uuid::Uuid,
+    issue: &newsletter_issues::Model,
+    subscriber: &subscriptions::Model,
+) -> Result<(), DbErr> {
+    email_deliveries::ActiveModel {
+        delivery_id: Set(delivery_id),
+        newsletter_issue_id: Set(issue.newsletter_issue_id),
+        subscriber_id: Set(subscriber.id),
+        sent_at: Set(chrono::Utc::now()),
+    }
+    .insert(txn)
+    .await?;
+    Ok(())
+}
+
+#[tracing::instrument(skip_all)]
+async fn get_confirmed_subscriber(
+    txn: &DatabaseTransaction,
+    email: &str,
+) -> Result<Option<subscriptions::Model>, DbErr> {
+    subscriptions::Entity::find()
+        .filter(subscriptions::Column::CanonicalEmail.eq(normalize_email(email)))
+        .filter(subscriptions::Column::Status.eq(SubscriptionStatus::Confirmed))
+        .one(txn)
+        .await
+}
+
+#[tracing::instrument(skip_all)]
+async fn get_issue(
+    txn: &DatabaseTransaction,
+    issue_id: uuid::Uuid,
+) -> Result<newsletter_issues::Model, DbErr> {
+    newsletter_issues::Entity::find_by_id(issue_id)
+        .one(txn)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound(format!("新闻邮件 {} 不存在", issue_id)))
+}
@@ -1,7 +1,18 @@
+pub mod authentication;
 pub mod configuration;
 pub mod entities;
+pub mod i18n;
+pub mod idempotency;
+pub mod issue_delivery_worker;
+pub mod newsletter_scheduler;
+pub mod rate_limit;
 pub mod routes;
+pub mod session_state;
+pub mod session_store;
 pub mod startup;
+pub mod suppression_list;
 pub mod telemetry;
+pub mod templates;
 pub mod domain;
 pub mod email_client;
+pub mod utils;
@@ -1,8 +1,15 @@
+use std::fmt::{Debug, Display};
+
 use my_zero2prod::{
-    configuration::get_configuration,
+    authentication::{Credentials, create_user},
+    configuration::{Settings, get_configuration},
+    issue_delivery_worker::run_worker_until_stopped,
     startup::Application,
     telemetry::{get_subscriber, init_subscriber},
 };
+use sea_orm::Database;
+use secrecy::SecretString;
+use tokio::task::JoinError;
 
 #[tokio::main]
 async fn main() -> std::io::Result<()> {
@@ -11,8 +18,82 @@ async fn main() -> std::io::Result<()> {
     init_subscriber(subscriber);
 
     let configuration = get_configuration().expect("Failed to read configuration.");
-    let app = Application::build(configuration).await.unwrap();
-    app.run_until_stopped().await;
+    let mut args = std::env::args().skip(1);
+    if args.next().as_deref() == Some("create-admin") {
+        return create_admin(configuration, args.next()).await;
+    }
+
+    let app = Application::build(configuration.clone()).await?;
+    let cleanup_task = tokio::spawn(app.run_cleanup_until_stopped());
+    let scheduler_task = tokio::spawn(app.run_scheduler_until_stopped());
+    let app_task = tokio::spawn(app.run_until_stopped());
+    let worker_task = tokio::spawn(run_worker_until_stopped(configuration));
+
+    // API 和各个后台任务任意一个退出，整个进程随之退出
+    tokio::select! {
+        outcome = app_task => report_exit("API", outcome),
+        outcome = worker_task => report_exit("后台投递任务", outcome),
+        outcome = scheduler_task => report_exit("计划发送任务", outcome),
+        outcome = cleanup_task => report_exit("后台清理任务", outcome),
+    };
 
     Ok(())
 }
+
+/// `create-admin <用户名>`：创建管理员账号，密码从 `ADMIN_PASSWORD` 环境变量读取，
+/// 未设置时从标准输入读取一行，避免出现在命令行参数和 shell 历史中
+async fn create_admin(configuration: Settings, username: Option<String>) -> std::io::Result<()> {
+    let username = username
+        .filter(|u| !u.trim().is_empty())
+        .ok_or_else(|| std::io::Error::other("用法：create-admin <用户名>"))?;
+    let password = match std::env::var("ADMIN_PASSWORD") {
+        Ok(password) => password,
+        Err(_) => {
+            let mut line = String::new();
+            std::io::stdin().read_line(&mut line)?;
+            line.trim_end_matches(['\r', '\n']).to_string()
+        }
+    };
+    if password.is_empty() {
+        return Err(std::io::Error::other("密码不能为空"));
+    }
+
+    let db = Database::connect(configuration.database.with_db())
+        .await
+        .map_err(std::io::Error::other)?;
+    let user_id = create_user(
+        Credentials {
+            username: username.clone(),
+            password: SecretString::from(password),
+        },
+        &db,
+    )
+    .await
+    .map_err(std::io::Error::other)?;
+    tracing::info!(%user_id, "已创建管理员 {}", username);
+    Ok(())
+}
+
+fn report_exit(task_name: &str, outcome: Result<Result<(), impl Debug + Display>, JoinError>) {
+    match outcome {
+        Ok(Ok(())) => {
+            tracing::info!("{} 已退出", task_name)
+        }
+        Ok(Err(e)) => {
+            tracing::error!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "{} 运行失败",
+                task_name
+            )
+        }
+        Err(e) => {
+            tracing::error!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "{} 任务无法完成",
+                task_name
+            )
+        }
+    }
+}
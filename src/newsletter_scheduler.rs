@@ -0,0 +1,104 @@
+use std::time::Duration;
+
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::Set, ColumnTrait, ConnectionTrait, DatabaseConnection,
+    DatabaseTransaction, DbBackend, DbErr, EntityTrait, QueryFilter, QueryOrder, QuerySelect,
+    Statement, TransactionTrait,
+};
+
+use crate::{domain::IssueStatus, entities::newsletter_issues};
+
+/// 调度任务使用的 advisory lock，多个实例中同一时刻只有一个实例执行调度
+pub const SCHEDULER_LOCK_ID: i64 = 0x6e65_7773_6c65_7474;
+
+/// 按固定间隔检查到期的计划发送，由 [`crate::startup::Application`] 创建并在 `main` 中运行
+///
+/// 单轮失败只记录错误，下一轮会重试
+pub async fn run_scheduler_until_stopped(
+    db: DatabaseConnection,
+    interval: Duration,
+) -> Result<(), std::io::Error> {
+    loop {
+        if let Err(e) = enqueue_due_issues(&db).await {
+            tracing::error!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "检查计划发送失败, 下一轮重试"
+            );
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+/// 为所有到期的计划发送创建投递任务，返回本轮开始发送的新闻邮件数量
+///
+/// 在事务中持有 advisory lock，其他实例拿不到锁时直接跳过本轮；
+/// 新闻邮件的状态与投递任务在同一个事务中更新，重启或并发运行都不会重复发送
+#[tracing::instrument(skip_all)]
+pub async fn enqueue_due_issues(db: &DatabaseConnection) -> Result<usize, DbErr> {
+    let txn = db.begin().await?;
+    if !try_lock_scheduler(&txn).await? {
+        tracing::debug!("其他实例正在执行调度, 跳过本轮");
+        return Ok(0);
+    }
+
+    let now = chrono::Utc::now();
+    let due_issues = newsletter_issues::Entity::find()
+        .filter(newsletter_issues::Column::Status.eq(IssueStatus::Scheduled))
+        .filter(newsletter_issues::Column::ScheduledAt.lte(now))
+        .order_by_asc(newsletter_issues::Column::ScheduledAt)
+        .lock_exclusive()
+        .all(&txn)
+        .await?;
+    let n_issues = due_issues.len();
+    for issue in due_issues {
+        tracing::info!(newsletter_issue_id = %issue.newsletter_issue_id, "计划发送的新闻邮件已到期");
+        enqueue_delivery_tasks(&txn, issue.newsletter_issue_id).await?;
+        let mut issue: newsletter_issues::ActiveModel = issue.into();
+        issue.status = Set(IssueStatus::Enqueued);
+        issue.published_at = Set(now);
+        issue.update(&txn).await?;
+    }
+    txn.commit().await?;
+
+    Ok(n_issues)
+}
+
+async fn try_lock_scheduler(txn: &DatabaseTransaction) -> Result<bool, DbErr> {
+    let row = txn
+        .query_one(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            "SELECT pg_try_advisory_xact_lock($1) AS locked",
+            [SCHEDULER_LOCK_ID.into()],
+        ))
+        .await?
+        .ok_or_else(|| DbErr::Custom("获取调度锁没有返回结果".into()))?;
+    row.try_get("", "locked")
+}
+
+/// 为新闻邮件目标列表中每个已确认的订阅者创建一条投递任务，由后台 worker 负责实际发送
+///
+/// 同时订阅了多个目标列表的订阅者只会收到一封邮件
+#[tracing::instrument(name = "创建投递任务", skip(txn))]
+pub async fn enqueue_delivery_tasks(
+    txn: &DatabaseTransaction,
+    newsletter_issue_id: uuid::Uuid,
+) -> Result<(), DbErr> {
+    txn.execute(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        r#"
+            INSERT INTO issue_delivery_queue (newsletter_issue_id, subscriber_email)
+            SELECT DISTINCT $1, s.email
+            FROM subscriptions s
+            JOIN list_memberships m ON m.subscriber_id = s.id
+            JOIN newsletter_issue_lists l ON l.list_id = m.list_id
+            WHERE l.newsletter_issue_id = $1
+                AND m.status = 'confirmed'
+                AND s.status = 'confirmed'
+        "#,
+        [newsletter_issue_id.into()],
+    ))
+    .await?;
+
+    Ok(())
+}
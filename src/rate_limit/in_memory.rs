@@ -0,0 +1,44 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use sea_orm::DbErr;
+
+use super::{RateLimit, RateLimitStore, RateLimited};
+
+/// 保存在进程内存中的令牌桶，只适用于单实例部署
+#[derive(Debug, Default)]
+pub struct InMemoryRateLimitStore {
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+#[async_trait::async_trait]
+impl RateLimitStore for InMemoryRateLimitStore {
+    async fn acquire(&self, key: &str, limit: &RateLimit) -> Result<Result<(), RateLimited>, DbErr> {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+        let bucket = buckets.entry(key.to_string()).or_insert(Bucket {
+            tokens: f64::from(limit.capacity),
+            updated_at: now,
+        });
+        let (tokens, outcome) = limit.take(bucket.tokens, now - bucket.updated_at);
+        bucket.tokens = tokens;
+        bucket.updated_at = now;
+        Ok(outcome)
+    }
+
+    async fn delete_idle(&self, max_idle: Duration) -> Result<(), DbErr> {
+        let now = Instant::now();
+        self.buckets
+            .lock()
+            .unwrap()
+            .retain(|_, bucket| now - bucket.updated_at < max_idle);
+        Ok(())
+    }
+}
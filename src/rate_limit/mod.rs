@@ -0,0 +1,257 @@
+use std::fmt::{Debug, Display, Formatter};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{HeaderMap, StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use fluent_bundle::FluentArgs;
+use sea_orm::DbErr;
+
+use crate::{
+    domain::{SubscriberEmail, normalize_email},
+    i18n::{Locale, Localize, Localized},
+};
+
+mod in_memory;
+mod postgres;
+
+pub use in_memory::InMemoryRateLimitStore;
+pub use postgres::PostgresRateLimitStore;
+
+/// 令牌桶限制：桶中最多存放 `capacity` 个令牌，每经过 `period` 补满一次
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    pub capacity: u32,
+    pub period: Duration,
+}
+
+impl RateLimit {
+    /// 每秒补充的令牌数
+    fn refill_rate(&self) -> f64 {
+        f64::from(self.capacity) / self.period.as_secs_f64()
+    }
+
+    /// 在距离上次取用 `elapsed` 之后，从剩余 `tokens` 个令牌的桶中取出一个
+    ///
+    /// 返回取用后桶中的令牌数；桶中不足一个令牌时返回需要等待的时间
+    pub fn take(&self, tokens: f64, elapsed: Duration) -> (f64, Result<(), RateLimited>) {
+        let rate = self.refill_rate();
+        let tokens = (tokens + elapsed.as_secs_f64() * rate).min(f64::from(self.capacity));
+        if tokens >= 1.0 {
+            (tokens - 1.0, Ok(()))
+        } else {
+            let retry_after = Duration::from_secs_f64((1.0 - tokens) / rate);
+            (tokens, Err(RateLimited { retry_after }))
+        }
+    }
+}
+
+/// 保存各个令牌桶的状态，多实例部署时需要使用共享的后端
+#[async_trait::async_trait]
+pub trait RateLimitStore: Debug + Send + Sync {
+    /// 从 `key` 对应的桶中取出一个令牌，桶不存在时视为满桶
+    async fn acquire(&self, key: &str, limit: &RateLimit) -> Result<Result<(), RateLimited>, DbErr>;
+
+    /// 删除超过 `max_idle` 未使用的桶，这些桶已经补满，删除与保留没有区别
+    async fn delete_idle(&self, max_idle: Duration) -> Result<(), DbErr>;
+}
+
+/// 订阅相关接口的限流器：按客户端 IP 限制请求频率，按目标邮箱限制确认邮件数量
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    store: Arc<dyn RateLimitStore>,
+    per_ip: RateLimit,
+    per_email: RateLimit,
+    trust_forwarded_for: bool,
+}
+
+impl RateLimiter {
+    pub fn new(
+        store: Arc<dyn RateLimitStore>,
+        per_ip: RateLimit,
+        per_email: RateLimit,
+        trust_forwarded_for: bool,
+    ) -> Self {
+        Self {
+            store,
+            per_ip,
+            per_email,
+            trust_forwarded_for,
+        }
+    }
+
+    pub async fn check_ip(&self, ip: IpAddr) -> Result<(), RateLimited> {
+        self.check(&format!("ip:{}", ip), &self.per_ip).await
+    }
+
+    pub async fn check_email(&self, email: &SubscriberEmail) -> Result<(), RateLimited> {
+        let key = format!("email:{}", normalize_email(email.as_ref()));
+        self.check(&key, &self.per_email).await
+    }
+
+    /// 存储后端出错时放行请求，限流不可用不应该阻止正常订阅
+    async fn check(&self, key: &str, limit: &RateLimit) -> Result<(), RateLimited> {
+        match self.store.acquire(key, limit).await {
+            Ok(outcome) => outcome,
+            Err(e) => {
+                tracing::error!(error.cause_chain = ?e, key, "限流状态读取失败, 放行请求");
+                Ok(())
+            }
+        }
+    }
+
+    /// 部署在反向代理之后时，客户端 IP 取自 `X-Forwarded-For` 的最后一项
+    ///
+    /// 前面的各项由客户端随意填写，只有最后一项是我们信任的代理追加的
+    fn client_ip(&self, headers: &HeaderMap, peer: SocketAddr) -> IpAddr {
+        self.trust_forwarded_for
+            .then(|| {
+                headers
+                    .get("x-forwarded-for")
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(|value| value.rsplit(',').next())
+                    .and_then(|ip| ip.trim().parse().ok())
+            })
+            .flatten()
+            .unwrap_or_else(|| peer.ip())
+    }
+
+    /// 每隔 `period` 清理一次闲置的令牌桶，由 `Application::run_cleanup_until_stopped` 在后台运行
+    pub async fn continuously_delete_idle(self, period: Duration) {
+        let max_idle = self.per_ip.period.max(self.per_email.period);
+        let mut interval = tokio::time::interval(period);
+        // 第一次 tick 会立即完成，跳过
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            if let Err(e) = self.store.delete_idle(max_idle).await {
+                tracing::error!(error.cause_chain = ?e, "清理闲置令牌桶失败");
+            }
+        }
+    }
+}
+
+/// 按客户端 IP 限流的中间件，超出限制时返回 429
+pub async fn limit_by_client_ip(
+    State(limiter): State<RateLimiter>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let ip = limiter.client_ip(request.headers(), peer);
+    match limiter.check_ip(ip).await {
+        Ok(()) => next.run(request).await,
+        Err(e) => {
+            let locale = Locale::negotiate(None, request.headers());
+            Localized::new(locale, e).into_response()
+        }
+    }
+}
+
+/// 超出限流，`retry_after` 之后才能再次请求
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimited {
+    pub retry_after: Duration,
+}
+
+impl RateLimited {
+    /// `Retry-After` 只能是整数秒，向上取整
+    fn retry_after_seconds(&self) -> u64 {
+        self.retry_after.as_secs_f64().ceil().max(1.0) as u64
+    }
+}
+
+impl Localize for RateLimited {
+    fn localize(&self, locale: Locale) -> String {
+        let mut args = FluentArgs::new();
+        args.set("seconds", self.retry_after_seconds());
+        locale.translate("rate-limit-exceeded", Some(&args))
+    }
+}
+
+impl Display for RateLimited {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.localize(Locale::default()))
+    }
+}
+
+impl std::error::Error for RateLimited {}
+
+impl IntoResponse for RateLimited {
+    fn into_response(self) -> Response {
+        tracing::warn!(retry_after = self.retry_after_seconds(), "请求超出限流");
+        (
+            StatusCode::TOO_MANY_REQUESTS,
+            [(header::RETRY_AFTER, self.retry_after_seconds().to_string())],
+            self.to_string(),
+        )
+            .into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use claim::{assert_err, assert_ok};
+
+    use super::{RateLimit, RateLimited};
+
+    fn limit() -> RateLimit {
+        RateLimit {
+            capacity: 2,
+            period: Duration::from_secs(2),
+        }
+    }
+
+    #[test]
+    fn a_full_bucket_allows_a_burst_up_to_its_capacity() {
+        let (tokens, first) = limit().take(2.0, Duration::ZERO);
+        let (tokens, second) = limit().take(tokens, Duration::ZERO);
+        let (_, third) = limit().take(tokens, Duration::ZERO);
+
+        assert_ok!(first);
+        assert_ok!(second);
+        assert_err!(third);
+    }
+
+    #[test]
+    fn an_empty_bucket_reports_when_the_next_token_is_available() {
+        let (_, outcome) = limit().take(0.0, Duration::ZERO);
+
+        assert_eq!(
+            outcome,
+            Err(RateLimited {
+                retry_after: Duration::from_secs(1)
+            })
+        );
+    }
+
+    #[test]
+    fn tokens_are_refilled_over_time_but_never_above_capacity() {
+        let (tokens, outcome) = limit().take(0.0, Duration::from_secs(1));
+        assert_ok!(outcome);
+        assert_eq!(tokens, 0.0);
+
+        let (tokens, _) = limit().take(0.0, Duration::from_secs(3600));
+        assert_eq!(tokens, 1.0);
+    }
+
+    #[test]
+    fn retry_after_is_rounded_up_to_whole_seconds() {
+        let limited = RateLimited {
+            retry_after: Duration::from_millis(1200),
+        };
+        assert_eq!(limited.retry_after_seconds(), 2);
+
+        let limited = RateLimited {
+            retry_after: Duration::ZERO,
+        };
+        assert_eq!(limited.retry_after_seconds(), 1);
+    }
+}
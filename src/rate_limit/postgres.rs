@@ -0,0 +1,86 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::Set, ConnectionTrait, DatabaseConnection, DatabaseTransaction,
+    DbBackend, DbErr, EntityTrait, QuerySelect, Statement, TransactionTrait,
+};
+
+use super::{RateLimit, RateLimitStore, RateLimited};
+use crate::entities::rate_limit_buckets;
+
+/// 保存在 Postgres `rate_limit_buckets` 表中的令牌桶，多个实例共享同一份限流状态
+#[derive(Debug, Clone)]
+pub struct PostgresRateLimitStore {
+    db: DatabaseConnection,
+}
+
+impl PostgresRateLimitStore {
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self { db }
+    }
+}
+
+#[async_trait::async_trait]
+impl RateLimitStore for PostgresRateLimitStore {
+    async fn acquire(&self, key: &str, limit: &RateLimit) -> Result<Result<(), RateLimited>, DbErr> {
+        let txn = self.db.begin().await?;
+
+        // 先插入满桶，再锁住这一行，并发请求会依次扣减令牌
+        txn.execute(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            r#"
+                INSERT INTO rate_limit_buckets (key, tokens, updated_at)
+                VALUES ($1, $2, clock_timestamp())
+                ON CONFLICT (key) DO NOTHING
+            "#,
+            [key.into(), f64::from(limit.capacity).into()],
+        ))
+        .await?;
+        let bucket = rate_limit_buckets::Entity::find_by_id(key)
+            .lock_exclusive()
+            .one(&txn)
+            .await?
+            .ok_or_else(|| DbErr::RecordNotFound(format!("令牌桶 {} 不存在", key)))?;
+
+        // 拿到行锁之后才读取数据库的时钟：等待锁的请求不会用更早的时间覆盖已保存的 updated_at，
+        // 各实例之间的时钟偏差也不影响补充。时间不会早于已保存的值，经过的时间因此不会为负
+        let now = clock_timestamp(&txn).await?.max(bucket.updated_at);
+        let elapsed = (now - bucket.updated_at).to_std().unwrap_or_default();
+        let (tokens, outcome) = limit.take(bucket.tokens, elapsed);
+        let mut bucket: rate_limit_buckets::ActiveModel = bucket.into();
+        bucket.tokens = Set(tokens);
+        bucket.updated_at = Set(now);
+        bucket.update(&txn).await?;
+
+        txn.commit().await?;
+        Ok(outcome)
+    }
+
+    /// 与 `acquire` 一样以数据库的时钟计算截止时间，不受本实例时钟偏差的影响
+    async fn delete_idle(&self, max_idle: Duration) -> Result<(), DbErr> {
+        self.db
+            .execute(Statement::from_sql_and_values(
+                DbBackend::Postgres,
+                r#"
+                    DELETE FROM rate_limit_buckets
+                    WHERE updated_at < clock_timestamp() - $1 * interval '1 second'
+                "#,
+                [max_idle.as_secs_f64().into()],
+            ))
+            .await?;
+        Ok(())
+    }
+}
+
+/// 数据库的当前时间；与 `now()` 不同，不会停留在事务开始的时刻
+async fn clock_timestamp(txn: &DatabaseTransaction) -> Result<DateTime<Utc>, DbErr> {
+    let row = txn
+        .query_one(Statement::from_string(
+            DbBackend::Postgres,
+            "SELECT clock_timestamp() AS now",
+        ))
+        .await?
+        .ok_or_else(|| DbErr::Custom("读取数据库时间没有返回结果".into()))?;
+    row.try_get("", "now")
+}
@@ -0,0 +1,53 @@
+use std::sync::Arc;
+
+use axum::{
+    Extension,
+    extract::State,
+    response::{Html, IntoResponse, Response},
+};
+use sea_orm::{DatabaseConnection, DbErr, EntityTrait};
+
+use crate::{authentication::UserId, entities::users, startup::AppState, utils::e500};
+
+pub async fn admin_dashboard(
+    State(state): State<Arc<AppState>>,
+    Extension(user_id): Extension<UserId>,
+) -> Response {
+    let username = match get_username(*user_id, state.db.as_ref()).await {
+        Ok(username) => username,
+        Err(e) => return e500(e),
+    };
+    let username = tera::escape_html(&username);
+
+    Html(format!(
+        r#"<!DOCTYPE html>
+<html lang="zh-CN">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>管理后台</title>
+</head>
+<body>
+    <p>欢迎 {username}!</p>
+    <p>可用操作:</p>
+    <ol>
+        <li><a href="/admin/newsletters">发布新闻邮件</a></li>
+        <li>
+            <form name="logoutForm" action="/admin/logout" method="post">
+                <input type="submit" value="退出登录">
+            </form>
+        </li>
+    </ol>
+</body>
+</html>"#,
+    ))
+    .into_response()
+}
+
+#[tracing::instrument(name = "获取用户名", skip(db))]
+pub async fn get_username(user_id: uuid::Uuid, db: &DatabaseConnection) -> Result<String, DbErr> {
+    users::Entity::find_by_id(user_id)
+        .one(db)
+        .await?
+        .map(|u| u.username)
+        .ok_or_else(|| DbErr::RecordNotFound(format!("用户 {} 不存在", user_id)))
+}
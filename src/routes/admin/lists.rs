@@ -0,0 +1,130 @@
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+use std::sync::Arc;
+
+use axum::{
+    Extension, Json,
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use sea_orm::{ActiveModelTrait, ActiveValue::Set, DbErr, EntityTrait, QueryOrder, SqlErr};
+
+use crate::{
+    authentication::UserId, domain::ListSlug, entities::lists, routes::error_chain_fmt,
+    startup::AppState,
+};
+
+#[derive(serde::Serialize)]
+pub struct MailingList {
+    id: uuid::Uuid,
+    slug: String,
+    name: String,
+    created_at: DateTime<Utc>,
+}
+
+impl From<lists::Model> for MailingList {
+    fn from(model: lists::Model) -> Self {
+        Self {
+            id: model.list_id,
+            slug: model.slug,
+            name: model.name,
+            created_at: model.created_at,
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+pub struct NewList {
+    slug: String,
+    /// 展示给订阅者的名称，会出现在确认邮件中
+    name: String,
+}
+
+#[tracing::instrument(name = "查询邮件列表", skip(state), fields(user_id = %*user_id))]
+pub async fn list_lists(
+    State(state): State<Arc<AppState>>,
+    Extension(user_id): Extension<UserId>,
+) -> Result<Json<Vec<MailingList>>, ListsError> {
+    let lists = lists::Entity::find()
+        .order_by_asc(lists::Column::CreatedAt)
+        .order_by_asc(lists::Column::Slug)
+        .all(state.db.as_ref())
+        .await
+        .map_err(ListsError::DbError)?;
+    Ok(Json(lists.into_iter().map(MailingList::from).collect()))
+}
+
+#[tracing::instrument(name = "创建邮件列表", skip(state, body), fields(user_id = %*user_id, slug = %body.slug))]
+pub async fn create_list(
+    State(state): State<Arc<AppState>>,
+    Extension(user_id): Extension<UserId>,
+    Json(body): Json<NewList>,
+) -> Result<(StatusCode, Json<MailingList>), ListsError> {
+    let slug = ListSlug::parse(body.slug).map_err(ListsError::InvalidSlug)?;
+    let name = body.name.trim();
+    if name.is_empty() {
+        return Err(ListsError::InvalidName);
+    }
+
+    let list = lists::ActiveModel {
+        list_id: Set(uuid::Uuid::new_v4()),
+        slug: Set(slug.as_ref().to_string()),
+        name: Set(name.to_string()),
+        created_at: Set(Utc::now()),
+    }
+    .insert(state.db.as_ref())
+    .await
+    .map_err(|e| match e.sql_err() {
+        Some(SqlErr::UniqueConstraintViolation(_)) => ListsError::SlugTaken,
+        _ => ListsError::DbError(e),
+    })?;
+    Ok((StatusCode::CREATED, Json(list.into())))
+}
+
+pub enum ListsError {
+    InvalidSlug(String),
+    InvalidName,
+    SlugTaken,
+    DbError(DbErr),
+}
+
+impl Display for ListsError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ListsError::InvalidSlug(e) => write!(f, "{}", e),
+            ListsError::InvalidName => write!(f, "列表名称不能为空"),
+            ListsError::SlugTaken => write!(f, "列表标识已被使用"),
+            ListsError::DbError(_) => write!(f, "读写邮件列表时发生数据库错误"),
+        }
+    }
+}
+
+impl Debug for ListsError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl Error for ListsError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ListsError::InvalidSlug(_) | ListsError::InvalidName | ListsError::SlugTaken => None,
+            ListsError::DbError(e) => Some(e),
+        }
+    }
+}
+
+impl IntoResponse for ListsError {
+    fn into_response(self) -> Response {
+        tracing::error!("{:?}", self);
+        match self {
+            ListsError::InvalidSlug(_) | ListsError::InvalidName => {
+                (StatusCode::BAD_REQUEST, self.to_string()).into_response()
+            }
+            ListsError::SlugTaken => (StatusCode::CONFLICT, self.to_string()).into_response(),
+            ListsError::DbError(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+        }
+    }
+}
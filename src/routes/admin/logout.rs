@@ -0,0 +1,12 @@
+use axum::response::{IntoResponse, Redirect, Response};
+use axum_messages::Messages;
+
+use crate::{session_state::TypedSession, utils::e500};
+
+pub async fn log_out(session: TypedSession, messages: Messages) -> Response {
+    if let Err(e) = session.log_out().await {
+        return e500(e);
+    }
+    messages.info("您已成功退出登录");
+    Redirect::to("/login").into_response()
+}
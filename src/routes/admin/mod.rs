@@ -0,0 +1,18 @@
+mod dashboard;
+mod lists;
+mod logout;
+mod newsletters;
+mod subscriber_csv;
+mod subscribers;
+mod suppressions;
+
+pub use dashboard::admin_dashboard;
+pub use lists::{create_list, list_lists};
+pub use logout::log_out;
+pub use newsletters::{
+    cancel_newsletter, list_scheduled_newsletters, newsletter_stats, publish_newsletter,
+    publish_newsletter_form, reschedule_newsletter,
+};
+pub use subscriber_csv::{export_subscribers, import_subscribers};
+pub use subscribers::{delete_subscriber, get_subscriber, list_subscribers};
+pub use suppressions::{add_suppression, list_suppressions, remove_suppression};
@@ -0,0 +1,59 @@
+use std::fmt::Write;
+
+use axum::response::Html;
+use axum_messages::Messages;
+
+use crate::domain::DEFAULT_LIST_SLUG;
+
+pub async fn publish_newsletter_form(messages: Messages) -> Html<String> {
+    let mut msg_html = String::new();
+    for message in messages {
+        writeln!(msg_html, "<p><i>{}</i></p>", message).unwrap();
+    }
+    let idempotency_key = uuid::Uuid::new_v4();
+
+    Html(format!(
+        r#"<!DOCTYPE html>
+<html lang="zh-CN">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>发布新闻邮件</title>
+</head>
+<body>
+    {msg_html}
+    <form action="/admin/newsletters" method="post">
+        <label>标题:<br>
+            <input type="text" placeholder="请输入邮件标题" name="title">
+        </label>
+        <br>
+        <label>纯文本内容:<br>
+            <textarea placeholder="请输入纯文本内容" name="text_content" rows="20" cols="50"></textarea>
+        </label>
+        <br>
+        <label>HTML 内容:<br>
+            <textarea placeholder="请输入 HTML 内容" name="html_content" rows="20" cols="50"></textarea>
+        </label>
+        <br>
+        <label>邮件列表 (多个列表以逗号分隔):<br>
+            <input type="text" placeholder="{DEFAULT_LIST_SLUG}" name="lists" value="{DEFAULT_LIST_SLUG}">
+        </label>
+        <br>
+        <label>计划发送时间 (RFC 3339 格式, 留空则立即发送):<br>
+            <input type="text" placeholder="2025-10-13T09:00:00+08:00" name="scheduled_at">
+        </label>
+        <br>
+        <label>
+            <input type="checkbox" name="track_opens" checked> 跟踪邮件打开
+        </label>
+        <label>
+            <input type="checkbox" name="track_clicks" checked> 跟踪链接点击
+        </label>
+        <br>
+        <input hidden type="text" name="idempotency_key" value="{idempotency_key}">
+        <button type="submit">发布</button>
+    </form>
+    <p><a href="/admin/dashboard">&lt;- 返回</a></p>
+</body>
+</html>"#,
+    ))
+}
@@ -0,0 +1,9 @@
+mod get;
+mod post;
+mod schedule;
+mod stats;
+
+pub use get::publish_newsletter_form;
+pub use post::publish_newsletter;
+pub use schedule::{cancel_newsletter, list_scheduled_newsletters, reschedule_newsletter};
+pub use stats::newsletter_stats;
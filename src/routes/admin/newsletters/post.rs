@@ -0,0 +1,91 @@
+use std::sync::Arc;
+
+use axum::{
+    Extension, Form,
+    extract::State,
+    http::HeaderMap,
+    response::{IntoResponse, Redirect, Response},
+};
+use axum_messages::Messages;
+use chrono::{DateTime, Utc};
+
+use crate::{
+    authentication::UserId,
+    idempotency::IdempotencyKey,
+    routes::newsletters::{
+        NewIssue, PublishError, find_target_lists, parse_schedule, publish_issue,
+    },
+    startup::AppState,
+};
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    title: String,
+    text_content: String,
+    html_content: String,
+    /// 以逗号分隔的列表标识，未提供时发布到默认列表
+    lists: Option<String>,
+    /// RFC 3339 格式的计划发送时间，为空或早于当前时间时立即发送
+    scheduled_at: Option<String>,
+    /// 勾选时为 `on`，未勾选的复选框不会出现在表单中
+    track_opens: Option<String>,
+    track_clicks: Option<String>,
+    idempotency_key: Option<String>,
+}
+
+#[tracing::instrument(
+    name = "发布新闻邮件",
+    skip(state, headers, messages, form),
+    fields(title = %form.title, user_id = %*user_id)
+)]
+pub async fn publish_newsletter(
+    State(state): State<Arc<AppState>>,
+    Extension(user_id): Extension<UserId>,
+    headers: HeaderMap,
+    messages: Messages,
+    Form(form): Form<FormData>,
+) -> Result<Response, PublishError> {
+    let FormData {
+        title,
+        text_content,
+        html_content,
+        lists,
+        scheduled_at,
+        track_opens,
+        track_clicks,
+        idempotency_key,
+    } = form;
+    let idempotency_key = IdempotencyKey::from_request(&headers, idempotency_key)
+        .map_err(PublishError::InvalidIdempotencyKey)?;
+    let issue = NewIssue {
+        title,
+        text_content,
+        html_content,
+        lists: find_target_lists(state.db.as_ref(), lists.as_deref()).await?,
+        scheduled_at: parse_schedule(scheduled_at.as_deref())?,
+        track_opens: track_opens.is_some(),
+        track_clicks: track_clicks.is_some(),
+    };
+    let scheduled_at = issue.scheduled_at;
+
+    let response = publish_issue(
+        state.db.as_ref(),
+        *user_id,
+        Some(&idempotency_key),
+        issue,
+        Redirect::to("/admin/newsletters").into_response(),
+    )
+    .await?;
+    success_message(messages, scheduled_at);
+    Ok(response)
+}
+
+fn success_message(messages: Messages, scheduled_at: Option<DateTime<Utc>>) {
+    match scheduled_at {
+        Some(scheduled_at) => messages.info(format!(
+            "新闻邮件已计划在 {} 发送",
+            scheduled_at.to_rfc3339()
+        )),
+        None => messages.info("新闻邮件已发布, 邮件将在后台陆续发送!"),
+    };
+}
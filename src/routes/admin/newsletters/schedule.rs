@@ -0,0 +1,158 @@
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+use std::sync::Arc;
+
+use axum::{
+    Extension, Json,
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::Set, ColumnTrait, DatabaseTransaction, DbErr, EntityTrait,
+    QueryFilter, QueryOrder, QuerySelect, TransactionTrait,
+};
+
+use crate::{
+    authentication::UserId, domain::IssueStatus, entities::newsletter_issues,
+    routes::error_chain_fmt, startup::AppState,
+};
+
+#[derive(serde::Serialize)]
+pub struct ScheduledIssue {
+    id: uuid::Uuid,
+    title: String,
+    status: IssueStatus,
+    scheduled_at: Option<DateTime<Utc>>,
+}
+
+impl From<newsletter_issues::Model> for ScheduledIssue {
+    fn from(model: newsletter_issues::Model) -> Self {
+        Self {
+            id: model.newsletter_issue_id,
+            title: model.title,
+            status: model.status,
+            scheduled_at: model.scheduled_at,
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+pub struct Schedule {
+    scheduled_at: DateTime<Utc>,
+}
+
+/// 列出尚未开始发送的计划发送，按计划时间排序
+#[tracing::instrument(name = "查询计划发送的新闻邮件", skip(state), fields(user_id = %*user_id))]
+pub async fn list_scheduled_newsletters(
+    State(state): State<Arc<AppState>>,
+    Extension(user_id): Extension<UserId>,
+) -> Result<Json<Vec<ScheduledIssue>>, ScheduleError> {
+    let issues = newsletter_issues::Entity::find()
+        .filter(newsletter_issues::Column::Status.eq(IssueStatus::Scheduled))
+        .order_by_asc(newsletter_issues::Column::ScheduledAt)
+        .all(state.db.as_ref())
+        .await
+        .map_err(ScheduleError::DbError)?;
+    Ok(Json(issues.into_iter().map(ScheduledIssue::from).collect()))
+}
+
+/// 修改计划发送时间，改为已经过去的时间时会在调度任务的下一轮发送
+#[tracing::instrument(name = "修改计划发送时间", skip(state, body), fields(user_id = %*user_id))]
+pub async fn reschedule_newsletter(
+    State(state): State<Arc<AppState>>,
+    Extension(user_id): Extension<UserId>,
+    Path(issue_id): Path<uuid::Uuid>,
+    Json(body): Json<Schedule>,
+) -> Result<Json<ScheduledIssue>, ScheduleError> {
+    let txn = state.db.begin().await.map_err(ScheduleError::DbError)?;
+    let issue = get_scheduled_issue_for_update(&txn, issue_id).await?;
+
+    let mut issue: newsletter_issues::ActiveModel = issue.into();
+    issue.scheduled_at = Set(Some(body.scheduled_at));
+    issue.published_at = Set(body.scheduled_at);
+    let issue = issue.update(&txn).await.map_err(ScheduleError::DbError)?;
+    txn.commit().await.map_err(ScheduleError::DbError)?;
+
+    Ok(Json(issue.into()))
+}
+
+#[tracing::instrument(name = "取消计划发送", skip(state), fields(user_id = %*user_id))]
+pub async fn cancel_newsletter(
+    State(state): State<Arc<AppState>>,
+    Extension(user_id): Extension<UserId>,
+    Path(issue_id): Path<uuid::Uuid>,
+) -> Result<StatusCode, ScheduleError> {
+    let txn = state.db.begin().await.map_err(ScheduleError::DbError)?;
+    let issue = get_scheduled_issue_for_update(&txn, issue_id).await?;
+
+    let mut issue: newsletter_issues::ActiveModel = issue.into();
+    issue.status = Set(IssueStatus::Cancelled);
+    issue.update(&txn).await.map_err(ScheduleError::DbError)?;
+    txn.commit().await.map_err(ScheduleError::DbError)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// 锁定一封尚未开始发送的新闻邮件，调度任务会等待本事务结束后再检查其状态
+async fn get_scheduled_issue_for_update(
+    txn: &DatabaseTransaction,
+    issue_id: uuid::Uuid,
+) -> Result<newsletter_issues::Model, ScheduleError> {
+    let issue = newsletter_issues::Entity::find_by_id(issue_id)
+        .lock_exclusive()
+        .one(txn)
+        .await
+        .map_err(ScheduleError::DbError)?
+        .ok_or(ScheduleError::NotFound)?;
+    if issue.status != IssueStatus::Scheduled {
+        return Err(ScheduleError::NotScheduled(issue.status));
+    }
+    Ok(issue)
+}
+
+pub enum ScheduleError {
+    NotFound,
+    NotScheduled(IssueStatus),
+    DbError(DbErr),
+}
+
+impl Display for ScheduleError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScheduleError::NotFound => write!(f, "新闻邮件不存在"),
+            ScheduleError::NotScheduled(IssueStatus::Cancelled) => write!(f, "计划发送已取消"),
+            ScheduleError::NotScheduled(_) => write!(f, "新闻邮件已经开始发送"),
+            ScheduleError::DbError(_) => write!(f, "读写新闻邮件时发生数据库错误"),
+        }
+    }
+}
+
+impl Debug for ScheduleError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl Error for ScheduleError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ScheduleError::NotFound | ScheduleError::NotScheduled(_) => None,
+            ScheduleError::DbError(e) => Some(e),
+        }
+    }
+}
+
+impl IntoResponse for ScheduleError {
+    fn into_response(self) -> Response {
+        tracing::error!("{:?}", self);
+        match self {
+            ScheduleError::NotFound => (StatusCode::NOT_FOUND, self.to_string()).into_response(),
+            ScheduleError::NotScheduled(_) => {
+                (StatusCode::CONFLICT, self.to_string()).into_response()
+            }
+            ScheduleError::DbError(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+        }
+    }
+}
@@ -0,0 +1,151 @@
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+use std::sync::Arc;
+
+use axum::{
+    Extension, Json,
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use sea_orm::{ConnectionTrait, DbBackend, DbErr, EntityTrait, Statement};
+
+use crate::{
+    authentication::UserId, domain::EmailEventKind, entities::newsletter_issues,
+    routes::error_chain_fmt, startup::AppState,
+};
+
+#[derive(serde::Serialize)]
+pub struct IssueStats {
+    id: uuid::Uuid,
+    title: String,
+    track_opens: bool,
+    track_clicks: bool,
+    /// 发送成功的邮件数量
+    deliveries: i64,
+    /// 至少打开过一次的邮件数量
+    unique_opens: i64,
+    /// 按点击次数从多到少排列的链接
+    links: Vec<LinkStats>,
+}
+
+#[derive(serde::Serialize)]
+pub struct LinkStats {
+    url: String,
+    clicks: i64,
+    /// 点击过该链接的邮件数量
+    unique_clicks: i64,
+}
+
+/// 查询一封新闻邮件的发送、打开和点击统计
+#[tracing::instrument(name = "查询新闻邮件统计", skip(state), fields(user_id = %*user_id))]
+pub async fn newsletter_stats(
+    State(state): State<Arc<AppState>>,
+    Extension(user_id): Extension<UserId>,
+    Path(issue_id): Path<uuid::Uuid>,
+) -> Result<Json<IssueStats>, StatsError> {
+    let issue = newsletter_issues::Entity::find_by_id(issue_id)
+        .one(state.db.as_ref())
+        .await
+        .map_err(StatsError::DbError)?
+        .ok_or(StatsError::NotFound)?;
+
+    let row = state
+        .db
+        .query_one(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            r#"
+                SELECT
+                    COUNT(DISTINCT d.delivery_id) AS deliveries,
+                    COUNT(DISTINCT e.delivery_id) AS unique_opens
+                FROM email_deliveries d
+                LEFT JOIN email_events e ON e.delivery_id = d.delivery_id AND e.kind = $2
+                WHERE d.newsletter_issue_id = $1
+            "#,
+            [issue_id.into(), EmailEventKind::Open.as_str().into()],
+        ))
+        .await
+        .map_err(StatsError::DbError)?
+        .ok_or_else(|| StatsError::DbError(DbErr::Custom("统计查询没有返回结果".into())))?;
+
+    let links = state
+        .db
+        .query_all(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            r#"
+                SELECT
+                    e.url,
+                    COUNT(*) AS clicks,
+                    COUNT(DISTINCT e.delivery_id) AS unique_clicks
+                FROM email_events e
+                JOIN email_deliveries d ON d.delivery_id = e.delivery_id
+                WHERE d.newsletter_issue_id = $1 AND e.kind = $2
+                GROUP BY e.url
+                ORDER BY clicks DESC, e.url
+            "#,
+            [issue_id.into(), EmailEventKind::Click.as_str().into()],
+        ))
+        .await
+        .map_err(StatsError::DbError)?
+        .into_iter()
+        .map(|row| {
+            Ok(LinkStats {
+                url: row.try_get("", "url")?,
+                clicks: row.try_get("", "clicks")?,
+                unique_clicks: row.try_get("", "unique_clicks")?,
+            })
+        })
+        .collect::<Result<Vec<_>, DbErr>>()
+        .map_err(StatsError::DbError)?;
+
+    Ok(Json(IssueStats {
+        id: issue.newsletter_issue_id,
+        title: issue.title,
+        track_opens: issue.track_opens,
+        track_clicks: issue.track_clicks,
+        deliveries: row.try_get("", "deliveries").map_err(StatsError::DbError)?,
+        unique_opens: row
+            .try_get("", "unique_opens")
+            .map_err(StatsError::DbError)?,
+        links,
+    }))
+}
+
+pub enum StatsError {
+    NotFound,
+    DbError(DbErr),
+}
+
+impl Display for StatsError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StatsError::NotFound => write!(f, "新闻邮件不存在"),
+            StatsError::DbError(_) => write!(f, "查询新闻邮件统计时发生数据库错误"),
+        }
+    }
+}
+
+impl Debug for StatsError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl Error for StatsError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            StatsError::NotFound => None,
+            StatsError::DbError(e) => Some(e),
+        }
+    }
+}
+
+impl IntoResponse for StatsError {
+    fn into_response(self) -> Response {
+        tracing::error!("{:?}", self);
+        match self {
+            StatsError::NotFound => (StatusCode::NOT_FOUND, self.to_string()).into_response(),
+            StatsError::DbError(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+        }
+    }
+}
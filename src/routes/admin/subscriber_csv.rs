@@ -0,0 +1,450 @@
+use std::collections::{HashMap, HashSet, hash_map::Entry};
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+use std::sync::Arc;
+
+use axum::{
+    Extension, Json,
+    body::Body,
+    extract::{Query, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use csv_async::{AsyncReaderBuilder, AsyncWriterBuilder, ErrorKind, StringRecord};
+use futures_util::TryStreamExt;
+use sea_orm::{
+    ActiveValue::Set, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, Iterable, QueryOrder,
+    TransactionTrait, sea_query::OnConflict,
+};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_util::io::{ReaderStream, StreamReader};
+
+use crate::{
+    authentication::UserId,
+    domain::{DEFAULT_LIST_SLUG, SubscriberEmail, SubscriberName, SubscriptionStatus},
+    entities::{list_memberships, subscriptions},
+    i18n::Locale,
+    routes::{
+        error_chain_fmt,
+        subscriptions::{find_list, new_subscription_row},
+    },
+    startup::AppState,
+};
+
+/// 每批写入数据库的行数
+const IMPORT_BATCH_SIZE: usize = 500;
+
+const EXPORT_HEADER: [&str; 6] = ["id", "email", "name", "status", "locale", "subscribed_at"];
+
+/// 以 CSV 格式导出全部订阅者，边查询边写入响应，不会把整个结果集读入内存
+#[tracing::instrument(name = "导出订阅者", skip(state), fields(user_id = %*user_id))]
+pub async fn export_subscribers(
+    State(state): State<Arc<AppState>>,
+    Extension(user_id): Extension<UserId>,
+) -> Response {
+    let (writer, reader) = tokio::io::duplex(64 * 1024);
+    let db = state.db.as_ref().clone();
+    tokio::spawn(async move {
+        // 出错时响应体会提前结束，客户端可以据此发现导出不完整
+        if let Err(e) = write_subscribers(&db, writer).await {
+            tracing::error!(error.cause_chain = ?e, error.message = %e, "导出订阅者失败");
+        }
+    });
+
+    (
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8"),
+            (
+                header::CONTENT_DISPOSITION,
+                r#"attachment; filename="subscribers.csv""#,
+            ),
+        ],
+        Body::from_stream(ReaderStream::new(reader)),
+    )
+        .into_response()
+}
+
+async fn write_subscribers(
+    db: &DatabaseConnection,
+    writer: impl AsyncWrite + Unpin,
+) -> Result<(), CsvTransferError> {
+    let mut csv = AsyncWriterBuilder::new().create_writer(writer);
+    csv.write_record(EXPORT_HEADER)
+        .await
+        .map_err(CsvTransferError::CsvError)?;
+
+    let mut subscribers = subscriptions::Entity::find()
+        .order_by_asc(subscriptions::Column::SubscribedAt)
+        .order_by_asc(subscriptions::Column::Id)
+        .stream(db)
+        .await
+        .map_err(CsvTransferError::DbError)?;
+    while let Some(subscriber) = subscribers
+        .try_next()
+        .await
+        .map_err(CsvTransferError::DbError)?
+    {
+        csv.write_record([
+            subscriber.id.to_string(),
+            subscriber.email,
+            subscriber.name,
+            subscriber.status.to_string(),
+            subscriber.locale,
+            subscriber.subscribed_at.to_rfc3339(),
+        ])
+        .await
+        .map_err(CsvTransferError::CsvError)?;
+    }
+    csv.flush().await.map_err(CsvTransferError::IoError)?;
+    Ok(())
+}
+
+#[derive(serde::Deserialize, Debug)]
+pub struct ImportParameters {
+    /// 导入的订阅者的初始状态，同时也是其在列表中的订阅状态
+    status: SubscriptionStatus,
+    /// 导入到的列表标识，未提供时导入到默认列表
+    list: Option<String>,
+}
+
+#[derive(serde::Serialize, Default)]
+pub struct ImportReport {
+    accepted: usize,
+    updated: usize,
+    duplicates: usize,
+    rejected: usize,
+    rows: Vec<ImportedRow>,
+}
+
+#[derive(serde::Serialize)]
+struct ImportedRow {
+    /// 该行在 CSV 文件中的行号，从 1 开始，表头为第 1 行
+    line: u64,
+    email: Option<String>,
+    outcome: ImportOutcome,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reason: Option<String>,
+}
+
+#[derive(serde::Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum ImportOutcome {
+    Accepted,
+    /// 邮箱已存在，已有的订阅者被更新为文件中的姓名和导入状态
+    Updated,
+    /// 邮箱在文件中更早的行出现过，或已有的订阅者的状态不允许被导入覆盖
+    Duplicate,
+    Rejected,
+}
+
+impl ImportReport {
+    fn record(
+        &mut self,
+        line: u64,
+        email: Option<String>,
+        outcome: ImportOutcome,
+        reason: Option<String>,
+    ) {
+        match outcome {
+            ImportOutcome::Accepted => self.accepted += 1,
+            ImportOutcome::Updated => self.updated += 1,
+            ImportOutcome::Duplicate => self.duplicates += 1,
+            ImportOutcome::Rejected => self.rejected += 1,
+        }
+        self.rows.push(ImportedRow {
+            line,
+            email,
+            outcome,
+            reason,
+        });
+    }
+}
+
+/// 通过校验、等待写入数据库的一行
+struct PendingRow {
+    line: u64,
+    subscriber: subscriptions::ActiveModel,
+    email: String,
+    id: uuid::Uuid,
+}
+
+/// 从 CSV 请求体中批量导入订阅者
+///
+/// 文件必须包含 `email` 和 `name` 列，`locale` 列可选。请求体按批读取并写入，
+/// 不会整体读入内存；每批单独提交，重复导入同一个文件时已导入的行会被报告为更新。
+/// 已存在的订阅者会被更新为文件中的姓名和指定的状态，并加入列表
+#[tracing::instrument(name = "导入订阅者", skip(state, body), fields(user_id = %*user_id))]
+pub async fn import_subscribers(
+    State(state): State<Arc<AppState>>,
+    Extension(user_id): Extension<UserId>,
+    Query(params): Query<ImportParameters>,
+    body: Body,
+) -> Result<Json<ImportReport>, CsvTransferError> {
+    let reader = StreamReader::new(body.into_data_stream().map_err(std::io::Error::other));
+    let list_slug = params.list.unwrap_or_else(|| DEFAULT_LIST_SLUG.into());
+    let list = find_list(state.db.as_ref(), &list_slug)
+        .await
+        .map_err(CsvTransferError::DbError)?
+        .ok_or(CsvTransferError::UnknownList(list_slug))?;
+    let report = import_rows(state.db.as_ref(), reader, list.list_id, params.status).await?;
+    tracing::info!(
+        accepted = report.accepted,
+        updated = report.updated,
+        duplicates = report.duplicates,
+        rejected = report.rejected,
+        "订阅者导入完成"
+    );
+    Ok(Json(report))
+}
+
+async fn import_rows(
+    db: &DatabaseConnection,
+    reader: impl AsyncRead + Unpin + Send,
+    list_id: uuid::Uuid,
+    status: SubscriptionStatus,
+) -> Result<ImportReport, CsvTransferError> {
+    let mut csv = AsyncReaderBuilder::new()
+        .flexible(true)
+        .trim(csv_async::Trim::All)
+        .create_reader(reader);
+    let headers = csv
+        .headers()
+        .await
+        .map_err(CsvTransferError::CsvError)?
+        .clone();
+    let column = |name: &'static str| {
+        headers
+            .iter()
+            .position(|h| h.eq_ignore_ascii_case(name))
+            .ok_or(CsvTransferError::MissingColumn(name))
+    };
+    let email_column = column("email")?;
+    let name_column = column("name")?;
+    let locale_column = column("locale").ok();
+
+    let mut report = ImportReport::default();
+    let mut batch = Vec::with_capacity(IMPORT_BATCH_SIZE);
+    let mut record = StringRecord::new();
+    loop {
+        match csv.read_record(&mut record).await {
+            Ok(false) => break,
+            Ok(true) => {
+                let line = record.position().map_or(0, |p| p.line());
+                let email = record.get(email_column).map(str::to_string);
+                match parse_row(&record, email_column, name_column, locale_column, status) {
+                    Ok((id, subscriber)) => batch.push(PendingRow {
+                        line,
+                        subscriber,
+                        email: email.unwrap_or_default(),
+                        id,
+                    }),
+                    Err(reason) => {
+                        report.record(line, email, ImportOutcome::Rejected, Some(reason))
+                    }
+                }
+            }
+            // 无法解码为 UTF-8 的行单独拒绝，读取请求体失败则中止整个导入
+            Err(e) => match e.kind() {
+                ErrorKind::Utf8 { pos, .. } => report.record(
+                    pos.as_ref().map_or(0, |p| p.line()),
+                    None,
+                    ImportOutcome::Rejected,
+                    Some("该行不是有效的 UTF-8 文本".into()),
+                ),
+                _ => return Err(CsvTransferError::CsvError(e)),
+            },
+        }
+
+        if batch.len() >= IMPORT_BATCH_SIZE {
+            upsert_batch(db, list_id, status, std::mem::take(&mut batch), &mut report).await?;
+        }
+    }
+    upsert_batch(db, list_id, status, batch, &mut report).await?;
+
+    Ok(report)
+}
+
+fn parse_row(
+    record: &StringRecord,
+    email_column: usize,
+    name_column: usize,
+    locale_column: Option<usize>,
+    status: SubscriptionStatus,
+) -> Result<(uuid::Uuid, subscriptions::ActiveModel), String> {
+    let email = SubscriberEmail::parse(record.get(email_column).unwrap_or_default().to_string())
+        .map_err(|e| e.to_string())?;
+    let name = SubscriberName::parse(record.get(name_column).unwrap_or_default().to_string())
+        .map_err(|e| e.to_string())?;
+    let locale = match locale_column
+        .and_then(|i| record.get(i))
+        .filter(|l| !l.is_empty())
+    {
+        Some(tag) => Locale::parse(tag).ok_or_else(|| format!("不支持的语言: {}", tag))?,
+        None => Locale::default(),
+    };
+
+    let id = uuid::Uuid::new_v4();
+    Ok((
+        id,
+        new_subscription_row(id, email.as_ref(), name.as_ref(), locale, status),
+    ))
+}
+
+/// 写入一批订阅者并加入列表
+///
+/// 邮箱已存在的订阅者只有当前状态在 [`updatable_statuses`] 中时才更新姓名和状态并加入列表，
+/// 报告为更新；其余订阅者（例如已退订或投诉过的）保持不变，报告为重复
+#[tracing::instrument(name = "写入一批导入的订阅者", skip_all, fields(n_rows = batch.len()))]
+async fn upsert_batch(
+    db: &DatabaseConnection,
+    list_id: uuid::Uuid,
+    status: SubscriptionStatus,
+    batch: Vec<PendingRow>,
+    report: &mut ImportReport,
+) -> Result<(), CsvTransferError> {
+    if batch.is_empty() {
+        return Ok(());
+    }
+
+    // 同一条 INSERT ... ON CONFLICT DO UPDATE 不能两次修改同一行，
+    // 因此先按规范化邮箱合并本批中重复的行，姓名以最后一行为准
+    let mut subscribers: Vec<subscriptions::ActiveModel> = Vec::with_capacity(batch.len());
+    let mut positions: HashMap<&String, usize> = HashMap::with_capacity(batch.len());
+    for row in &batch {
+        match positions.entry(row.subscriber.canonical_email.as_ref()) {
+            Entry::Occupied(position) => {
+                subscribers[*position.get()].name = row.subscriber.name.clone()
+            }
+            Entry::Vacant(position) => {
+                position.insert(subscribers.len());
+                subscribers.push(row.subscriber.clone());
+            }
+        }
+    }
+
+    let updatable = updatable_statuses(status);
+    let txn = db.begin().await.map_err(CsvTransferError::DbError)?;
+    // 冲突时不满足条件的行不会被更新，也不会出现在 RETURNING 中
+    let subscribers = subscriptions::Entity::insert_many(subscribers)
+        .on_conflict(
+            OnConflict::column(subscriptions::Column::CanonicalEmail)
+                .update_columns([subscriptions::Column::Name, subscriptions::Column::Status])
+                .action_and_where(subscriptions::Column::Status.is_in(updatable.iter().copied()))
+                .to_owned(),
+        )
+        .exec_with_returning_many(&txn)
+        .await
+        .map_err(CsvTransferError::DbError)?;
+    // 本批全部是不可更新的重复行时没有需要加入列表的订阅者
+    if !subscribers.is_empty() {
+        let now = chrono::Utc::now();
+        list_memberships::Entity::insert_many(subscribers.iter().map(|subscriber| {
+            list_memberships::ActiveModel {
+                list_id: Set(list_id),
+                subscriber_id: Set(subscriber.id),
+                status: Set(status),
+                created_at: Set(now),
+            }
+        }))
+        .on_conflict(
+            OnConflict::columns([
+                list_memberships::Column::ListId,
+                list_memberships::Column::SubscriberId,
+            ])
+            .update_column(list_memberships::Column::Status)
+            .action_and_where(list_memberships::Column::Status.is_in(updatable.iter().copied()))
+            .to_owned(),
+        )
+        .exec_without_returning(&txn)
+        .await
+        .map_err(CsvTransferError::DbError)?;
+    }
+    txn.commit().await.map_err(CsvTransferError::DbError)?;
+
+    // 新插入的行返回的是导入时生成的 id，被更新的已存在行返回原有的 id，未更新的行不返回；
+    // 本批中同一邮箱只有第一行对应写入的订阅者，之后的行报告为重复
+    let written: HashMap<_, _> = subscribers
+        .iter()
+        .map(|subscriber| (&subscriber.canonical_email, subscriber.id))
+        .collect();
+    let mut seen = HashSet::with_capacity(batch.len());
+    for row in &batch {
+        let canonical_email = row.subscriber.canonical_email.as_ref();
+        let outcome = if !seen.insert(canonical_email) {
+            ImportOutcome::Duplicate
+        } else {
+            match written.get(canonical_email) {
+                Some(id) if *id == row.id => ImportOutcome::Accepted,
+                Some(_) => ImportOutcome::Updated,
+                None => ImportOutcome::Duplicate,
+            }
+        };
+        report.record(row.line, Some(row.email.clone()), outcome, None);
+    }
+    Ok(())
+}
+
+/// 导入可以覆盖的已有状态：与导入状态相同，或可以按 [`SubscriptionStatus::can_transition_to`]
+/// 变为导入状态的。待确认的订阅者不会被导入为已确认，确认只能由订阅者本人完成
+fn updatable_statuses(status: SubscriptionStatus) -> Vec<SubscriptionStatus> {
+    SubscriptionStatus::iter()
+        .filter(|current| *current == status || current.can_transition_to(status))
+        .filter(|current| {
+            !(*current == SubscriptionStatus::PendingConfirmation
+                && status == SubscriptionStatus::Confirmed)
+        })
+        .collect()
+}
+
+pub enum CsvTransferError {
+    MissingColumn(&'static str),
+    UnknownList(String),
+    CsvError(csv_async::Error),
+    IoError(std::io::Error),
+    DbError(DbErr),
+}
+
+impl Display for CsvTransferError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CsvTransferError::MissingColumn(column) => write!(f, "CSV 文件缺少 {} 列", column),
+            CsvTransferError::UnknownList(slug) => write!(f, "邮件列表 {} 不存在", slug),
+            CsvTransferError::CsvError(_) => write!(f, "读写 CSV 失败"),
+            CsvTransferError::IoError(_) => write!(f, "写入 CSV 失败"),
+            CsvTransferError::DbError(_) => write!(f, "读写订阅者时发生数据库错误"),
+        }
+    }
+}
+
+impl Debug for CsvTransferError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl Error for CsvTransferError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CsvTransferError::MissingColumn(_) | CsvTransferError::UnknownList(_) => None,
+            CsvTransferError::CsvError(e) => Some(e),
+            CsvTransferError::IoError(e) => Some(e),
+            CsvTransferError::DbError(e) => Some(e),
+        }
+    }
+}
+
+impl IntoResponse for CsvTransferError {
+    fn into_response(self) -> Response {
+        tracing::error!("{:?}", self);
+        match self {
+            CsvTransferError::MissingColumn(_)
+            | CsvTransferError::UnknownList(_)
+            | CsvTransferError::CsvError(_) => {
+                (StatusCode::BAD_REQUEST, self.to_string()).into_response()
+            }
+            CsvTransferError::IoError(_) | CsvTransferError::DbError(_) => {
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        }
+    }
+}
@@ -0,0 +1,313 @@
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+use std::sync::Arc;
+
+use axum::{
+    Extension, Json,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use sea_orm::{
+    ColumnTrait, Condition, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder,
+    QuerySelect,
+    sea_query::{Expr, Func, LikeExpr},
+};
+
+use crate::{
+    authentication::UserId, domain::SubscriptionStatus, entities::subscriptions,
+    routes::error_chain_fmt, startup::AppState,
+};
+
+const DEFAULT_PAGE_SIZE: u64 = 50;
+const MAX_PAGE_SIZE: u64 = 100;
+
+#[derive(serde::Deserialize, Debug)]
+pub struct ListParameters {
+    status: Option<SubscriptionStatus>,
+    /// 邮箱中包含的子串，不区分大小写
+    email: Option<String>,
+    /// 订阅时间不早于该时间
+    subscribed_from: Option<DateTime<Utc>>,
+    /// 订阅时间早于该时间
+    subscribed_to: Option<DateTime<Utc>>,
+    /// 上一页响应中的 `next_cursor`
+    cursor: Option<String>,
+    limit: Option<u64>,
+}
+
+#[derive(serde::Serialize)]
+pub struct Subscriber {
+    id: uuid::Uuid,
+    email: String,
+    name: String,
+    status: SubscriptionStatus,
+    locale: String,
+    subscribed_at: DateTime<Utc>,
+}
+
+impl From<subscriptions::Model> for Subscriber {
+    fn from(model: subscriptions::Model) -> Self {
+        Self {
+            id: model.id,
+            email: model.email,
+            name: model.name,
+            status: model.status,
+            locale: model.locale,
+            subscribed_at: model.subscribed_at,
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+pub struct SubscriberDetail {
+    #[serde(flatten)]
+    subscriber: Subscriber,
+    /// 忽略加号标签和 Gmail 地址中的点之后与该订阅者相同的其他订阅者
+    possible_duplicates: Vec<uuid::Uuid>,
+}
+
+#[derive(serde::Serialize)]
+pub struct SubscriberPage {
+    subscribers: Vec<Subscriber>,
+    /// 没有下一页时为 `null`
+    next_cursor: Option<String>,
+}
+
+/// 分页游标，按订阅时间和 ID 倒序翻页，新订阅的用户不会导致翻页时重复或遗漏
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Cursor {
+    subscribed_at: DateTime<Utc>,
+    id: uuid::Uuid,
+}
+
+impl Cursor {
+    fn encode(&self) -> String {
+        format!(
+            "{}.{}",
+            self.subscribed_at.timestamp_micros(),
+            self.id.simple()
+        )
+    }
+
+    fn decode(s: &str) -> Option<Self> {
+        let (micros, id) = s.split_once('.')?;
+        Some(Self {
+            subscribed_at: DateTime::from_timestamp_micros(micros.parse().ok()?)?,
+            id: uuid::Uuid::parse_str(id).ok()?,
+        })
+    }
+}
+
+#[tracing::instrument(name = "查询订阅者列表", skip(state), fields(user_id = %*user_id))]
+pub async fn list_subscribers(
+    State(state): State<Arc<AppState>>,
+    Extension(user_id): Extension<UserId>,
+    Query(params): Query<ListParameters>,
+) -> Result<Json<SubscriberPage>, SubscribersError> {
+    let cursor = params
+        .cursor
+        .as_deref()
+        .map(|s| Cursor::decode(s).ok_or(SubscribersError::InvalidCursor))
+        .transpose()?;
+    let limit = params
+        .limit
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+
+    // 多取一条用于判断是否还有下一页
+    let mut subscribers = subscriptions::Entity::find()
+        .filter(filter_condition(&params, cursor))
+        .order_by_desc(subscriptions::Column::SubscribedAt)
+        .order_by_desc(subscriptions::Column::Id)
+        .limit(limit + 1)
+        .all(state.db.as_ref())
+        .await
+        .map_err(SubscribersError::DbError)?;
+
+    let next_cursor = if subscribers.len() as u64 > limit {
+        subscribers.truncate(limit as usize);
+        subscribers.last().map(|last| {
+            Cursor {
+                subscribed_at: last.subscribed_at,
+                id: last.id,
+            }
+            .encode()
+        })
+    } else {
+        None
+    };
+
+    Ok(Json(SubscriberPage {
+        subscribers: subscribers.into_iter().map(Subscriber::from).collect(),
+        next_cursor,
+    }))
+}
+
+fn filter_condition(params: &ListParameters, cursor: Option<Cursor>) -> Condition {
+    let mut condition = Condition::all();
+    if let Some(status) = params.status {
+        condition = condition.add(subscriptions::Column::Status.eq(status));
+    }
+    if let Some(email) = params.email.as_deref().filter(|e| !e.is_empty()) {
+        let pattern = format!("%{}%", escape_like(&email.to_lowercase()));
+        condition = condition.add(
+            Expr::expr(Func::lower(Expr::col(subscriptions::Column::Email)))
+                .like(LikeExpr::new(pattern).escape('\\')),
+        );
+    }
+    if let Some(from) = params.subscribed_from {
+        condition = condition.add(subscriptions::Column::SubscribedAt.gte(from));
+    }
+    if let Some(to) = params.subscribed_to {
+        condition = condition.add(subscriptions::Column::SubscribedAt.lt(to));
+    }
+    if let Some(cursor) = cursor {
+        condition = condition.add(
+            Condition::any()
+                .add(subscriptions::Column::SubscribedAt.lt(cursor.subscribed_at))
+                .add(
+                    Condition::all()
+                        .add(subscriptions::Column::SubscribedAt.eq(cursor.subscribed_at))
+                        .add(subscriptions::Column::Id.lt(cursor.id)),
+                ),
+        );
+    }
+    condition
+}
+
+/// 转义 LIKE 模式中的通配符，让用户输入按字面匹配
+fn escape_like(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[tracing::instrument(name = "查询订阅者", skip(state), fields(user_id = %*user_id))]
+pub async fn get_subscriber(
+    State(state): State<Arc<AppState>>,
+    Extension(user_id): Extension<UserId>,
+    Path(subscriber_id): Path<uuid::Uuid>,
+) -> Result<Json<SubscriberDetail>, SubscribersError> {
+    let subscriber = find_subscriber(state.db.as_ref(), subscriber_id)
+        .await
+        .map_err(SubscribersError::DbError)?
+        .ok_or(SubscribersError::NotFound)?;
+    let possible_duplicates = subscriptions::Entity::find()
+        .select_only()
+        .column(subscriptions::Column::Id)
+        .filter(subscriptions::Column::AliasKey.eq(&subscriber.alias_key))
+        .filter(subscriptions::Column::Id.ne(subscriber.id))
+        .order_by_asc(subscriptions::Column::SubscribedAt)
+        .into_tuple()
+        .all(state.db.as_ref())
+        .await
+        .map_err(SubscribersError::DbError)?;
+    Ok(Json(SubscriberDetail {
+        subscriber: subscriber.into(),
+        possible_duplicates,
+    }))
+}
+
+async fn find_subscriber(
+    db: &DatabaseConnection,
+    subscriber_id: uuid::Uuid,
+) -> Result<Option<subscriptions::Model>, DbErr> {
+    subscriptions::Entity::find_by_id(subscriber_id).one(db).await
+}
+
+/// 删除订阅者，确认令牌通过外键级联删除
+#[tracing::instrument(name = "删除订阅者", skip(state), fields(user_id = %*user_id))]
+pub async fn delete_subscriber(
+    State(state): State<Arc<AppState>>,
+    Extension(user_id): Extension<UserId>,
+    Path(subscriber_id): Path<uuid::Uuid>,
+) -> Result<StatusCode, SubscribersError> {
+    let result = subscriptions::Entity::delete_by_id(subscriber_id)
+        .exec(state.db.as_ref())
+        .await
+        .map_err(SubscribersError::DbError)?;
+    if result.rows_affected == 0 {
+        return Err(SubscribersError::NotFound);
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub enum SubscribersError {
+    InvalidCursor,
+    NotFound,
+    DbError(DbErr),
+}
+
+impl Display for SubscribersError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SubscribersError::InvalidCursor => write!(f, "分页游标无效"),
+            SubscribersError::NotFound => write!(f, "订阅者不存在"),
+            SubscribersError::DbError(_) => write!(f, "查询订阅者时发生数据库错误"),
+        }
+    }
+}
+
+impl Debug for SubscribersError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl Error for SubscribersError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SubscribersError::InvalidCursor | SubscribersError::NotFound => None,
+            SubscribersError::DbError(e) => Some(e),
+        }
+    }
+}
+
+impl IntoResponse for SubscribersError {
+    fn into_response(self) -> Response {
+        tracing::error!("{:?}", self);
+        match self {
+            SubscribersError::InvalidCursor => {
+                (StatusCode::BAD_REQUEST, self.to_string()).into_response()
+            }
+            SubscribersError::NotFound => (StatusCode::NOT_FOUND, self.to_string()).into_response(),
+            SubscribersError::DbError(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claim::assert_none;
+
+    use super::{Cursor, escape_like};
+
+    #[test]
+    fn a_cursor_survives_a_round_trip() {
+        let cursor = Cursor {
+            subscribed_at: chrono::DateTime::from_timestamp_micros(1_759_000_000_123_456).unwrap(),
+            id: uuid::Uuid::new_v4(),
+        };
+
+        assert_eq!(Cursor::decode(&cursor.encode()), Some(cursor));
+    }
+
+    #[test]
+    fn malformed_cursors_are_rejected() {
+        for cursor in ["", "abc", "123", "abc.def", "123.not-a-uuid"] {
+            assert_none!(Cursor::decode(cursor));
+        }
+    }
+
+    #[test]
+    fn like_wildcards_are_escaped() {
+        assert_eq!(escape_like(r"a%b_c\d"), r"a\%b\_c\\d");
+    }
+}
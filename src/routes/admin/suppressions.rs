@@ -0,0 +1,164 @@
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+use std::sync::Arc;
+
+use axum::{
+    Extension, Json,
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use sea_orm::{DbErr, EntityTrait, QueryOrder};
+
+use crate::{
+    authentication::UserId,
+    domain::{SubscriberEmail, SuppressionSource, normalize_email},
+    entities::suppressions,
+    routes::error_chain_fmt,
+    startup::AppState,
+    suppression_list::suppress,
+};
+
+#[derive(serde::Serialize)]
+pub struct Suppression {
+    email: String,
+    reason: String,
+    source: SuppressionSource,
+    created_at: DateTime<Utc>,
+}
+
+impl From<suppressions::Model> for Suppression {
+    fn from(model: suppressions::Model) -> Self {
+        Self {
+            email: model.email,
+            reason: model.reason,
+            source: model.source,
+            created_at: model.created_at,
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+pub struct NewSuppression {
+    email: String,
+    /// 加入抑制列表的原因，例如法律要求或角色账号
+    reason: String,
+}
+
+/// 按加入时间从新到旧列出抑制列表
+#[tracing::instrument(name = "查询抑制列表", skip(state), fields(user_id = %*user_id))]
+pub async fn list_suppressions(
+    State(state): State<Arc<AppState>>,
+    Extension(user_id): Extension<UserId>,
+) -> Result<Json<Vec<Suppression>>, SuppressionsError> {
+    let entries = suppressions::Entity::find()
+        .order_by_desc(suppressions::Column::CreatedAt)
+        .order_by_asc(suppressions::Column::Email)
+        .all(state.db.as_ref())
+        .await
+        .map_err(SuppressionsError::DbError)?;
+    Ok(Json(entries.into_iter().map(Suppression::from).collect()))
+}
+
+#[tracing::instrument(name = "添加抑制地址", skip(state, body), fields(user_id = %*user_id))]
+pub async fn add_suppression(
+    State(state): State<Arc<AppState>>,
+    Extension(user_id): Extension<UserId>,
+    Json(body): Json<NewSuppression>,
+) -> Result<(StatusCode, Json<Suppression>), SuppressionsError> {
+    let email = SubscriberEmail::parse(normalize_email(&body.email))
+        .map_err(|e| SuppressionsError::InvalidEmail(e.to_string()))?;
+    let reason = body.reason.trim();
+    if reason.is_empty() {
+        return Err(SuppressionsError::InvalidReason);
+    }
+
+    let inserted = suppress(
+        state.db.as_ref(),
+        email.as_ref(),
+        reason,
+        SuppressionSource::Admin,
+    )
+    .await
+    .map_err(SuppressionsError::DbError)?;
+    if !inserted {
+        return Err(SuppressionsError::AlreadySuppressed);
+    }
+    let entry = suppressions::Entity::find_by_id(email.as_ref())
+        .one(state.db.as_ref())
+        .await
+        .map_err(SuppressionsError::DbError)?
+        .ok_or(SuppressionsError::NotFound)?;
+    Ok((StatusCode::CREATED, Json(entry.into())))
+}
+
+/// 把地址移出抑制列表，之后可以重新订阅并收到邮件
+#[tracing::instrument(name = "移除抑制地址", skip(state), fields(user_id = %*user_id))]
+pub async fn remove_suppression(
+    State(state): State<Arc<AppState>>,
+    Extension(user_id): Extension<UserId>,
+    Path(email): Path<String>,
+) -> Result<StatusCode, SuppressionsError> {
+    let result = suppressions::Entity::delete_by_id(normalize_email(&email))
+        .exec(state.db.as_ref())
+        .await
+        .map_err(SuppressionsError::DbError)?;
+    if result.rows_affected == 0 {
+        return Err(SuppressionsError::NotFound);
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub enum SuppressionsError {
+    InvalidEmail(String),
+    InvalidReason,
+    AlreadySuppressed,
+    NotFound,
+    DbError(DbErr),
+}
+
+impl Display for SuppressionsError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SuppressionsError::InvalidEmail(e) => write!(f, "{}", e),
+            SuppressionsError::InvalidReason => write!(f, "原因不能为空"),
+            SuppressionsError::AlreadySuppressed => write!(f, "地址已在抑制列表中"),
+            SuppressionsError::NotFound => write!(f, "地址不在抑制列表中"),
+            SuppressionsError::DbError(_) => write!(f, "读写抑制列表时发生数据库错误"),
+        }
+    }
+}
+
+impl Debug for SuppressionsError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl Error for SuppressionsError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SuppressionsError::DbError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl IntoResponse for SuppressionsError {
+    fn into_response(self) -> Response {
+        tracing::error!("{:?}", self);
+        match self {
+            SuppressionsError::InvalidEmail(_) | SuppressionsError::InvalidReason => {
+                (StatusCode::BAD_REQUEST, self.to_string()).into_response()
+            }
+            SuppressionsError::AlreadySuppressed => {
+                (StatusCode::CONFLICT, self.to_string()).into_response()
+            }
+            SuppressionsError::NotFound => {
+                (StatusCode::NOT_FOUND, self.to_string()).into_response()
+            }
+            SuppressionsError::DbError(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+        }
+    }
+}
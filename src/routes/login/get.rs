@@ -0,0 +1,33 @@
+use std::fmt::Write;
+
+use axum::response::Html;
+use axum_messages::Messages;
+
+pub async fn login_form(messages: Messages) -> Html<String> {
+    let mut error_html = String::new();
+    for message in messages {
+        writeln!(error_html, "<p><i>{}</i></p>", message).unwrap();
+    }
+
+    Html(format!(
+        r#"<!DOCTYPE html>
+<html lang="zh-CN">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>登录</title>
+</head>
+<body>
+    {error_html}
+    <form action="/login" method="post">
+        <label>用户名
+            <input type="text" placeholder="请输入用户名" name="username">
+        </label>
+        <label>密码
+            <input type="password" placeholder="请输入密码" name="password">
+        </label>
+        <button type="submit">登录</button>
+    </form>
+</body>
+</html>"#,
+    ))
+}
@@ -0,0 +1,106 @@
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+use std::sync::Arc;
+
+use axum::{
+    Form,
+    extract::State,
+    response::{IntoResponse, Redirect, Response},
+};
+use axum_messages::Messages;
+use secrecy::SecretString;
+use tower_sessions::session;
+
+use crate::{
+    authentication::{AuthError, Credentials, validate_credentials},
+    routes::error_chain_fmt,
+    session_state::TypedSession,
+    startup::AppState,
+};
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    username: String,
+    password: SecretString,
+}
+
+#[tracing::instrument(
+    name = "管理员登录",
+    skip(state, session, messages, form),
+    fields(username = %form.username, user_id = tracing::field::Empty)
+)]
+pub async fn login(
+    State(state): State<Arc<AppState>>,
+    session: TypedSession,
+    messages: Messages,
+    Form(form): Form<FormData>,
+) -> Response {
+    let credentials = Credentials {
+        username: form.username,
+        password: form.password,
+    };
+
+    match log_in(credentials, &state, &session).await {
+        Ok(()) => Redirect::to("/admin/dashboard").into_response(),
+        Err(e) => {
+            tracing::warn!("{:?}", e);
+            messages.error(e.to_string());
+            Redirect::to("/login").into_response()
+        }
+    }
+}
+
+async fn log_in(
+    credentials: Credentials,
+    state: &AppState,
+    session: &TypedSession,
+) -> Result<(), LoginError> {
+    let user_id = validate_credentials(credentials, state.db.as_ref())
+        .await
+        .map_err(|e| match e {
+            AuthError::InvalidCredentials => LoginError::InvalidCredentials(e),
+            e => LoginError::UnexpectedError(e),
+        })?;
+    tracing::Span::current().record("user_id", tracing::field::display(&user_id));
+
+    session.renew().await.map_err(LoginError::SessionError)?;
+    session
+        .insert_user_id(user_id)
+        .await
+        .map_err(LoginError::SessionError)?;
+
+    Ok(())
+}
+
+pub enum LoginError {
+    InvalidCredentials(AuthError),
+    UnexpectedError(AuthError),
+    SessionError(session::Error),
+}
+
+impl Display for LoginError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoginError::InvalidCredentials(e) => write!(f, "{}", e),
+            LoginError::UnexpectedError(_) | LoginError::SessionError(_) => {
+                write!(f, "登录时发生未知错误, 请稍后重试")
+            }
+        }
+    }
+}
+
+impl Debug for LoginError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl Error for LoginError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            LoginError::InvalidCredentials(e) => Some(e),
+            LoginError::UnexpectedError(e) => Some(e),
+            LoginError::SessionError(e) => Some(e),
+        }
+    }
+}
@@ -1,3 +1,23 @@
+use std::error::Error;
+use std::fmt::Formatter;
+
+pub mod admin;
 pub mod health_check;
+pub mod login;
+pub mod newsletters;
 pub mod subscriptions;
 pub mod subscription_confirm;
+pub mod tracking;
+pub mod unsubscribe;
+pub mod webhooks;
+
+pub fn error_chain_fmt(e: &impl Error, f: &mut Formatter<'_>) -> std::fmt::Result {
+    writeln!(f, "{}\n", e)?;
+    let mut current = e.source();
+    while let Some(cause) = current {
+        writeln!(f, "Caused by: \n\t{}", cause)?;
+        current = cause.source();
+    }
+
+    Ok(())
+}
@@ -0,0 +1,341 @@
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+use std::sync::Arc;
+
+use axum::{
+    Json,
+    extract::State,
+    http::{HeaderMap, HeaderValue, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::Set, ColumnTrait, DatabaseConnection, DatabaseTransaction,
+    DbErr, EntityTrait, QueryFilter, TransactionTrait,
+};
+use secrecy::SecretString;
+
+use super::error_chain_fmt;
+use crate::{
+    authentication::{AuthError, Credentials, validate_credentials},
+    domain::{DEFAULT_LIST_SLUG, IssueStatus},
+    entities::{lists, newsletter_issue_lists, newsletter_issues},
+    idempotency::{IdempotencyError, IdempotencyKey, NextAction, save_response, try_processing},
+    newsletter_scheduler::enqueue_delivery_tasks,
+    startup::AppState,
+};
+
+#[derive(serde::Deserialize)]
+pub struct BodyData {
+    title: String,
+    content: Content,
+}
+
+#[derive(serde::Deserialize)]
+pub struct Content {
+    html: String,
+    text: String,
+}
+
+/// 通过 Basic 认证发布新闻邮件，与管理后台的表单使用相同的默认值：
+/// 发布到默认列表、立即发送并开启跟踪。提供 `Idempotency-Key` 请求头时重复的请求不会重复发布
+#[tracing::instrument(
+    name = "发布新闻邮件",
+    skip(state, headers, body),
+    fields(title = %body.title, username = tracing::field::Empty, user_id = tracing::field::Empty)
+)]
+pub async fn publish_newsletter(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(body): Json<BodyData>,
+) -> Result<Response, PublishError> {
+    let credentials = basic_authentication(&headers)?;
+    tracing::Span::current().record("username", tracing::field::display(&credentials.username));
+    let user_id = validate_credentials(credentials, state.db.as_ref())
+        .await
+        .map_err(|e| match e {
+            AuthError::InvalidCredentials => PublishError::AuthError(e),
+            e => PublishError::ValidateCredentialsError(e),
+        })?;
+    tracing::Span::current().record("user_id", tracing::field::display(&user_id));
+
+    let idempotency_key = headers
+        .contains_key(IdempotencyKey::HEADER_NAME)
+        .then(|| IdempotencyKey::from_request(&headers, None))
+        .transpose()
+        .map_err(PublishError::InvalidIdempotencyKey)?;
+    let issue = NewIssue {
+        title: body.title,
+        text_content: body.content.text,
+        html_content: body.content.html,
+        lists: find_target_lists(state.db.as_ref(), None).await?,
+        scheduled_at: None,
+        track_opens: true,
+        track_clicks: true,
+    };
+
+    publish_issue(
+        state.db.as_ref(),
+        user_id,
+        idempotency_key.as_ref(),
+        issue,
+        StatusCode::OK.into_response(),
+    )
+    .await
+}
+
+/// 从 `Authorization: Basic ...` 请求头中解析用户名和密码
+fn basic_authentication(headers: &HeaderMap) -> Result<Credentials, PublishError> {
+    let header_value = headers
+        .get(header::AUTHORIZATION)
+        .ok_or(PublishError::MissingCredentials("缺少 Authorization 请求头"))?
+        .to_str()
+        .map_err(|_| PublishError::MissingCredentials("Authorization 请求头不是合法的 UTF8 字符串"))?;
+    let base64encoded_segment = header_value
+        .strip_prefix("Basic ")
+        .ok_or(PublishError::MissingCredentials("认证方式不是 Basic"))?;
+    let decoded_bytes = base64::engine::general_purpose::STANDARD
+        .decode(base64encoded_segment)
+        .map_err(|_| PublishError::MissingCredentials("Basic 凭证不是合法的 base64 编码"))?;
+    let decoded_credentials = String::from_utf8(decoded_bytes)
+        .map_err(|_| PublishError::MissingCredentials("Basic 凭证不是合法的 UTF8 字符串"))?;
+
+    let (username, password) = decoded_credentials
+        .split_once(':')
+        .ok_or(PublishError::MissingCredentials("Basic 凭证中缺少 ':' 分隔符"))?;
+
+    Ok(Credentials {
+        username: username.to_string(),
+        password: SecretString::from(password.to_string()),
+    })
+}
+
+/// 一期待发布的新闻邮件
+pub struct NewIssue {
+    pub title: String,
+    pub text_content: String,
+    pub html_content: String,
+    pub lists: Vec<lists::Model>,
+    /// 为空时立即创建投递任务
+    pub scheduled_at: Option<DateTime<Utc>>,
+    pub track_opens: bool,
+    pub track_clicks: bool,
+}
+
+/// 保存新闻邮件，立即发送时同时创建投递任务，`response` 作为成功的响应返回
+///
+/// 提供幂等键时，同一个用户重复的请求直接返回第一次保存的响应
+#[tracing::instrument(name = "保存并发布新闻邮件", skip_all, fields(title = %issue.title))]
+pub async fn publish_issue(
+    db: &DatabaseConnection,
+    user_id: uuid::Uuid,
+    idempotency_key: Option<&IdempotencyKey>,
+    issue: NewIssue,
+    response: Response,
+) -> Result<Response, PublishError> {
+    let txn = match idempotency_key {
+        Some(key) => match try_processing(db, key, user_id)
+            .await
+            .map_err(PublishError::IdempotencyError)?
+        {
+            NextAction::StartProcessing(txn) => txn,
+            NextAction::ReturnSavedResponse(saved_response) => {
+                return Ok(saved_response);
+            }
+        },
+        None => db.begin().await.map_err(PublishError::StoreIssueError)?,
+    };
+
+    let issue_id = insert_newsletter_issue(&txn, &issue)
+        .await
+        .map_err(PublishError::StoreIssueError)?;
+    insert_issue_lists(&txn, issue_id, &issue.lists)
+        .await
+        .map_err(PublishError::StoreIssueError)?;
+    // 计划发送的新闻邮件由调度任务在到期后创建投递任务
+    if issue.scheduled_at.is_none() {
+        enqueue_delivery_tasks(&txn, issue_id)
+            .await
+            .map_err(PublishError::EnqueueError)?;
+    }
+
+    let response = match idempotency_key {
+        Some(key) => save_response(txn, key, user_id, response)
+            .await
+            .map_err(PublishError::IdempotencyError)?,
+        None => {
+            txn.commit().await.map_err(PublishError::StoreIssueError)?;
+            response
+        }
+    };
+    Ok(response)
+}
+
+/// 解析计划发送时间，已经过去的时间按立即发送处理
+pub fn parse_schedule(scheduled_at: Option<&str>) -> Result<Option<DateTime<Utc>>, PublishError> {
+    let Some(scheduled_at) = scheduled_at.map(str::trim).filter(|s| !s.is_empty()) else {
+        return Ok(None);
+    };
+    let scheduled_at = DateTime::parse_from_rfc3339(scheduled_at)
+        .map_err(|_| PublishError::InvalidSchedule(scheduled_at.to_string()))?
+        .with_timezone(&Utc);
+    Ok((scheduled_at > Utc::now()).then_some(scheduled_at))
+}
+
+#[tracing::instrument(name = "保存新闻邮件", skip_all)]
+async fn insert_newsletter_issue(
+    txn: &DatabaseTransaction,
+    issue: &NewIssue,
+) -> Result<uuid::Uuid, DbErr> {
+    let newsletter_issue_id = uuid::Uuid::new_v4();
+    let status = match issue.scheduled_at {
+        Some(_) => IssueStatus::Scheduled,
+        None => IssueStatus::Enqueued,
+    };
+    newsletter_issues::ActiveModel {
+        newsletter_issue_id: Set(newsletter_issue_id),
+        title: Set(issue.title.clone()),
+        text_content: Set(issue.text_content.clone()),
+        html_content: Set(issue.html_content.clone()),
+        published_at: Set(issue.scheduled_at.unwrap_or_else(Utc::now)),
+        status: Set(status),
+        scheduled_at: Set(issue.scheduled_at),
+        track_opens: Set(issue.track_opens),
+        track_clicks: Set(issue.track_clicks),
+    }
+    .insert(txn)
+    .await?;
+
+    Ok(newsletter_issue_id)
+}
+
+/// 按标识查找要发布到的列表，忽略空白和重复的标识，任一列表不存在时拒绝发布
+#[tracing::instrument(name = "查找发布的目标列表", skip(db))]
+pub async fn find_target_lists(
+    db: &DatabaseConnection,
+    lists: Option<&str>,
+) -> Result<Vec<lists::Model>, PublishError> {
+    let mut slugs: Vec<&str> = lists
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|slug| !slug.is_empty())
+        .collect();
+    if slugs.is_empty() {
+        slugs.push(DEFAULT_LIST_SLUG);
+    }
+    slugs.sort_unstable();
+    slugs.dedup();
+
+    let found = lists::Entity::find()
+        .filter(lists::Column::Slug.is_in(slugs.iter().copied()))
+        .all(db)
+        .await
+        .map_err(PublishError::ListLookupError)?;
+    match slugs
+        .into_iter()
+        .find(|slug| !found.iter().any(|list| list.slug == *slug))
+    {
+        Some(missing) => Err(PublishError::UnknownList(missing.to_string())),
+        None => Ok(found),
+    }
+}
+
+#[tracing::instrument(name = "保存新闻邮件的目标列表", skip_all)]
+async fn insert_issue_lists(
+    txn: &DatabaseTransaction,
+    newsletter_issue_id: uuid::Uuid,
+    lists: &[lists::Model],
+) -> Result<(), DbErr> {
+    newsletter_issue_lists::Entity::insert_many(lists.iter().map(|list| {
+        newsletter_issue_lists::ActiveModel {
+            newsletter_issue_id: Set(newsletter_issue_id),
+            list_id: Set(list.list_id),
+        }
+    }))
+    .exec_without_returning(txn)
+    .await?;
+    Ok(())
+}
+
+pub enum PublishError {
+    MissingCredentials(&'static str),
+    AuthError(AuthError),
+    ValidateCredentialsError(AuthError),
+    InvalidIdempotencyKey(String),
+    UnknownList(String),
+    InvalidSchedule(String),
+    ListLookupError(DbErr),
+    IdempotencyError(IdempotencyError),
+    StoreIssueError(DbErr),
+    EnqueueError(DbErr),
+}
+
+impl Display for PublishError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PublishError::MissingCredentials(e) => write!(f, "缺少有效的认证信息: {}", e),
+            PublishError::AuthError(_) => write!(f, "认证失败"),
+            PublishError::ValidateCredentialsError(_) => write!(f, "校验用户凭证时发生错误"),
+            PublishError::InvalidIdempotencyKey(e) => write!(f, "幂等键无效: {}", e),
+            PublishError::UnknownList(slug) => write!(f, "邮件列表 {} 不存在", slug),
+            PublishError::InvalidSchedule(s) => write!(f, "{} 不是 RFC 3339 格式的时间", s),
+            PublishError::ListLookupError(_) => write!(f, "查询邮件列表失败"),
+            PublishError::IdempotencyError(_) => write!(f, "处理幂等键失败"),
+            PublishError::StoreIssueError(_) => write!(f, "保存新闻邮件失败"),
+            PublishError::EnqueueError(_) => write!(f, "创建投递任务失败"),
+        }
+    }
+}
+
+impl Debug for PublishError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl Error for PublishError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PublishError::MissingCredentials(_)
+            | PublishError::InvalidIdempotencyKey(_)
+            | PublishError::UnknownList(_)
+            | PublishError::InvalidSchedule(_) => None,
+            PublishError::AuthError(e) => Some(e),
+            PublishError::ValidateCredentialsError(e) => Some(e),
+            PublishError::ListLookupError(e) => Some(e),
+            PublishError::IdempotencyError(e) => Some(e),
+            PublishError::StoreIssueError(e) => Some(e),
+            PublishError::EnqueueError(e) => Some(e),
+        }
+    }
+}
+
+impl IntoResponse for PublishError {
+    fn into_response(self) -> Response {
+        tracing::error!("{:?}", self);
+        match self {
+            PublishError::MissingCredentials(_) | PublishError::AuthError(_) => {
+                let mut response = StatusCode::UNAUTHORIZED.into_response();
+                response.headers_mut().insert(
+                    header::WWW_AUTHENTICATE,
+                    HeaderValue::from_static(r#"Basic realm="publish""#),
+                );
+                response
+            }
+            PublishError::InvalidIdempotencyKey(e) => (StatusCode::BAD_REQUEST, e).into_response(),
+            PublishError::UnknownList(_) | PublishError::InvalidSchedule(_) => {
+                (StatusCode::BAD_REQUEST, self.to_string()).into_response()
+            }
+            PublishError::IdempotencyError(IdempotencyError::RequestInProgress) => {
+                StatusCode::CONFLICT.into_response()
+            }
+            PublishError::ValidateCredentialsError(_)
+            | PublishError::IdempotencyError(_)
+            | PublishError::ListLookupError(_)
+            | PublishError::StoreIssueError(_)
+            | PublishError::EnqueueError(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+        }
+    }
+}
@@ -1,61 +1,439 @@
-
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
 use std::sync::Arc;
 
-use axum::{extract::{Query, State}, http::StatusCode};
-use sea_orm::{ActiveModelTrait, ActiveValue::Set, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter};
+use axum::{
+    Form,
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{Html, IntoResponse, Response},
+};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::Set, ColumnTrait, ConnectionTrait, DatabaseConnection,
+    DatabaseTransaction, DbErr, EntityTrait, QueryFilter, QuerySelect, TransactionTrait,
+};
 
-use crate::{entities::{subscription_tokens, subscriptions}, startup::AppState};
+use super::{
+    error_chain_fmt,
+    subscriptions::{
+        StoreTokenError, generate_subscription_token, send_confirmation_email, store_token,
+    },
+    unsubscribe::unsubscribe_link,
+};
+use crate::{
+    domain::{
+        NewSubscriber, StatusTransitionError, SubscriberEmail, SubscriberName, SubscriptionStatus,
+        ValidationError,
+    },
+    email_client::{EmailClient, EmailError},
+    entities::{list_memberships, lists, subscription_tokens, subscriptions},
+    i18n::{Locale, Localize, Localized},
+    rate_limit::RateLimited,
+    startup::AppState,
+    templates::{EmailTemplate, EmailTemplates, PageTemplate},
+};
 
 #[derive(serde::Deserialize)]
 pub struct Parameters {
     subscription_token: String,
 }
 
-#[tracing::instrument(name = "确认订阅", skip(params, state))]
-pub async fn confirm(Query(params): Query<Parameters>, State(state): State<Arc<AppState>>) -> StatusCode {
-    let id = match get_subscriber_id_from_token(state.db.as_ref(), &params.subscription_token).await {
-        Ok(id) => id,
-        Err(_) => return StatusCode::INTERNAL_SERVER_ERROR,
-    };
+#[tracing::instrument(name = "确认订阅", skip(params, state, headers))]
+pub async fn confirm(
+    Query(params): Query<Parameters>,
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Response, Localized<ConfirmError>> {
+    let locale = Locale::negotiate(None, &headers);
+    match confirm_token(&state, &params.subscription_token).await {
+        Ok(()) => Ok(StatusCode::OK.into_response()),
+        // 过期的令牌给出单独的页面，让订阅者可以重新获取确认邮件
+        Err(ConfirmError::ExpiredToken {
+            subscription_token,
+            locale,
+        }) => {
+            let mut context = tera::Context::new();
+            context.insert("subscription_token", &subscription_token);
+            let page = state
+                .templates
+                .render_page(PageTemplate::ConfirmationExpired, locale, &context)
+                .map_err(|e| Localized::new(locale, ConfirmError::RenderPageError(e)))?;
+            Ok((StatusCode::GONE, Html(page)).into_response())
+        }
+        Err(e) => Err(Localized::new(locale, e)),
+    }
+}
+
+async fn confirm_token(state: &AppState, subscription_token: &str) -> Result<(), ConfirmError> {
+    let txn = state.db.begin().await.map_err(ConfirmError::DbError)?;
+    let token = get_valid_token(&txn, subscription_token).await?;
+    let subscriber = confirm_subscriber(&txn, token.subscriber_id).await?;
+    confirm_membership(&txn, token.list_id, subscriber.id).await?;
+    delete_tokens(&txn, subscriber.id, token.list_id)
+        .await
+        .map_err(ConfirmError::DbError)?;
+    txn.commit().await.map_err(ConfirmError::DbError)?;
 
-    match id {
-        None => StatusCode::UNAUTHORIZED,
-        Some(subscriber_id) => {
-            if confirm_subscriber(state.db.as_ref(), subscriber_id).await.is_err() {
-                return StatusCode::INTERNAL_SERVER_ERROR;
+    // 订阅已经确认成功，欢迎邮件发送失败不影响本次请求的结果
+    match SubscriberEmail::parse(subscriber.email) {
+        Ok(email) => {
+            let locale = Locale::parse(&subscriber.locale).unwrap_or_default();
+            let unsubscribe_link = unsubscribe_link(
+                state.base_url.as_ref(),
+                &state.hmac_secret.unsubscribe,
+                subscriber.id,
+            );
+            if let Err(e) = send_welcome_email(
+                state.email_client.as_ref(),
+                state.templates.as_ref(),
+                email,
+                &subscriber.name,
+                locale,
+                &unsubscribe_link,
+            )
+            .await
+            {
+                tracing::error!(
+                    error.cause_chain = ?e,
+                    error.message = %e,
+                    "发送欢迎邮件失败",
+                );
             }
-            StatusCode::OK
         }
-        
+        Err(e) => {
+            tracing::error!(
+                error.cause_chain = ?e,
+                "跳过欢迎邮件, 订阅者存储的邮箱无效",
+            );
+        }
     }
+    Ok(())
 }
 
-#[tracing::instrument(name = "通过令牌获取订阅者ID", skip(subscriber_id, db))]
-pub async fn confirm_subscriber(
-    db: &DatabaseConnection,
+/// 令牌过期时，订阅者可以凭旧令牌重新获取一封确认邮件
+#[tracing::instrument(name = "重新发送确认邮件", skip(form, state, headers))]
+pub async fn resend_confirmation(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Form(form): Form<Parameters>,
+) -> Result<Html<String>, Localized<ConfirmError>> {
+    let locale = Locale::negotiate(None, &headers);
+    let locale = resend_confirmation_email(&state, &form.subscription_token)
+        .await
+        .map_err(|e| Localized::new(locale, e))?;
+
+    state
+        .templates
+        .render_page(
+            PageTemplate::ConfirmationResent,
+            locale,
+            &tera::Context::new(),
+        )
+        .map(Html)
+        .map_err(|e| Localized::new(locale, ConfirmError::RenderPageError(e)))
+}
+
+/// 需要时重新发送确认邮件，返回订阅者的语言
+async fn resend_confirmation_email(
+    state: &AppState,
+    subscription_token: &str,
+) -> Result<Locale, ConfirmError> {
+    let token = get_token(state.db.as_ref(), subscription_token)
+        .await
+        .map_err(ConfirmError::DbError)?
+        .ok_or(ConfirmError::UnknownToken)?;
+    let subscriber = get_subscriber(state.db.as_ref(), token.subscriber_id)
+        .await
+        .map_err(ConfirmError::DbError)?;
+    let membership = get_membership(state.db.as_ref(), token.list_id, subscriber.id)
+        .await
+        .map_err(ConfirmError::DbError)?;
+    let locale = Locale::parse(&subscriber.locale).unwrap_or_default();
+
+    // 邮箱和列表中的订阅都已经确认过时不需要新的确认邮件
+    let awaiting_confirmation = subscriber.status == SubscriptionStatus::PendingConfirmation
+        || (subscriber.status == SubscriptionStatus::Confirmed
+            && membership.status == SubscriptionStatus::PendingConfirmation);
+    if awaiting_confirmation {
+        let list = get_list(state.db.as_ref(), token.list_id)
+            .await
+            .map_err(ConfirmError::DbError)?;
+        let new_subscriber = NewSubscriber {
+            email: SubscriberEmail::parse(subscriber.email)
+                .map_err(ConfirmError::InvalidStoredSubscriber)?,
+            name: SubscriberName::parse(subscriber.name)
+                .map_err(ConfirmError::InvalidStoredSubscriber)?,
+            locale,
+        };
+        state
+            .rate_limiter
+            .check_email(&new_subscriber.email)
+            .await
+            .map_err(ConfirmError::RateLimited)?;
+
+        let txn = state.db.begin().await.map_err(ConfirmError::DbError)?;
+        delete_tokens(&txn, subscriber.id, list.list_id)
+            .await
+            .map_err(ConfirmError::DbError)?;
+        let subscription_token = generate_subscription_token();
+        store_token(
+            &txn,
+            subscriber.id,
+            list.list_id,
+            &subscription_token,
+            state.subscription_token_ttl,
+        )
+        .await
+        .map_err(ConfirmError::StoreTokenError)?;
+        txn.commit().await.map_err(ConfirmError::DbError)?;
+
+        let unsubscribe_link = unsubscribe_link(
+            state.base_url.as_ref(),
+            &state.hmac_secret.unsubscribe,
+            subscriber.id,
+        );
+        send_confirmation_email(
+            state.email_client.as_ref(),
+            state.templates.as_ref(),
+            new_subscriber,
+            &list.name,
+            state.base_url.as_ref(),
+            &subscription_token,
+            &unsubscribe_link,
+        )
+        .await
+        .map_err(ConfirmError::SendEmailError)?;
+    }
+
+    Ok(locale)
+}
+
+/// 查找并锁住未过期的令牌，过期的令牌保留下来用于重新发送确认邮件
+///
+/// 令牌在同一个事务中被删除，同时点击两次链接时后到的请求会在锁释放后找不到令牌，
+/// 因此每个令牌只会被使用一次，欢迎邮件也只发送一封
+async fn get_valid_token(
+    txn: &DatabaseTransaction,
+    token: &str,
+) -> Result<subscription_tokens::Model, ConfirmError> {
+    let token = subscription_tokens::Entity::find()
+        .filter(subscription_tokens::Column::SubscriptionToken.eq(token))
+        .lock_exclusive()
+        .one(txn)
+        .await
+        .map_err(ConfirmError::DbError)?
+        .ok_or(ConfirmError::UnknownToken)?;
+
+    if token.expires_at <= chrono::Utc::now() {
+        let subscriber = get_subscriber(txn, token.subscriber_id)
+            .await
+            .map_err(ConfirmError::DbError)?;
+        return Err(ConfirmError::ExpiredToken {
+            subscription_token: token.subscription_token,
+            locale: Locale::parse(&subscriber.locale).unwrap_or_default(),
+        });
+    }
+    Ok(token)
+}
+
+async fn get_subscriber(
+    db: &impl ConnectionTrait,
     subscriber_id: uuid::Uuid,
-) -> Result<(), DbErr> {
+) -> Result<subscriptions::Model, DbErr> {
+    subscriptions::Entity::find_by_id(subscriber_id)
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::Custom("订阅者未找到".into()))
+}
 
-    let mut subscriber: subscriptions::ActiveModel = subscriptions::Entity::find_by_id(subscriber_id)
+async fn get_membership(
+    db: &impl ConnectionTrait,
+    list_id: uuid::Uuid,
+    subscriber_id: uuid::Uuid,
+) -> Result<list_memberships::Model, DbErr> {
+    list_memberships::Entity::find_by_id((list_id, subscriber_id))
         .one(db)
         .await?
-        .ok_or_else(|| DbErr::Custom("订阅者未找到".into()))?
-        .into();
+        .ok_or_else(|| DbErr::RecordNotFound("订阅者不在该列表中".into()))
+}
+
+async fn get_list(db: &impl ConnectionTrait, list_id: uuid::Uuid) -> Result<lists::Model, DbErr> {
+    lists::Entity::find_by_id(list_id)
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound(format!("邮件列表 {} 不存在", list_id)))
+}
+
+/// 只有待确认的订阅者可以确认，例如已退订的订阅者不能凭旧令牌直接恢复订阅；
+/// 邮箱已经确认过的订阅者确认的是新加入的列表，状态保持不变
+#[tracing::instrument(name = "通过令牌获取订阅者ID", skip(subscriber_id, db))]
+pub async fn confirm_subscriber(
+    db: &impl ConnectionTrait,
+    subscriber_id: uuid::Uuid,
+) -> Result<subscriptions::Model, ConfirmError> {
+    let subscriber = get_subscriber(db, subscriber_id)
+        .await
+        .map_err(ConfirmError::DbError)?;
+    if subscriber.status == SubscriptionStatus::Confirmed {
+        return Ok(subscriber);
+    }
+    let status = subscriber
+        .status
+        .transition_to(SubscriptionStatus::Confirmed)
+        .map_err(ConfirmError::InvalidStatus)?;
+
+    let mut subscriber: subscriptions::ActiveModel = subscriber.into();
+    subscriber.status = Set(status);
+    subscriber.update(db).await.map_err(ConfirmError::DbError)
+}
 
-    subscriber.status = Set("confirmed".to_string());
+/// 确认订阅者在令牌对应列表中的订阅，列表中已确认的订阅保持不变
+#[tracing::instrument(name = "确认列表中的订阅", skip(db))]
+async fn confirm_membership(
+    db: &impl ConnectionTrait,
+    list_id: uuid::Uuid,
+    subscriber_id: uuid::Uuid,
+) -> Result<(), ConfirmError> {
+    let membership = get_membership(db, list_id, subscriber_id)
+        .await
+        .map_err(ConfirmError::DbError)?;
+    if membership.status == SubscriptionStatus::Confirmed {
+        return Ok(());
+    }
+    let status = membership
+        .status
+        .transition_to(SubscriptionStatus::Confirmed)
+        .map_err(ConfirmError::InvalidStatus)?;
 
-    subscriber.update(db).await?;
+    let mut membership: list_memberships::ActiveModel = membership.into();
+    membership.status = Set(status);
+    membership.update(db).await.map_err(ConfirmError::DbError)?;
     Ok(())
 }
 
-pub async fn get_subscriber_id_from_token(
+/// 令牌使用后即删除，同一订阅者在该列表中的其他令牌也随之失效
+#[tracing::instrument(name = "删除订阅令牌", skip(db))]
+pub async fn delete_tokens(
+    db: &impl ConnectionTrait,
+    subscriber_id: uuid::Uuid,
+    list_id: uuid::Uuid,
+) -> Result<(), DbErr> {
+    subscription_tokens::Entity::delete_many()
+        .filter(subscription_tokens::Column::SubscriberId.eq(subscriber_id))
+        .filter(subscription_tokens::Column::ListId.eq(list_id))
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+#[tracing::instrument(name = "发送欢迎邮件", skip_all)]
+pub async fn send_welcome_email(
+    email_client: &EmailClient,
+    templates: &EmailTemplates,
+    recipient: SubscriberEmail,
+    name: &str,
+    locale: Locale,
+    unsubscribe_link: &str,
+) -> Result<(), EmailError> {
+    let mut context = tera::Context::new();
+    context.insert("name", name);
+    context.insert("unsubscribe_link", unsubscribe_link);
+    let email = templates
+        .render(EmailTemplate::Welcome, locale, &context)
+        .map_err(EmailError::TemplateError)?;
+
+    email_client
+        .send_transactional_email(
+            recipient,
+            &email.subject,
+            &email.html_content,
+            &email.text_content,
+        )
+        .await
+}
+
+pub async fn get_token(
     db: &DatabaseConnection,
     token: &str,
-) -> Result<Option<uuid::Uuid>, DbErr> {
-    let result = subscription_tokens::Entity::find()
+) -> Result<Option<subscription_tokens::Model>, DbErr> {
+    subscription_tokens::Entity::find()
         .filter(subscription_tokens::Column::SubscriptionToken.eq(token))
         .one(db)
-        .await?;
+        .await
+}
 
-    Ok(result.map(|t| t.subscriber_id))
-}
\ No newline at end of file
+pub enum ConfirmError {
+    UnknownToken,
+    ExpiredToken {
+        subscription_token: String,
+        locale: Locale,
+    },
+    InvalidStatus(StatusTransitionError),
+    RateLimited(RateLimited),
+    InvalidStoredSubscriber(ValidationError),
+    StoreTokenError(StoreTokenError),
+    SendEmailError(EmailError),
+    RenderPageError(tera::Error),
+    DbError(DbErr),
+}
+
+impl Localize for ConfirmError {
+    fn localize(&self, locale: Locale) -> String {
+        let key = match self {
+            ConfirmError::UnknownToken => "confirm-error-unknown-token",
+            ConfirmError::ExpiredToken { .. } => "confirm-error-expired-token",
+            ConfirmError::InvalidStatus(_) => "confirm-error-invalid-status",
+            ConfirmError::RateLimited(e) => return e.localize(locale),
+            ConfirmError::InvalidStoredSubscriber(_) => "confirm-error-invalid-subscriber",
+            ConfirmError::StoreTokenError(_) => "subscribe-error-store-token",
+            ConfirmError::SendEmailError(_) => "subscribe-error-send-email",
+            ConfirmError::RenderPageError(_) => "confirm-error-render-page",
+            ConfirmError::DbError(_) => "confirm-error-database",
+        };
+        locale.translate(key, None)
+    }
+}
+
+impl Display for ConfirmError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.localize(Locale::default()))
+    }
+}
+
+impl Debug for ConfirmError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl Error for ConfirmError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ConfirmError::UnknownToken | ConfirmError::ExpiredToken { .. } => None,
+            ConfirmError::InvalidStatus(e) => Some(e),
+            ConfirmError::RateLimited(e) => Some(e),
+            ConfirmError::InvalidStoredSubscriber(e) => Some(e),
+            ConfirmError::StoreTokenError(e) => Some(e),
+            ConfirmError::SendEmailError(e) => Some(e),
+            ConfirmError::RenderPageError(e) => Some(e),
+            ConfirmError::DbError(e) => Some(e),
+        }
+    }
+}
+
+impl IntoResponse for ConfirmError {
+    fn into_response(self) -> Response {
+        tracing::error!("{:?}", self);
+        match self {
+            ConfirmError::UnknownToken => StatusCode::UNAUTHORIZED.into_response(),
+            ConfirmError::InvalidStatus(_) => StatusCode::CONFLICT.into_response(),
+            ConfirmError::RateLimited(e) => e.into_response(),
+            ConfirmError::ExpiredToken { .. } => StatusCode::GONE.into_response(),
+            ConfirmError::InvalidStoredSubscriber(_)
+            | ConfirmError::StoreTokenError(_)
+            | ConfirmError::SendEmailError(_)
+            | ConfirmError::RenderPageError(_)
+            | ConfirmError::DbError(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+        }
+    }
+}
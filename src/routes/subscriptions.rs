@@ -1,90 +1,343 @@
 use std::error::Error;
 use std::fmt::{Debug, Display, Formatter};
 use std::sync::Arc;
+use std::time::Duration;
 
-use axum::{Form, extract::State, http::StatusCode};
 use axum::response::{IntoResponse, Response};
-use rand::{distr::Alphanumeric, Rng};
-use sea_orm::{ActiveModelTrait, ActiveValue::Set, DatabaseTransaction, DbErr, TransactionTrait};
+use axum::{
+    Json,
+    extract::State,
+    http::{HeaderMap, HeaderValue, StatusCode, header},
+};
+use rand::{Rng, distr::Alphanumeric};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::Set, ColumnTrait, ConnectionTrait, DatabaseTransaction, DbErr,
+    EntityTrait, QueryFilter, QuerySelect, TransactionTrait, sea_query::OnConflict,
+};
+
+use fluent_bundle::FluentArgs;
 
+use super::{error_chain_fmt, subscription_confirm::delete_tokens, unsubscribe::unsubscribe_link};
 use crate::{
-    domain::{NewSubscriber, SubscriberEmail, SubscriberName},
-    email_client::EmailClient,
-    entities::{subscription_tokens, subscriptions}, startup::{AppState, ApplicationBaseUrl},
+    domain::{
+        DEFAULT_LIST_SLUG, NewSubscriber, SubscriberEmail, SubscriberName, SubscriptionStatus,
+        ValidationError, email_alias_key, normalize_email,
+    },
+    email_client::{EmailClient, EmailError},
+    entities::{list_memberships, lists, subscription_tokens, subscriptions},
+    i18n::{Locale, Localize, Localized},
+    rate_limit::RateLimited,
+    startup::{AppState, ApplicationBaseUrl},
+    templates::{EmailTemplate, EmailTemplates},
+    utils::{FieldError, FormOrJson, JsonErrorBody},
 };
 
+/// 订阅请求，可以以表单或 JSON 提交
 #[derive(serde::Deserialize, Clone)]
 pub struct FormData {
     email: String,
     name: String,
+    /// 订阅者偏好的语言，未提供时使用 `Accept-Language` 请求头
+    locale: Option<String>,
+    /// 订阅的列表标识，未提供时订阅默认列表
+    list: Option<String>,
 }
 
-impl TryFrom<FormData> for NewSubscriber {
-    type Error = String;
-
-    fn try_from(form: FormData) -> Result<Self, Self::Error> {
-        let name = SubscriberName::parse(form.name)?;
-        let email = SubscriberEmail::parse(form.email)?;
-        Ok(Self { email, name })
+impl FormData {
+    /// 校验所有字段，返回全部出错的字段而不是只返回第一个
+    fn parse(self, locale: Locale) -> Result<NewSubscriber, Vec<ValidationError>> {
+        match (
+            SubscriberName::parse(self.name),
+            SubscriberEmail::parse(self.email),
+        ) {
+            (Ok(name), Ok(email)) => Ok(NewSubscriber {
+                email,
+                name,
+                locale,
+            }),
+            (name, email) => Err(name.err().into_iter().chain(email.err()).collect()),
+        }
     }
 }
 
 #[tracing::instrument(
     name = "添加一个新的订阅者",
-    skip(state, form),
+    skip(state, headers, body),
     fields(
-        subscriber_email = %form.email,
-        subscriber_name = %form.name
+        subscriber_email = tracing::field::Empty,
+        subscriber_name = tracing::field::Empty
     )
 )]
 pub async fn subscribe(
     State(state): State<Arc<AppState>>,
-    Form(form): Form<FormData>,
+    headers: HeaderMap,
+    body: FormOrJson<FormData>,
+) -> Result<(), Response> {
+    let is_json = body.is_json();
+    let form = body.into_inner();
+    let span = tracing::Span::current();
+    span.record("subscriber_email", tracing::field::display(&form.email));
+    span.record("subscriber_name", tracing::field::display(&form.name));
+
+    let locale = Locale::negotiate(form.locale.as_deref(), &headers);
+    add_subscriber(&state, form, locale).await.map_err(|e| {
+        if is_json {
+            json_error_response(locale, e)
+        } else {
+            Localized::new(locale, e).into_response()
+        }
+    })
+}
+
+/// JSON 请求的错误响应，状态码和响应头与表单请求相同，响应体改为 JSON
+fn json_error_response(locale: Locale, error: SubscribeError) -> Response {
+    let errors = match &error {
+        SubscribeError::ValidationError(errors) => errors
+            .iter()
+            .map(|e| FieldError {
+                field: e.field().into(),
+                message: e.localize(locale),
+            })
+            .collect(),
+        SubscribeError::UnknownList(_) => vec![FieldError {
+            field: "list".into(),
+            message: error.localize(locale),
+        }],
+        _ => vec![],
+    };
+    let body = JsonErrorBody {
+        message: error.localize(locale),
+        errors,
+    };
+    let (mut parts, _) = error.into_response().into_parts();
+    parts.headers.remove(header::CONTENT_TYPE);
+    parts.headers.insert(
+        header::CONTENT_LANGUAGE,
+        HeaderValue::from_static(locale.as_str()),
+    );
+    let mut response = Json(body).into_response();
+    *response.status_mut() = parts.status;
+    response.headers_mut().extend(parts.headers);
+    response
+}
+
+async fn add_subscriber(
+    state: &AppState,
+    form: FormData,
+    locale: Locale,
 ) -> Result<(), SubscribeError> {
-    let new_subscriber = form.try_into()?;
+    let list_slug = form
+        .list
+        .clone()
+        .filter(|l| !l.is_empty())
+        .unwrap_or_else(|| DEFAULT_LIST_SLUG.into());
+    let new_subscriber = form.parse(locale)?;
+    let list = find_list(state.db.as_ref(), &list_slug)
+        .await
+        .map_err(SubscribeError::InsertSubscriberError)?
+        .ok_or(SubscribeError::UnknownList(list_slug))?;
+    // 不论邮箱处于什么状态都先扣减配额，响应不会因此泄露邮箱是否已订阅
+    state
+        .rate_limiter
+        .check_email(&new_subscriber.email)
+        .await?;
 
     let txn = state.db.begin().await.map_err(SubscribeError::PoolError)?;
 
-    let subscription_id = insert_subscriber(&txn, &new_subscriber).await.map_err(SubscribeError::InsertSubscriberError)?.id;
+    let (subscription_id, address_confirmed) = match insert_subscriber(&txn, &new_subscriber)
+        .await
+        .map_err(SubscribeError::InsertSubscriberError)?
+    {
+        Some(subscription_id) => (subscription_id, false),
+        None => {
+            let existing = get_subscriber_for_update(&txn, &new_subscriber.email)
+                .await
+                .map_err(SubscribeError::InsertSubscriberError)?;
+            if existing.status == SubscriptionStatus::Confirmed {
+                (existing.id, true)
+            } else if existing
+                .status
+                .can_transition_to(SubscriptionStatus::PendingConfirmation)
+            {
+                // 待确认、已退订或退信的订阅者重新走一遍确认流程
+                let subscription_id = restart_confirmation(&txn, existing, &new_subscriber)
+                    .await
+                    .map_err(SubscribeError::InsertSubscriberError)?;
+                (subscription_id, false)
+            } else {
+                // 投诉过的订阅者不再发送任何邮件，响应与新订阅完全相同，调用方无法借此判断邮箱是否已订阅
+                txn.commit()
+                    .await
+                    .map_err(SubscribeError::TransactionCommitError)?;
+                return Ok(());
+            }
+        }
+    };
+
+    // 已经确认过的订阅同样不发送任何邮件
+    if !join_list(&txn, list.list_id, subscription_id, address_confirmed)
+        .await
+        .map_err(SubscribeError::InsertSubscriberError)?
+    {
+        txn.commit()
+            .await
+            .map_err(SubscribeError::TransactionCommitError)?;
+        return Ok(());
+    }
 
+    // 同一列表的旧令牌全部作废
+    delete_tokens(&txn, subscription_id, list.list_id)
+        .await
+        .map_err(SubscribeError::InsertSubscriberError)?;
     let subscription_token = generate_subscription_token();
-    store_token(&txn, subscription_id, &subscription_token).await?;
+    store_token(
+        &txn,
+        subscription_id,
+        list.list_id,
+        &subscription_token,
+        state.subscription_token_ttl,
+    )
+    .await?;
 
     txn.commit().await.map_err(SubscribeError::PoolError)?;
 
-    send_confirmation_email(state.email_client.as_ref(), new_subscriber, state.base_url.as_ref(), &subscription_token).await?;
+    let unsubscribe_link = unsubscribe_link(
+        state.base_url.as_ref(),
+        &state.hmac_secret.unsubscribe,
+        subscription_id,
+    );
+    send_confirmation_email(
+        state.email_client.as_ref(),
+        state.templates.as_ref(),
+        new_subscriber,
+        &list.name,
+        state.base_url.as_ref(),
+        &subscription_token,
+        &unsubscribe_link,
+    )
+    .await?;
 
     Ok(())
 }
 
+#[tracing::instrument(name = "查找邮件列表", skip(db))]
+pub async fn find_list(
+    db: &impl ConnectionTrait,
+    slug: &str,
+) -> Result<Option<lists::Model>, DbErr> {
+    lists::Entity::find()
+        .filter(lists::Column::Slug.eq(slug))
+        .one(db)
+        .await
+}
+
+/// 把订阅者加入列表，返回是否需要发送确认邮件
+///
+/// 列表中已确认的订阅保持不变，只有订阅者的邮箱本身还需要确认时才会再次发送确认邮件
+#[tracing::instrument(name = "加入邮件列表", skip(db))]
+async fn join_list(
+    db: &DatabaseTransaction,
+    list_id: uuid::Uuid,
+    subscriber_id: uuid::Uuid,
+    address_confirmed: bool,
+) -> Result<bool, DbErr> {
+    let membership = list_memberships::Entity::find_by_id((list_id, subscriber_id))
+        .lock_exclusive()
+        .one(db)
+        .await?;
+    match membership {
+        None => {
+            list_memberships::ActiveModel {
+                list_id: Set(list_id),
+                subscriber_id: Set(subscriber_id),
+                status: Set(SubscriptionStatus::PendingConfirmation),
+                created_at: Set(chrono::Utc::now()),
+            }
+            .insert(db)
+            .await?;
+            Ok(true)
+        }
+        Some(membership) if membership.status == SubscriptionStatus::Confirmed => {
+            Ok(!address_confirmed)
+        }
+        Some(membership)
+            if membership
+                .status
+                .can_transition_to(SubscriptionStatus::PendingConfirmation) =>
+        {
+            let mut membership: list_memberships::ActiveModel = membership.into();
+            membership.status = Set(SubscriptionStatus::PendingConfirmation);
+            membership.update(db).await?;
+            Ok(true)
+        }
+        Some(_) => Ok(false),
+    }
+}
+
 #[tracing::instrument(name = "存储订阅令牌", skip(token, db))]
-pub async fn store_token(db: &DatabaseTransaction, subscription_id: uuid::Uuid, token: &str) -> Result<(), StoreTokenError> {
+pub async fn store_token(
+    db: &DatabaseTransaction,
+    subscription_id: uuid::Uuid,
+    list_id: uuid::Uuid,
+    token: &str,
+    ttl: Duration,
+) -> Result<(), StoreTokenError> {
+    let created_at = chrono::Utc::now();
+    let expires_at = chrono::Duration::from_std(ttl)
+        .ok()
+        .and_then(|ttl| created_at.checked_add_signed(ttl))
+        .unwrap_or(chrono::DateTime::<chrono::Utc>::MAX_UTC);
     let new_token = subscription_tokens::ActiveModel {
         subscription_token: Set(token.into()),
         subscriber_id: Set(subscription_id),
+        list_id: Set(list_id),
+        created_at: Set(created_at),
+        expires_at: Set(expires_at),
     };
 
-    new_token.insert(db).await.map(|_| ()).map_err(|e| {
-        StoreTokenError(e)
-    })
+    new_token
+        .insert(db)
+        .await
+        .map(|_| ())
+        .map_err(StoreTokenError)
 }
 
-pub async fn send_confirmation_email(email_client: &EmailClient, new_subscriber: NewSubscriber, base_url: &ApplicationBaseUrl, token: &str) -> Result<(), lettre::error::Error> {
+#[tracing::instrument(name = "发送确认邮件", skip_all)]
+pub async fn send_confirmation_email(
+    email_client: &EmailClient,
+    templates: &EmailTemplates,
+    new_subscriber: NewSubscriber,
+    list_name: &str,
+    base_url: &ApplicationBaseUrl,
+    token: &str,
+    unsubscribe_link: &str,
+) -> Result<(), EmailError> {
     let confirmation_link = format!(
         "{}/subscriptions/confirm?subscription_token={}",
-        base_url.0,
-        token
+        base_url.0, token
     );
 
-    email_client.send_email(
-        new_subscriber.email,
-        "Welcome!",
-        &format!(r#"<h1>欢迎订阅我们的新闻邮件</h1><p>请点击以下链接确认您的订阅：</p><a href="{}">确认订阅</a>"#, confirmation_link),
-        &format!("欢迎订阅我们的新闻邮件, {}", confirmation_link),
-    ).await
+    let mut context = tera::Context::new();
+    context.insert("name", new_subscriber.name.as_ref());
+    context.insert("list_name", list_name);
+    context.insert("confirmation_link", &confirmation_link);
+    context.insert("unsubscribe_link", unsubscribe_link);
+    let email = templates
+        .render(EmailTemplate::Confirmation, new_subscriber.locale, &context)
+        .map_err(EmailError::TemplateError)?;
+
+    // 抑制列表中的地址与正常订阅返回相同的结果，调用方无法借此判断地址是否被抑制
+    email_client
+        .send_transactional_email(
+            new_subscriber.email,
+            &email.subject,
+            &email.html_content,
+            &email.text_content,
+        )
+        .await
 }
 
-fn generate_subscription_token() -> String {
+pub fn generate_subscription_token() -> String {
     let mut rng = rand::rng();
     std::iter::repeat_with(|| rng.sample(Alphanumeric))
         .map(char::from)
@@ -92,21 +345,81 @@ fn generate_subscription_token() -> String {
         .collect()
 }
 
-
+/// 保存新的订阅者，规范形式相同的邮箱已存在时不做任何修改并返回 `None`
+///
+/// 使用 `ON CONFLICT DO NOTHING`，并发提交同一个邮箱时后到的请求会等待先到的事务提交
 #[tracing::instrument(name = "保存订阅者", skip(db, new_subscriber))]
 pub async fn insert_subscriber(
     db: &DatabaseTransaction,
     new_subscriber: &NewSubscriber,
-) -> Result<subscriptions::Model, DbErr> {
-    let subscriptions: subscriptions::ActiveModel = subscriptions::ActiveModel {
-        id: Set(uuid::Uuid::new_v4()),
-        email: Set(new_subscriber.email.as_ref().to_string()),
-        name: Set(new_subscriber.name.as_ref().to_string()),
+) -> Result<Option<uuid::Uuid>, DbErr> {
+    let subscription_id = uuid::Uuid::new_v4();
+    let subscriptions = new_subscription_row(
+        subscription_id,
+        new_subscriber.email.as_ref(),
+        new_subscriber.name.as_ref(),
+        new_subscriber.locale,
+        SubscriptionStatus::PendingConfirmation,
+    );
+
+    let n_inserted_rows = subscriptions::Entity::insert(subscriptions)
+        .on_conflict(
+            OnConflict::column(subscriptions::Column::CanonicalEmail)
+                .do_nothing()
+                .to_owned(),
+        )
+        .exec_without_returning(db)
+        .await?;
+
+    Ok((n_inserted_rows > 0).then_some(subscription_id))
+}
+
+/// 新订阅者对应的一行，规范化邮箱和别名键都由 `email` 计算，写入订阅者的地方都通过它构造
+pub fn new_subscription_row(
+    id: uuid::Uuid,
+    email: &str,
+    name: &str,
+    locale: Locale,
+    status: SubscriptionStatus,
+) -> subscriptions::ActiveModel {
+    subscriptions::ActiveModel {
+        id: Set(id),
+        email: Set(email.to_string()),
+        canonical_email: Set(normalize_email(email)),
+        alias_key: Set(email_alias_key(email)),
+        name: Set(name.to_string()),
         subscribed_at: Set(chrono::Utc::now()),
-        status: Set("pending_confirmation".into()),
-    };
+        status: Set(status),
+        locale: Set(locale.as_str().to_string()),
+        ..Default::default()
+    }
+}
+
+#[tracing::instrument(name = "获取已存在的订阅者", skip_all)]
+async fn get_subscriber_for_update(
+    db: &DatabaseTransaction,
+    email: &SubscriberEmail,
+) -> Result<subscriptions::Model, DbErr> {
+    subscriptions::Entity::find()
+        .filter(subscriptions::Column::CanonicalEmail.eq(normalize_email(email.as_ref())))
+        .lock_exclusive()
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("订阅者未找到".into()))
+}
 
-    subscriptions.insert(db).await
+/// 把未确认、已退订或退信的订阅者重置为待确认，并使用最新提交的姓名和语言
+#[tracing::instrument(name = "重新开始确认流程", skip_all, fields(subscriber_id = %existing.id))]
+async fn restart_confirmation(
+    db: &DatabaseTransaction,
+    existing: subscriptions::Model,
+    new_subscriber: &NewSubscriber,
+) -> Result<uuid::Uuid, DbErr> {
+    let mut subscriber: subscriptions::ActiveModel = existing.into();
+    subscriber.name = Set(new_subscriber.name.as_ref().to_string());
+    subscriber.locale = Set(new_subscriber.locale.as_str().to_string());
+    subscriber.status = Set(SubscriptionStatus::PendingConfirmation);
+    Ok(subscriber.update(db).await?.id)
 }
 
 pub struct StoreTokenError(DbErr);
@@ -136,37 +449,49 @@ impl std::fmt::Debug for StoreTokenError {
     }
 }
 
-fn error_chain_fmt(e: &impl Error, f: &mut Formatter<'_>) -> std::fmt::Result {
-    writeln!(f, "{}\n", e)?;
-    let mut current = e.source();
-    while let Some(cause) = current {
-        writeln!(f, "Caused by: \n\t{}", cause)?;
-        current = cause.source();
-    }
-
-    Ok(())
-}
-
-
 pub enum SubscribeError {
-    ValidationError(String),
+    ValidationError(Vec<ValidationError>),
+    UnknownList(String),
+    RateLimited(RateLimited),
     StoreTokenError(StoreTokenError),
-    SendEmailError(lettre::error::Error),
+    SendEmailError(EmailError),
     PoolError(DbErr),
     InsertSubscriberError(DbErr),
     TransactionCommitError(DbErr),
 }
 
+impl Localize for SubscribeError {
+    fn localize(&self, locale: Locale) -> String {
+        let key = match self {
+            SubscribeError::ValidationError(errors) => {
+                let reason = errors
+                    .iter()
+                    .map(|e| e.localize(locale))
+                    .collect::<Vec<_>>()
+                    .join("; ");
+                let mut args = FluentArgs::new();
+                args.set("reason", reason);
+                return locale.translate("subscribe-error-validation", Some(&args));
+            }
+            SubscribeError::UnknownList(list) => {
+                let mut args = FluentArgs::new();
+                args.set("list", list.as_str());
+                return locale.translate("subscribe-error-unknown-list", Some(&args));
+            }
+            SubscribeError::RateLimited(e) => return e.localize(locale),
+            SubscribeError::StoreTokenError(_) => "subscribe-error-store-token",
+            SubscribeError::SendEmailError(_) => "subscribe-error-send-email",
+            SubscribeError::PoolError(_) => "subscribe-error-pool",
+            SubscribeError::InsertSubscriberError(_) => "subscribe-error-insert-subscriber",
+            SubscribeError::TransactionCommitError(_) => "subscribe-error-transaction-commit",
+        };
+        locale.translate(key, None)
+    }
+}
+
 impl Display for SubscribeError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        match self {
-            SubscribeError::ValidationError(e) => write!(f, "验证错误: {}", e),
-            SubscribeError::StoreTokenError(_) => write!(f, "存储令牌错误"),
-            SubscribeError::SendEmailError(_) => write!(f, "发送邮件错误"),
-            SubscribeError::PoolError(_) => write!(f, "数据库连接池错误"),
-            SubscribeError::InsertSubscriberError(_) => write!(f, "插入订阅者错误"),
-            SubscribeError::TransactionCommitError(_) => write!(f, "事务提交错误"),
-        }
+        f.write_str(&self.localize(Locale::default()))
     }
 }
 
@@ -179,7 +504,11 @@ impl Debug for SubscribeError {
 impl Error for SubscribeError {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
         match self {
-            SubscribeError::ValidationError(_) => None,
+            SubscribeError::ValidationError(errors) => {
+                errors.first().map(|e| e as &(dyn Error + 'static))
+            }
+            SubscribeError::UnknownList(_) => None,
+            SubscribeError::RateLimited(e) => Some(e),
             SubscribeError::StoreTokenError(e) => Some(e),
             SubscribeError::SendEmailError(e) => Some(e),
             SubscribeError::PoolError(e) => Some(e),
@@ -193,22 +522,23 @@ impl IntoResponse for SubscribeError {
     fn into_response(self) -> Response {
         tracing::error!("{:?}", self);
         match self {
-            SubscribeError::ValidationError(_) => {
+            SubscribeError::ValidationError(_) | SubscribeError::UnknownList(_) => {
                 StatusCode::BAD_REQUEST.into_response()
             }
-            SubscribeError::PoolError(_) |
-            SubscribeError::InsertSubscriberError(_) |
-            SubscribeError::TransactionCommitError(_) |
-            SubscribeError::StoreTokenError(_) |
-            SubscribeError::SendEmailError(_) => {
+            SubscribeError::RateLimited(e) => e.into_response(),
+            SubscribeError::PoolError(_)
+            | SubscribeError::InsertSubscriberError(_)
+            | SubscribeError::TransactionCommitError(_)
+            | SubscribeError::StoreTokenError(_)
+            | SubscribeError::SendEmailError(_) => {
                 StatusCode::INTERNAL_SERVER_ERROR.into_response()
             }
         }
     }
 }
 
-impl From<lettre::error::Error> for SubscribeError {
-    fn from(value: lettre::error::Error) -> Self {
+impl From<EmailError> for SubscribeError {
+    fn from(value: EmailError) -> Self {
         Self::SendEmailError(value)
     }
 }
@@ -219,8 +549,14 @@ impl From<StoreTokenError> for SubscribeError {
     }
 }
 
-impl From<String> for SubscribeError {
-    fn from(value: String) -> Self {
+impl From<RateLimited> for SubscribeError {
+    fn from(value: RateLimited) -> Self {
+        Self::RateLimited(value)
+    }
+}
+
+impl From<Vec<ValidationError>> for SubscribeError {
+    fn from(value: Vec<ValidationError>) -> Self {
         Self::ValidationError(value)
     }
 }
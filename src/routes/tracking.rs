@@ -0,0 +1,233 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use sea_orm::{ConnectionTrait, DatabaseConnection, DbBackend, DbErr, Statement};
+use secrecy::SecretString;
+
+use crate::{
+    domain::{ClickToken, EmailEventKind},
+    startup::{AppState, ApplicationBaseUrl},
+};
+
+/// `<a>` 标签的 `href` 属性，第一组为属性值之前的部分，属性值在第二组（双引号）或第三组（单引号）
+static ANCHOR_HREF: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(?i)(<a\s[^>]*?\bhref\s*=\s*)(?:"([^"]*)"|'([^']*)')"#).unwrap());
+
+/// 1x1 透明 GIF
+const TRANSPARENT_GIF: &[u8] = &[
+    0x47, 0x49, 0x46, 0x38, 0x39, 0x61, 0x01, 0x00, 0x01, 0x00, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0xff, 0xff, 0xff, 0x21, 0xf9, 0x04, 0x01, 0x00, 0x00, 0x00, 0x00, 0x2c, 0x00, 0x00, 0x00, 0x00,
+    0x01, 0x00, 0x01, 0x00, 0x00, 0x02, 0x02, 0x44, 0x01, 0x00, 0x3b,
+];
+
+/// 生成注入到新闻邮件中的打开跟踪像素地址
+pub fn open_tracking_url(base_url: &ApplicationBaseUrl, delivery_id: uuid::Uuid) -> String {
+    format!("{}/t/o/{}.gif", base_url.0, delivery_id)
+}
+
+/// 把 HTML 中指向 http(s) 地址的链接改写为本次投递的点击跟踪链接，其他链接（如 `mailto:`、页内锚点）保持不变
+pub fn track_links(
+    html: &str,
+    base_url: &ApplicationBaseUrl,
+    hmac_secret: &SecretString,
+    delivery_id: uuid::Uuid,
+) -> String {
+    ANCHOR_HREF
+        .replace_all(html, |caps: &Captures| {
+            let href = caps.get(2).or_else(|| caps.get(3)).unwrap().as_str();
+            match parse_target(&unescape_attribute(href)) {
+                Some(target) => format!(
+                    r#"{}"{}/t/c/{}""#,
+                    &caps[1],
+                    base_url.0,
+                    ClickToken::generate(delivery_id, target.as_str(), hmac_secret).as_ref()
+                ),
+                None => caps[0].to_string(),
+            }
+        })
+        .into_owned()
+}
+
+/// 只接受 http 和 https 地址，避免跳转到 `javascript:` 等危险的协议
+fn parse_target(url: &str) -> Option<url::Url> {
+    url::Url::parse(url.trim())
+        .ok()
+        .filter(|url| matches!(url.scheme(), "http" | "https"))
+}
+
+/// 还原属性值中常见的字符引用，例如查询字符串中的 `&amp;`
+fn unescape_attribute(value: &str) -> String {
+    value
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&#x27;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+/// 返回跟踪像素并记录一次打开
+///
+/// 无论投递是否存在、是否开启了跟踪，都返回同样的图片，不向请求方暴露任何信息；
+/// 记录失败也只写日志，不影响邮件的显示
+#[tracing::instrument(name = "记录邮件打开", skip(state))]
+pub async fn track_open(State(state): State<Arc<AppState>>, Path(pixel): Path<String>) -> Response {
+    let Some(delivery_id) = pixel
+        .strip_suffix(".gif")
+        .and_then(|id| uuid::Uuid::parse_str(id).ok())
+    else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    if state.tracking.open_tracking_enabled
+        && let Err(e) = record_open(state.db.as_ref(), delivery_id).await
+    {
+        tracing::error!(error.cause_chain = ?e, "记录邮件打开失败");
+    }
+
+    (
+        [
+            (header::CONTENT_TYPE, "image/gif"),
+            (header::CACHE_CONTROL, "no-store, no-cache, must-revalidate"),
+        ],
+        TRANSPARENT_GIF,
+    )
+        .into_response()
+}
+
+/// 只记录开启了打开跟踪的新闻邮件，同一次投递的重复打开由唯一索引去重
+async fn record_open(db: &DatabaseConnection, delivery_id: uuid::Uuid) -> Result<(), DbErr> {
+    db.execute(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        r#"
+            INSERT INTO email_events (delivery_id, kind)
+            SELECT d.delivery_id, $2
+            FROM email_deliveries d
+            JOIN newsletter_issues i ON i.newsletter_issue_id = d.newsletter_issue_id
+            WHERE d.delivery_id = $1 AND i.track_opens
+            ON CONFLICT (delivery_id) WHERE kind = 'open' DO NOTHING
+        "#,
+        [delivery_id.into(), EmailEventKind::Open.as_str().into()],
+    ))
+    .await?;
+    Ok(())
+}
+
+/// 记录一次点击并跳转到令牌中的原始链接
+///
+/// 只有签名有效的令牌才会跳转，签名无效或目标不是 http(s) 地址时返回 404，
+/// 因此这个接口不能被用作开放重定向；记录失败只写日志，不影响跳转
+#[tracing::instrument(name = "记录链接点击", skip_all)]
+pub async fn track_click(
+    State(state): State<Arc<AppState>>,
+    Path(token): Path<String>,
+) -> Response {
+    let Some((delivery_id, target)) = ClickToken::verify(&token, &state.hmac_secret.click)
+        .and_then(|(delivery_id, url)| Some((delivery_id, parse_target(&url)?)))
+    else {
+        tracing::warn!("点击跟踪令牌无效");
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    if state.tracking.click_tracking_enabled
+        && let Err(e) = record_click(state.db.as_ref(), delivery_id, target.as_str()).await
+    {
+        tracing::error!(error.cause_chain = ?e, "记录链接点击失败");
+    }
+
+    (StatusCode::FOUND, [(header::LOCATION, target.as_str())]).into_response()
+}
+
+/// 只记录开启了点击跟踪的新闻邮件，每次点击都记录一条事件
+async fn record_click(
+    db: &DatabaseConnection,
+    delivery_id: uuid::Uuid,
+    url: &str,
+) -> Result<(), DbErr> {
+    db.execute(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        r#"
+            INSERT INTO email_events (delivery_id, kind, url)
+            SELECT d.delivery_id, $2, $3
+            FROM email_deliveries d
+            JOIN newsletter_issues i ON i.newsletter_issue_id = d.newsletter_issue_id
+            WHERE d.delivery_id = $1 AND i.track_clicks
+        "#,
+        [
+            delivery_id.into(),
+            EmailEventKind::Click.as_str().into(),
+            url.into(),
+        ],
+    ))
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::SecretString;
+
+    use super::track_links;
+    use crate::{domain::ClickToken, startup::ApplicationBaseUrl};
+
+    fn rewrite(html: &str) -> String {
+        track_links(
+            html,
+            &ApplicationBaseUrl("http://127.0.0.1".into()),
+            &SecretString::from("secret"),
+            uuid::Uuid::nil(),
+        )
+    }
+
+    /// 取出改写后链接中的令牌并解出原始地址
+    fn target_of(rewritten: &str) -> String {
+        let start = rewritten.find("/t/c/").unwrap() + "/t/c/".len();
+        let token = &rewritten[start..];
+        let token = &token[..token.find(['"', '\'']).unwrap()];
+        ClickToken::verify(token, &SecretString::from("secret"))
+            .unwrap()
+            .1
+    }
+
+    #[test]
+    fn http_links_are_rewritten() {
+        for html in [
+            r#"<p><a href="https://example.com/a?x=1&amp;y=2">link</a></p>"#,
+            r#"<A class="btn" HREF = 'https://example.com/a?x=1&y=2'>link</A>"#,
+        ] {
+            let rewritten = rewrite(html);
+
+            assert!(rewritten.contains("http://127.0.0.1/t/c/"), "{}", rewritten);
+            assert!(rewritten.ends_with(">link</a></p>") || rewritten.ends_with(">link</A>"));
+            assert_eq!(target_of(&rewritten), "https://example.com/a?x=1&y=2");
+        }
+    }
+
+    #[test]
+    fn other_links_are_left_alone() {
+        for html in [
+            r#"<a href="mailto:editor@example.com">mail</a>"#,
+            r##"<a href="#section">jump</a>"##,
+            r#"<a href="javascript:alert(1)">x</a>"#,
+            r#"<link href="https://example.com/style.css">"#,
+            r#"<p>https://example.com</p>"#,
+        ] {
+            assert_eq!(rewrite(html), html);
+        }
+    }
+
+    #[test]
+    fn every_link_gets_its_own_token() {
+        let rewritten = rewrite(
+            r#"<a href="https://example.com/1">1</a><a href="https://example.com/2">2</a>"#,
+        );
+
+        assert_eq!(rewritten.matches("/t/c/").count(), 2);
+    }
+}
@@ -0,0 +1,244 @@
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+use std::sync::Arc;
+
+use axum::{
+    Form,
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{Html, IntoResponse, Response},
+};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::Set, ColumnTrait, DatabaseConnection, DbErr, EntityTrait,
+    QueryFilter, QuerySelect, TransactionTrait, sea_query::Expr,
+};
+use secrecy::SecretString;
+
+use super::error_chain_fmt;
+use crate::{
+    domain::{SubscriberEmail, SubscriptionStatus, UnsubscribeToken},
+    email_client::{EmailClient, EmailError},
+    entities::{list_memberships, subscriptions},
+    i18n::{Locale, Localize, Localized},
+    startup::{AppState, ApplicationBaseUrl},
+    templates::{EmailTemplate, EmailTemplates, PageTemplate},
+};
+
+#[derive(serde::Deserialize)]
+pub struct Parameters {
+    token: String,
+}
+
+/// 生成附在邮件页脚中的退订链接
+pub fn unsubscribe_link(
+    base_url: &ApplicationBaseUrl,
+    hmac_secret: &SecretString,
+    subscriber_id: uuid::Uuid,
+) -> String {
+    format!(
+        "{}/subscriptions/unsubscribe?token={}",
+        base_url.0,
+        UnsubscribeToken::generate(subscriber_id, hmac_secret).as_ref()
+    )
+}
+
+#[tracing::instrument(name = "退订确认页面", skip_all)]
+pub async fn unsubscribe_form(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<Parameters>,
+) -> Result<Html<String>, Localized<UnsubscribeError>> {
+    let locale = Locale::negotiate(None, &headers);
+    let subscriber = get_subscriber_from_token(&state, &params.token)
+        .await
+        .map_err(|e| Localized::new(locale, e))?;
+    let locale = Locale::parse(&subscriber.locale).unwrap_or_default();
+    // 使用重新生成的令牌，而不是把请求中的原始输入写回页面
+    let token = UnsubscribeToken::generate(subscriber.id, &state.hmac_secret.unsubscribe);
+    let mut context = tera::Context::new();
+    context.insert("token", token.as_ref());
+
+    state
+        .templates
+        .render_page(PageTemplate::UnsubscribeForm, locale, &context)
+        .map(Html)
+        .map_err(|e| Localized::new(locale, UnsubscribeError::RenderPageError(e)))
+}
+
+#[tracing::instrument(name = "退订", skip_all)]
+pub async fn unsubscribe(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Form(params): Form<Parameters>,
+) -> Result<Html<String>, Localized<UnsubscribeError>> {
+    let locale = Locale::negotiate(None, &headers);
+    let subscriber = get_subscriber_from_token(&state, &params.token)
+        .await
+        .map_err(|e| Localized::new(locale, e))?;
+    let locale = Locale::parse(&subscriber.locale).unwrap_or_default();
+
+    // 重复提交时不再修改状态，也不再发送退订邮件；退信或投诉的地址同样不再投递
+    let unsubscribed = mark_as_unsubscribed(state.db.as_ref(), subscriber.id)
+        .await
+        .map_err(|e| Localized::new(locale, UnsubscribeError::DbError(e)))?;
+    if let Some(subscriber) = unsubscribed {
+        // 退订已经生效，确认邮件发送失败不影响本次请求的结果
+        match SubscriberEmail::parse(subscriber.email) {
+            Ok(email) => {
+                if let Err(e) = send_unsubscribe_confirmation_email(
+                    state.email_client.as_ref(),
+                    state.templates.as_ref(),
+                    email,
+                    &subscriber.name,
+                    locale,
+                )
+                .await
+                {
+                    tracing::error!(
+                        error.cause_chain = ?e,
+                        error.message = %e,
+                        "发送退订确认邮件失败",
+                    );
+                }
+            }
+            Err(e) => {
+                tracing::error!(
+                    error.cause_chain = ?e,
+                    "跳过退订确认邮件, 订阅者存储的邮箱无效",
+                );
+            }
+        }
+    }
+
+    state
+        .templates
+        .render_page(PageTemplate::UnsubscribeDone, locale, &tera::Context::new())
+        .map(Html)
+        .map_err(|e| Localized::new(locale, UnsubscribeError::RenderPageError(e)))
+}
+
+async fn get_subscriber_from_token(
+    state: &AppState,
+    token: &str,
+) -> Result<subscriptions::Model, UnsubscribeError> {
+    let subscriber_id = UnsubscribeToken::verify(token, &state.hmac_secret.unsubscribe)
+        .ok_or(UnsubscribeError::InvalidToken)?;
+    subscriptions::Entity::find_by_id(subscriber_id)
+        .one(state.db.as_ref())
+        .await
+        .map_err(UnsubscribeError::DbError)?
+        .ok_or(UnsubscribeError::InvalidToken)
+}
+
+/// 退订对所有列表生效，重新订阅任一列表时都需要再次确认
+///
+/// 在事务中锁住订阅者后再检查状态，避免覆盖同时到达的退信或投诉；
+/// 状态不允许变为已退订时不做修改并返回 `None`
+#[tracing::instrument(name = "将订阅者标记为已退订", skip(db))]
+async fn mark_as_unsubscribed(
+    db: &DatabaseConnection,
+    subscriber_id: uuid::Uuid,
+) -> Result<Option<subscriptions::Model>, DbErr> {
+    let txn = db.begin().await?;
+    let subscriber = subscriptions::Entity::find_by_id(subscriber_id)
+        .lock_exclusive()
+        .one(&txn)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound(format!("订阅者 {} 不存在", subscriber_id)))?;
+    let Ok(status) = subscriber
+        .status
+        .transition_to(SubscriptionStatus::Unsubscribed)
+    else {
+        return Ok(None);
+    };
+
+    list_memberships::Entity::update_many()
+        .col_expr(
+            list_memberships::Column::Status,
+            Expr::value(SubscriptionStatus::Unsubscribed),
+        )
+        .filter(list_memberships::Column::SubscriberId.eq(subscriber.id))
+        .filter(list_memberships::Column::Status.is_in([
+            SubscriptionStatus::PendingConfirmation,
+            SubscriptionStatus::Confirmed,
+        ]))
+        .exec(&txn)
+        .await?;
+
+    let mut subscriber: subscriptions::ActiveModel = subscriber.into();
+    subscriber.status = Set(status);
+    let subscriber = subscriber.update(&txn).await?;
+    txn.commit().await?;
+    Ok(Some(subscriber))
+}
+
+#[tracing::instrument(name = "发送退订确认邮件", skip_all)]
+pub async fn send_unsubscribe_confirmation_email(
+    email_client: &EmailClient,
+    templates: &EmailTemplates,
+    recipient: SubscriberEmail,
+    name: &str,
+    locale: Locale,
+) -> Result<(), EmailError> {
+    let mut context = tera::Context::new();
+    context.insert("name", name);
+    let email = templates
+        .render(EmailTemplate::UnsubscribeConfirmation, locale, &context)
+        .map_err(EmailError::TemplateError)?;
+
+    email_client
+        .send_transactional_email(recipient, &email.subject, &email.html_content, &email.text_content)
+        .await
+}
+
+pub enum UnsubscribeError {
+    InvalidToken,
+    RenderPageError(tera::Error),
+    DbError(DbErr),
+}
+
+impl Localize for UnsubscribeError {
+    fn localize(&self, locale: Locale) -> String {
+        match self {
+            UnsubscribeError::InvalidToken => locale.translate("unsubscribe-error-invalid-token", None),
+            UnsubscribeError::RenderPageError(_) => {
+                locale.translate("unsubscribe-error-render-page", None)
+            }
+            UnsubscribeError::DbError(_) => locale.translate("unsubscribe-error-database", None),
+        }
+    }
+}
+
+impl Display for UnsubscribeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.localize(Locale::default()))
+    }
+}
+
+impl Debug for UnsubscribeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl Error for UnsubscribeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            UnsubscribeError::InvalidToken => None,
+            UnsubscribeError::RenderPageError(e) => Some(e),
+            UnsubscribeError::DbError(e) => Some(e),
+        }
+    }
+}
+
+impl IntoResponse for UnsubscribeError {
+    fn into_response(self) -> Response {
+        tracing::error!("{:?}", self);
+        match self {
+            UnsubscribeError::InvalidToken => StatusCode::UNAUTHORIZED.into_response(),
+            UnsubscribeError::RenderPageError(_) | UnsubscribeError::DbError(_) => {
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        }
+    }
+}
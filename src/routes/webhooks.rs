@@ -0,0 +1,223 @@
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+use std::sync::Arc;
+
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use hmac::{Hmac, Mac};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::Set, ColumnTrait, DatabaseTransaction, DbErr, EntityTrait,
+    PaginatorTrait, QueryFilter, QuerySelect, TransactionTrait, sea_query::Expr,
+};
+use secrecy::{ExposeSecret, SecretString};
+use sha2::Sha256;
+
+use super::error_chain_fmt;
+use crate::{
+    domain::{SubscriptionStatus, SuppressionSource, WebhookEventKind, normalize_email},
+    entities::{email_webhook_events, list_memberships, subscriptions},
+    startup::AppState,
+    suppression_list::suppress,
+};
+
+/// Postmark 风格的退信和投诉事件，其余字段只随原始 JSON 一起保存
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ProviderEvent {
+    record_type: String,
+    /// 退信类型，只有退信事件才有
+    #[serde(rename = "Type")]
+    bounce_type: Option<String>,
+    email: String,
+}
+
+/// 接收邮件服务商推送的退信和投诉事件
+///
+/// 请求体的签名验证通过后原样保存事件；硬退信和投诉立即停止向该地址投递并加入抑制列表，
+/// 软退信累计达到 `email_events_webhook.soft_bounce_threshold` 次后才停止
+#[tracing::instrument(name = "接收邮件事件", skip_all, fields(kind = tracing::field::Empty))]
+pub async fn receive_email_events(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<StatusCode, WebhookError> {
+    let signature = headers
+        .get("X-Webhook-Signature")
+        .and_then(|value| value.to_str().ok())
+        .ok_or(WebhookError::MissingSignature)?;
+    if !verify_signature(&body, signature, &state.email_events_webhook.secret) {
+        return Err(WebhookError::InvalidSignature);
+    }
+
+    let payload: serde_json::Value =
+        serde_json::from_slice(&body).map_err(WebhookError::InvalidPayload)?;
+    let event: ProviderEvent =
+        serde_json::from_value(payload.clone()).map_err(WebhookError::InvalidPayload)?;
+    let kind = WebhookEventKind::classify(&event.record_type, event.bounce_type.as_deref());
+    tracing::Span::current().record("kind", tracing::field::display(kind));
+
+    let txn = state.db.begin().await.map_err(WebhookError::DbError)?;
+    let subscriber = subscriptions::Entity::find()
+        .filter(subscriptions::Column::CanonicalEmail.eq(normalize_email(&event.email)))
+        .lock_exclusive()
+        .one(&txn)
+        .await
+        .map_err(WebhookError::DbError)?;
+    let stored = email_webhook_events::ActiveModel {
+        subscriber_id: Set(subscriber.as_ref().map(|s| s.id)),
+        email: Set(event.email),
+        kind: Set(kind),
+        status_changed: Set(false),
+        payload: Set(payload),
+        // 接收时间取数据库的时钟，与订阅者的 status_changed_at 可以直接比较
+        ..Default::default()
+    }
+    .insert(&txn)
+    .await
+    .map_err(WebhookError::DbError)?;
+
+    // 硬退信和投诉的地址不论是否属于订阅者都不再发送任何邮件
+    if matches!(
+        kind,
+        WebhookEventKind::HardBounce | WebhookEventKind::Complaint
+    ) {
+        suppress(&txn, &stored.email, kind.as_str(), SuppressionSource::Webhook)
+            .await
+            .map_err(WebhookError::DbError)?;
+    }
+
+    if let Some(subscriber) = subscriber {
+        let next = next_status(
+            &txn,
+            kind,
+            &subscriber,
+            state.email_events_webhook.soft_bounce_threshold,
+        )
+        .await
+        .map_err(WebhookError::DbError)?;
+        if let Some(next) = next.filter(|next| subscriber.status.can_transition_to(*next)) {
+            stop_delivery(&txn, subscriber, next)
+                .await
+                .map_err(WebhookError::DbError)?;
+            let mut stored: email_webhook_events::ActiveModel = stored.into();
+            stored.status_changed = Set(true);
+            stored.update(&txn).await.map_err(WebhookError::DbError)?;
+        }
+    }
+    txn.commit().await.map_err(WebhookError::DbError)?;
+
+    Ok(StatusCode::OK)
+}
+
+/// 请求头中的签名是请求体 HMAC-SHA256 的十六进制编码，比较时间与内容无关
+fn verify_signature(body: &[u8], signature: &str, secret: &SecretString) -> bool {
+    let Ok(signature) = hex::decode(signature.trim()) else {
+        return false;
+    };
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.expose_secret().as_bytes())
+        .expect("HMAC 可以接受任意长度的密钥");
+    mac.update(body);
+    mac.verify_slice(&signature).is_ok()
+}
+
+/// 根据事件类型决定订阅者的新状态
+///
+/// 软退信只统计订阅者最近一次状态变化之后收到的事件，退订、重新订阅或确认都会重新计数
+async fn next_status(
+    txn: &DatabaseTransaction,
+    kind: WebhookEventKind,
+    subscriber: &subscriptions::Model,
+    soft_bounce_threshold: u32,
+) -> Result<Option<SubscriptionStatus>, DbErr> {
+    match kind {
+        WebhookEventKind::HardBounce => Ok(Some(SubscriptionStatus::Bounced)),
+        WebhookEventKind::Complaint => Ok(Some(SubscriptionStatus::Complained)),
+        WebhookEventKind::Other => Ok(None),
+        WebhookEventKind::SoftBounce => {
+            let soft_bounces = email_webhook_events::Entity::find()
+                .filter(email_webhook_events::Column::SubscriberId.eq(subscriber.id))
+                .filter(email_webhook_events::Column::Kind.eq(WebhookEventKind::SoftBounce))
+                .filter(email_webhook_events::Column::ReceivedAt.gte(subscriber.status_changed_at))
+                .count(txn)
+                .await?;
+            Ok((soft_bounces >= u64::from(soft_bounce_threshold))
+                .then_some(SubscriptionStatus::Bounced))
+        }
+    }
+}
+
+/// 退信和投诉对所有列表生效
+#[tracing::instrument(skip_all, fields(subscriber_id = %subscriber.id, status = %status))]
+async fn stop_delivery(
+    txn: &DatabaseTransaction,
+    subscriber: subscriptions::Model,
+    status: SubscriptionStatus,
+) -> Result<(), DbErr> {
+    list_memberships::Entity::update_many()
+        .col_expr(list_memberships::Column::Status, Expr::value(status))
+        .filter(list_memberships::Column::SubscriberId.eq(subscriber.id))
+        .filter(list_memberships::Column::Status.is_in([
+            SubscriptionStatus::PendingConfirmation,
+            SubscriptionStatus::Confirmed,
+        ]))
+        .exec(txn)
+        .await?;
+
+    let mut subscriber: subscriptions::ActiveModel = subscriber.into();
+    subscriber.status = Set(status);
+    subscriber.update(txn).await?;
+    Ok(())
+}
+
+pub enum WebhookError {
+    MissingSignature,
+    InvalidSignature,
+    InvalidPayload(serde_json::Error),
+    DbError(DbErr),
+}
+
+impl Display for WebhookError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WebhookError::MissingSignature => write!(f, "缺少 X-Webhook-Signature 请求头"),
+            WebhookError::InvalidSignature => write!(f, "请求签名无效"),
+            WebhookError::InvalidPayload(_) => write!(f, "无法解析邮件事件"),
+            WebhookError::DbError(_) => write!(f, "保存邮件事件时发生数据库错误"),
+        }
+    }
+}
+
+impl Debug for WebhookError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl Error for WebhookError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            WebhookError::MissingSignature | WebhookError::InvalidSignature => None,
+            WebhookError::InvalidPayload(e) => Some(e),
+            WebhookError::DbError(e) => Some(e),
+        }
+    }
+}
+
+impl IntoResponse for WebhookError {
+    fn into_response(self) -> Response {
+        tracing::error!("{:?}", self);
+        match self {
+            WebhookError::MissingSignature | WebhookError::InvalidSignature => {
+                (StatusCode::UNAUTHORIZED, self.to_string()).into_response()
+            }
+            WebhookError::InvalidPayload(_) => {
+                (StatusCode::BAD_REQUEST, self.to_string()).into_response()
+            }
+            WebhookError::DbError(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+        }
+    }
+}
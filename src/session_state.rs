@@ -0,0 +1,37 @@
+use axum::{extract::FromRequestParts, http::request::Parts};
+use tower_sessions::{Session, session};
+
+/// 对 `tower_sessions::Session` 的强类型封装，避免在各处手写会话键名
+pub struct TypedSession(Session);
+
+impl TypedSession {
+    const USER_ID_KEY: &'static str = "user_id";
+
+    /// 登录成功后更换会话 ID，防止会话固定攻击
+    pub async fn renew(&self) -> Result<(), session::Error> {
+        self.0.cycle_id().await
+    }
+
+    pub async fn insert_user_id(&self, user_id: uuid::Uuid) -> Result<(), session::Error> {
+        self.0.insert(Self::USER_ID_KEY, user_id).await
+    }
+
+    pub async fn get_user_id(&self) -> Result<Option<uuid::Uuid>, session::Error> {
+        self.0.get(Self::USER_ID_KEY).await
+    }
+
+    pub async fn log_out(&self) -> Result<(), session::Error> {
+        self.0.flush().await
+    }
+}
+
+impl<S> FromRequestParts<S> for TypedSession
+where
+    S: Send + Sync,
+{
+    type Rejection = <Session as FromRequestParts<S>>::Rejection;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        Session::from_request_parts(parts, state).await.map(TypedSession)
+    }
+}
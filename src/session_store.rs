@@ -0,0 +1,112 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use sea_orm::{
+    ActiveValue::Set, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter,
+    sea_query::OnConflict,
+};
+use time::OffsetDateTime;
+use tower_sessions::{
+    ExpiredDeletion, SessionStore,
+    session::{Id, Record},
+    session_store,
+};
+
+use crate::entities::sessions;
+
+/// 基于 Postgres `sessions` 表的会话存储，不需要额外部署 Redis
+#[derive(Clone, Debug)]
+pub struct PostgresSessionStore {
+    db: DatabaseConnection,
+}
+
+impl PostgresSessionStore {
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self { db }
+    }
+
+    /// 每隔 `period` 清理一次过期会话，由 `Application::run_cleanup_until_stopped` 在后台运行
+    pub async fn continuously_delete_expired(self, period: Duration) {
+        let mut interval = tokio::time::interval(period);
+        // 第一次 tick 会立即完成，跳过
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            if let Err(e) = self.delete_expired().await {
+                tracing::error!(error.cause_chain = ?e, "清理过期会话失败");
+            }
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl SessionStore for PostgresSessionStore {
+    async fn save(&self, record: &Record) -> session_store::Result<()> {
+        let data = serde_json::to_value(&record.data)
+            .map_err(|e| session_store::Error::Encode(e.to_string()))?;
+        let expiry_date = DateTime::<Utc>::from_timestamp(record.expiry_date.unix_timestamp(), 0)
+            .ok_or_else(|| session_store::Error::Encode("会话过期时间超出范围".into()))?;
+
+        let session = sessions::ActiveModel {
+            id: Set(record.id.to_string()),
+            data: Set(data),
+            expiry_date: Set(expiry_date),
+        };
+
+        sessions::Entity::insert(session)
+            .on_conflict(
+                OnConflict::column(sessions::Column::Id)
+                    .update_columns([sessions::Column::Data, sessions::Column::ExpiryDate])
+                    .to_owned(),
+            )
+            .exec(&self.db)
+            .await
+            .map_err(|e| session_store::Error::Backend(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn load(&self, session_id: &Id) -> session_store::Result<Option<Record>> {
+        let session = sessions::Entity::find_by_id(session_id.to_string())
+            .filter(sessions::Column::ExpiryDate.gt(Utc::now()))
+            .one(&self.db)
+            .await
+            .map_err(|e| session_store::Error::Backend(e.to_string()))?;
+
+        session
+            .map(|s| {
+                let data = serde_json::from_value(s.data)
+                    .map_err(|e| session_store::Error::Decode(e.to_string()))?;
+                let expiry_date = OffsetDateTime::from_unix_timestamp(s.expiry_date.timestamp())
+                    .map_err(|e| session_store::Error::Decode(e.to_string()))?;
+                Ok(Record {
+                    id: *session_id,
+                    data,
+                    expiry_date,
+                })
+            })
+            .transpose()
+    }
+
+    async fn delete(&self, session_id: &Id) -> session_store::Result<()> {
+        sessions::Entity::delete_by_id(session_id.to_string())
+            .exec(&self.db)
+            .await
+            .map_err(|e| session_store::Error::Backend(e.to_string()))?;
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl ExpiredDeletion for PostgresSessionStore {
+    async fn delete_expired(&self) -> session_store::Result<()> {
+        sessions::Entity::delete_many()
+            .filter(sessions::Column::ExpiryDate.lt(Utc::now()))
+            .exec(&self.db)
+            .await
+            .map_err(|e| session_store::Error::Backend(e.to_string()))?;
+
+        Ok(())
+    }
+}
@@ -1,40 +1,82 @@
+use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
 
 use axum::{
     Router,
     extract::Request,
     http::HeaderName,
-    routing::{get, post},
+    middleware,
+    routing::{delete, get, post, put},
 };
+use axum_messages::MessagesManagerLayer;
+use hmac::{Hmac, Mac};
 use sea_orm::{Database, DatabaseConnection};
-use secrecy::SecretString;
+use secrecy::{ExposeSecret, SecretString};
+use sha2::Sha512;
 use tokio::net::TcpListener;
 use tower::ServiceBuilder;
 use tower_http::{
     request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
     trace::TraceLayer,
 };
+use tower_sessions::{SessionManagerLayer, cookie::Key};
 use uuid::Uuid;
 
 use crate::{
-    configuration::Settings,
+    authentication::reject_anonymous_users,
+    configuration::{EmailEventsWebhookSettings, Settings, TrackingSettings},
     email_client::EmailClient,
-    routes::{health_check::health_check, subscription_confirm::confirm, subscriptions::subscribe},
+    newsletter_scheduler::run_scheduler_until_stopped,
+    rate_limit::{RateLimiter, limit_by_client_ip},
+    routes::{
+        admin::{
+            add_suppression, admin_dashboard, cancel_newsletter, create_list, delete_subscriber,
+            export_subscribers, get_subscriber, import_subscribers, list_lists,
+            list_scheduled_newsletters, list_subscribers, list_suppressions, log_out,
+            newsletter_stats, publish_newsletter, publish_newsletter_form, remove_suppression,
+            reschedule_newsletter,
+        },
+        health_check::health_check,
+        login::{login, login_form},
+        newsletters,
+        subscription_confirm::{confirm, resend_confirmation},
+        subscriptions::subscribe,
+        tracking::{track_click, track_open},
+        unsubscribe::{unsubscribe, unsubscribe_form},
+        webhooks::receive_email_events,
+    },
+    session_store::PostgresSessionStore,
+    suppression_list::SuppressionList,
+    templates::EmailTemplates,
 };
 
 pub struct Application {
     port: u16,
-    db: DatabaseConnection,
     listener: TcpListener,
-    email_client: EmailClient,
-    base_url: ApplicationBaseUrl,
+    state: AppState,
+    scheduler_interval: Duration,
 }
 
 impl Application {
     pub async fn build(configuration: Settings) -> Result<Application, std::io::Error> {
+        let email_client = configuration
+            .email_client
+            .clone()
+            .client()
+            .map_err(std::io::Error::other)?;
+        Self::build_with_email_client(configuration, email_client).await
+    }
+
+    /// 使用调用方提供的邮件客户端构建应用，测试中可以借此注入内存后端
+    pub async fn build_with_email_client(
+        configuration: Settings,
+        email_client: EmailClient,
+    ) -> Result<Application, std::io::Error> {
         let db = Database::connect(configuration.database.with_db())
             .await
             .unwrap();
+        let email_client = email_client.with_suppression_list(SuppressionList::new(db.clone()));
 
         let address = format!(
             "{}:{}",
@@ -42,19 +84,29 @@ impl Application {
         );
         let listener = TcpListener::bind(address).await.unwrap();
         let port = listener.local_addr().unwrap().port();
+        let templates =
+            EmailTemplates::new(&configuration.templates).map_err(std::io::Error::other)?;
+        let subscription_token_ttl = configuration.application.subscription_token_ttl();
+        let rate_limiter = configuration.rate_limit.limiter(db.clone());
+        let scheduler_interval = configuration.application.newsletter_scheduler_interval();
 
-        let email_client = EmailClient::new(
-            configuration.email_client.smtp_username,
-            SecretString::from(configuration.email_client.smtp_password),
-            &configuration.email_client.base_url,
-        );
+        let state = AppState {
+            db: Arc::new(db),
+            email_client: Arc::new(email_client),
+            templates: Arc::new(templates),
+            base_url: Arc::new(ApplicationBaseUrl(configuration.application.base_url)),
+            hmac_secret: Arc::new(HmacSecret::derive(&configuration.application.hmac_secret)),
+            subscription_token_ttl,
+            rate_limiter,
+            tracking: configuration.tracking,
+            email_events_webhook: configuration.email_events_webhook,
+        };
 
         Ok(Self {
             port,
-            db,
             listener,
-            email_client,
-            base_url: ApplicationBaseUrl(configuration.application.base_url),
+            state,
+            scheduler_interval,
         })
     }
 
@@ -63,35 +115,141 @@ impl Application {
     }
 
     pub fn db(&self) -> DatabaseConnection {
-        self.db.clone()
+        self.state.db.as_ref().clone()
+    }
+
+    /// 定期清理过期会话和闲置令牌桶，与 API 一起在 `main` 中运行
+    pub fn run_cleanup_until_stopped(
+        &self,
+    ) -> impl Future<Output = Result<(), std::io::Error>> + Send + 'static {
+        let session_store = PostgresSessionStore::new(self.db());
+        let rate_limiter = self.state.rate_limiter.clone();
+        async move {
+            tokio::join!(
+                session_store.continuously_delete_expired(Duration::from_secs(60)),
+                rate_limiter.continuously_delete_idle(Duration::from_secs(600)),
+            );
+            Ok(())
+        }
+    }
+
+    /// 定期为到期的计划发送创建投递任务，与 API 一起在 `main` 中运行
+    ///
+    /// 每个实例都运行调度任务，由 advisory lock 保证同一时刻只有一个实例生效
+    pub fn run_scheduler_until_stopped(
+        &self,
+    ) -> impl Future<Output = Result<(), std::io::Error>> + Send + 'static {
+        run_scheduler_until_stopped(self.db(), self.scheduler_interval)
     }
 
-    pub async fn run_until_stopped(self) {
-        run(self.listener, self.db, self.email_client, self.base_url).await;
+    pub async fn run_until_stopped(self) -> Result<(), std::io::Error> {
+        run(self.listener, self.state).await
     }
 }
 
 pub struct ApplicationBaseUrl(pub String);
 
+/// 由 `application.hmac_secret` 为每种用途分别派生的密钥
+///
+/// 会话 cookie、退订令牌和点击跟踪令牌各用各的密钥，不直接使用配置中的原始密钥
+#[derive(Clone)]
+pub struct HmacSecret {
+    pub session: Key,
+    pub unsubscribe: SecretString,
+    pub click: SecretString,
+}
+
+impl HmacSecret {
+    pub fn derive(secret: &SecretString) -> Self {
+        Self {
+            session: Key::from(&derive_key(secret, "session")),
+            unsubscribe: SecretString::from(hex::encode(derive_key(secret, "unsubscribe"))),
+            click: SecretString::from(hex::encode(derive_key(secret, "click"))),
+        }
+    }
+}
+
+/// 以原始密钥对用途名称计算 HMAC-SHA512，得到的 64 字节正好满足会话 cookie 密钥的长度要求
+fn derive_key(secret: &SecretString, purpose: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha512>::new_from_slice(secret.expose_secret().as_bytes())
+        .expect("HMAC 可以接受任意长度的密钥");
+    mac.update(purpose.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
 pub struct AppState {
     pub db: Arc<DatabaseConnection>,
     pub email_client: Arc<EmailClient>,
+    pub templates: Arc<EmailTemplates>,
     pub base_url: Arc<ApplicationBaseUrl>,
+    pub hmac_secret: Arc<HmacSecret>,
+    pub subscription_token_ttl: Duration,
+    pub rate_limiter: RateLimiter,
+    pub tracking: TrackingSettings,
+    pub email_events_webhook: EmailEventsWebhookSettings,
 }
 
-pub async fn run(listener: TcpListener, db: DatabaseConnection, email_client: EmailClient, base_url: ApplicationBaseUrl) {
+pub async fn run(listener: TcpListener, app_state: AppState) -> Result<(), std::io::Error> {
     let x_request_id = HeaderName::from_static("x-request-id");
-    let app_state = AppState {
-        db: Arc::new(db),
-        email_client: Arc::new(email_client),
-        base_url: Arc::new(base_url),
-    };
+
+    let session_store = PostgresSessionStore::new(app_state.db.as_ref().clone());
+    let session_key = app_state.hmac_secret.session.clone();
+    let session_layer = SessionManagerLayer::new(session_store)
+        .with_secure(app_state.base_url.0.starts_with("https://"))
+        .with_private(session_key);
+    let rate_limiter = app_state.rate_limiter.clone();
+
+    let admin_routes = Router::new()
+        .route("/dashboard", get(admin_dashboard))
+        .route(
+            "/newsletters",
+            get(publish_newsletter_form).post(publish_newsletter),
+        )
+        .route("/newsletters/scheduled", get(list_scheduled_newsletters))
+        .route("/newsletters/{issue_id}/stats", get(newsletter_stats))
+        .route(
+            "/newsletters/{issue_id}/schedule",
+            put(reschedule_newsletter).delete(cancel_newsletter),
+        )
+        .route("/lists", get(list_lists).post(create_list))
+        .route("/subscribers", get(list_subscribers))
+        .route("/subscribers/export.csv", get(export_subscribers))
+        .route("/subscribers/import", post(import_subscribers))
+        .route(
+            "/subscribers/{subscriber_id}",
+            get(get_subscriber).delete(delete_subscriber),
+        )
+        .route("/suppressions", get(list_suppressions).post(add_suppression))
+        .route("/suppressions/{email}", delete(remove_suppression))
+        .route("/logout", post(log_out))
+        .route_layer(middleware::from_fn(reject_anonymous_users));
+
+    // 会触发确认邮件的接口按客户端 IP 限流
+    let rate_limited_routes = Router::new()
+        .route("/subscriptions", post(subscribe))
+        .route("/subscriptions/confirm/resend", post(resend_confirmation))
+        .route_layer(middleware::from_fn_with_state(
+            rate_limiter,
+            limit_by_client_ip,
+        ));
 
     let app = Router::new()
         .route("/health_check", get(health_check))
-        .route("/subscriptions", post(subscribe))
+        .merge(rate_limited_routes)
         .route("/subscriptions/confirm", get(confirm))
+        .route(
+            "/subscriptions/unsubscribe",
+            get(unsubscribe_form).post(unsubscribe),
+        )
+        .route("/login", get(login_form).post(login))
+        .route("/newsletters", post(newsletters::publish_newsletter))
+        .route("/t/o/{pixel}", get(track_open))
+        .route("/t/c/{token}", get(track_click))
+        .route("/webhooks/email-events", post(receive_email_events))
+        .nest("/admin", admin_routes)
         .with_state(Arc::new(app_state))
+        .layer(MessagesManagerLayer)
+        .layer(session_layer)
         .layer(
             ServiceBuilder::new().layer(TraceLayer::new_for_http().make_span_with(
                 |request: &Request| {
@@ -119,5 +277,9 @@ pub async fn run(listener: TcpListener, db: DatabaseConnection, email_client: Em
         ))
         // propagate `x-request-id` headers from request to response
         .layer(PropagateRequestIdLayer::new(x_request_id));
-    axum::serve(listener, app).await.unwrap();
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await
 }
@@ -0,0 +1,56 @@
+use sea_orm::{
+    ActiveValue::Set, ConnectionTrait, DatabaseConnection, DbErr, EntityTrait,
+    sea_query::OnConflict,
+};
+
+use crate::{
+    domain::{SuppressionSource, normalize_email},
+    entities::suppressions,
+};
+
+/// 全局抑制列表，列表中的地址不会再收到任何邮件，包括确认邮件
+///
+/// 通过 [`crate::email_client::EmailClient::with_suppression_list`] 挂到邮件客户端上，
+/// 每次发送前都会检查收件人
+#[derive(Clone, Debug)]
+pub struct SuppressionList {
+    db: DatabaseConnection,
+}
+
+impl SuppressionList {
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self { db }
+    }
+
+    #[tracing::instrument(name = "检查抑制列表", skip(self))]
+    pub async fn contains(&self, email: &str) -> Result<bool, DbErr> {
+        let entry = suppressions::Entity::find_by_id(normalize_email(email))
+            .one(&self.db)
+            .await?;
+        Ok(entry.is_some())
+    }
+}
+
+/// 把地址加入抑制列表，已经在列表中时保留原来的原因和来源，返回是否新增了记录
+#[tracing::instrument(name = "加入抑制列表", skip(db))]
+pub async fn suppress(
+    db: &impl ConnectionTrait,
+    email: &str,
+    reason: &str,
+    source: SuppressionSource,
+) -> Result<bool, DbErr> {
+    let inserted = suppressions::Entity::insert(suppressions::ActiveModel {
+        email: Set(normalize_email(email)),
+        reason: Set(reason.to_string()),
+        source: Set(source),
+        created_at: Set(chrono::Utc::now()),
+    })
+    .on_conflict(
+        OnConflict::column(suppressions::Column::Email)
+            .do_nothing()
+            .to_owned(),
+    )
+    .exec_without_returning(db)
+    .await?;
+    Ok(inserted > 0)
+}
@@ -0,0 +1,279 @@
+use std::collections::HashMap;
+
+use fluent_bundle::FluentArgs;
+use tera::{Context, Tera, Value};
+
+use crate::{configuration::TemplateSettings, i18n::Locale};
+
+/// 系统发送的事务性邮件
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmailTemplate {
+    Confirmation,
+    Welcome,
+    UnsubscribeConfirmation,
+    /// 管理员发布的新闻邮件，正文由管理员提供，模板只负责布局和页脚
+    Newsletter,
+}
+
+impl EmailTemplate {
+    fn name(&self) -> &'static str {
+        match self {
+            EmailTemplate::Confirmation => "confirmation",
+            EmailTemplate::Welcome => "welcome",
+            EmailTemplate::UnsubscribeConfirmation => "unsubscribe_confirmation",
+            EmailTemplate::Newsletter => "newsletter",
+        }
+    }
+}
+
+/// 直接返回给浏览器的页面
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageTemplate {
+    ConfirmationExpired,
+    ConfirmationResent,
+    UnsubscribeForm,
+    UnsubscribeDone,
+}
+
+impl PageTemplate {
+    fn name(&self) -> &'static str {
+        match self {
+            PageTemplate::ConfirmationExpired => "confirmation_expired",
+            PageTemplate::ConfirmationResent => "confirmation_resent",
+            PageTemplate::UnsubscribeForm => "unsubscribe_form",
+            PageTemplate::UnsubscribeDone => "unsubscribe_done",
+        }
+    }
+}
+
+/// 渲染完成、可以直接发送的邮件内容
+#[derive(Debug)]
+pub struct RenderedEmail {
+    pub subject: String,
+    pub html_content: String,
+    pub text_content: String,
+}
+
+/// 从 `templates/` 目录加载的邮件和页面模板
+///
+/// 每封邮件由 `emails/<名称>.subject.txt`、`emails/<名称>.html` 和 `emails/<名称>.txt`
+/// 三个模板组成，`.html` 模板中的变量会自动转义。
+/// 提供 `unsubscribe_link` 变量时，布局会在页脚附上退订链接。
+/// 页面模板位于 `pages/<名称>.html`，与邮件共用同样的转义规则和 `t` 函数。
+/// 模板中的文本通过 `t(key=..., lang=lang)` 函数从 Fluent 目录中取出
+#[derive(Debug)]
+pub struct EmailTemplates {
+    tera: Tera,
+}
+
+impl EmailTemplates {
+    /// 加载模板目录，`override_directory` 中的同名模板优先于默认模板
+    pub fn new(settings: &TemplateSettings) -> Result<Self, tera::Error> {
+        let base = Tera::parse(&glob(&settings.directory))?;
+        let mut tera = match &settings.override_directory {
+            Some(directory) => {
+                let mut overrides = Tera::parse(&glob(directory))?;
+                overrides.extend(&base)?;
+                overrides
+            }
+            None => base,
+        };
+        tera.build_inheritance_chains()?;
+        tera.autoescape_on(vec![".html"]);
+        tera.set_escape_fn(escape_html);
+        tera.register_function("t", translate);
+
+        Ok(Self { tera })
+    }
+
+    pub fn render(
+        &self,
+        template: EmailTemplate,
+        locale: Locale,
+        context: &Context,
+    ) -> Result<RenderedEmail, tera::Error> {
+        let name = template.name();
+        let mut context = context.clone();
+        context.insert("lang", locale.as_str());
+        let subject = self
+            .tera
+            .render(&format!("emails/{}.subject.txt", name), &context)?;
+
+        Ok(RenderedEmail {
+            subject: subject.trim().to_string(),
+            html_content: self.tera.render(&format!("emails/{}.html", name), &context)?,
+            text_content: self.tera.render(&format!("emails/{}.txt", name), &context)?,
+        })
+    }
+
+    pub fn render_page(
+        &self,
+        template: PageTemplate,
+        locale: Locale,
+        context: &Context,
+    ) -> Result<String, tera::Error> {
+        let mut context = context.clone();
+        context.insert("lang", locale.as_str());
+        self.tera
+            .render(&format!("pages/{}.html", template.name()), &context)
+    }
+}
+
+/// 模板函数 `t`：`key` 为 Fluent 条目名，`lang` 为语言，其余参数作为条目的变量
+fn translate(args: &HashMap<String, Value>) -> tera::Result<Value> {
+    let key = args
+        .get("key")
+        .and_then(Value::as_str)
+        .ok_or_else(|| tera::Error::msg("t 函数缺少 key 参数"))?;
+    let locale = args
+        .get("lang")
+        .and_then(Value::as_str)
+        .and_then(Locale::parse)
+        .unwrap_or_default();
+
+    let mut fluent_args = FluentArgs::new();
+    for (name, value) in args {
+        match (name.as_str(), value) {
+            ("key" | "lang", _) => {}
+            (_, Value::String(s)) => fluent_args.set(name.as_str(), s.as_str()),
+            (_, Value::Number(n)) => fluent_args.set(name.as_str(), n.as_f64()),
+            (_, other) => fluent_args.set(name.as_str(), other.to_string()),
+        }
+    }
+
+    Ok(Value::String(locale.translate(key, Some(&fluent_args))))
+}
+
+fn glob(directory: &str) -> String {
+    format!("{}/**/*", directory.trim_end_matches('/'))
+}
+
+/// 与 tera 默认的转义规则相比不转义 `/`，避免邮件中的链接被改写
+fn escape_html(input: &str) -> String {
+    let mut output = String::with_capacity(input.len());
+    for c in input.chars() {
+        match c {
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            '"' => output.push_str("&quot;"),
+            '\'' => output.push_str("&#x27;"),
+            _ => output.push(c),
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use tera::Context;
+
+    use super::{EmailTemplate, EmailTemplates, PageTemplate};
+    use crate::{configuration::TemplateSettings, i18n::Locale};
+
+    fn default_templates() -> EmailTemplates {
+        EmailTemplates::new(&TemplateSettings {
+            directory: "templates".into(),
+            override_directory: None,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn confirmation_email_contains_the_link_in_both_bodies() {
+        let mut context = Context::new();
+        context.insert("name", "le guin");
+        context.insert("list_name", "Release Notes");
+        context.insert(
+            "confirmation_link",
+            "http://127.0.0.1/subscriptions/confirm?subscription_token=abc",
+        );
+
+        let email = default_templates()
+            .render(EmailTemplate::Confirmation, Locale::ZhCn, &context)
+            .unwrap();
+
+        assert!(!email.subject.is_empty());
+        assert!(email.html_content.contains(
+            r#"href="http://127.0.0.1/subscriptions/confirm?subscription_token=abc""#
+        ));
+        assert!(email.html_content.contains("<html"));
+        assert!(email.text_content.contains("Release Notes"));
+        assert!(
+            email
+                .text_content
+                .contains("http://127.0.0.1/subscriptions/confirm?subscription_token=abc")
+        );
+    }
+
+    #[test]
+    fn variables_are_escaped_in_html_but_not_in_text() {
+        let mut context = Context::new();
+        context.insert("name", "<b>le guin</b>");
+
+        let email = default_templates()
+            .render(EmailTemplate::Welcome, Locale::ZhCn, &context)
+            .unwrap();
+
+        assert!(email.html_content.contains("&lt;b&gt;le guin&lt;/b&gt;"));
+        assert!(email.text_content.contains("<b>le guin</b>"));
+    }
+
+    #[test]
+    fn override_directory_takes_precedence_over_default_templates() {
+        let directory = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        std::fs::create_dir_all(directory.join("emails")).unwrap();
+        std::fs::write(
+            directory.join("emails/welcome.subject.txt"),
+            "Welcome, {{ name }}",
+        )
+        .unwrap();
+        let templates = EmailTemplates::new(&TemplateSettings {
+            directory: "templates".into(),
+            override_directory: Some(directory.to_string_lossy().into_owned()),
+        })
+        .unwrap();
+        let mut context = Context::new();
+        context.insert("name", "le guin");
+
+        let email = templates.render(EmailTemplate::Welcome, Locale::ZhCn, &context).unwrap();
+
+        assert_eq!(email.subject, "Welcome, le guin");
+        assert!(email.html_content.contains("le guin"));
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn emails_are_rendered_in_the_requested_language() {
+        let templates = default_templates();
+        let mut context = Context::new();
+        context.insert("name", "le guin");
+
+        let chinese = templates
+            .render(EmailTemplate::Welcome, Locale::ZhCn, &context)
+            .unwrap();
+        let english = templates
+            .render(EmailTemplate::Welcome, Locale::En, &context)
+            .unwrap();
+
+        assert_eq!(chinese.subject, Locale::ZhCn.translate("welcome-subject", None));
+        assert_eq!(english.subject, Locale::En.translate("welcome-subject", None));
+        assert!(english.html_content.contains(r#"<html lang="en">"#));
+        assert!(english.text_content.contains("le guin, your subscription is confirmed"));
+    }
+
+    #[test]
+    fn pages_are_rendered_in_the_requested_language_with_escaped_variables() {
+        let mut context = Context::new();
+        context.insert("subscription_token", "\"><script>");
+
+        let page = default_templates()
+            .render_page(PageTemplate::ConfirmationExpired, Locale::En, &context)
+            .unwrap();
+
+        assert!(page.contains(r#"<html lang="en">"#));
+        assert!(page.contains(&Locale::En.translate("confirm-expired-resend-button", None)));
+        assert!(page.contains("&quot;&gt;&lt;script&gt;"));
+    }
+}
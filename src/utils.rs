@@ -0,0 +1,140 @@
+use std::fmt::Debug;
+
+use axum::{
+    Form, Json,
+    extract::{FromRequest, Request},
+    http::{HeaderValue, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use serde::de::DeserializeOwned;
+
+use crate::i18n::Locale;
+
+/// 记录错误并返回 500，用于不值得单独定义错误类型的意外错误
+pub fn e500<T>(e: T) -> Response
+where
+    T: Debug,
+{
+    tracing::error!("{:?}", e);
+    StatusCode::INTERNAL_SERVER_ERROR.into_response()
+}
+
+/// 按 `Content-Type` 从 JSON 或 `application/x-www-form-urlencoded` 表单中解析请求体
+pub enum FormOrJson<T> {
+    Form(T),
+    Json(T),
+}
+
+impl<T> FormOrJson<T> {
+    pub fn is_json(&self) -> bool {
+        matches!(self, FormOrJson::Json(_))
+    }
+
+    pub fn into_inner(self) -> T {
+        match self {
+            FormOrJson::Form(value) | FormOrJson::Json(value) => value,
+        }
+    }
+}
+
+impl<S, T> FromRequest<S> for FormOrJson<T>
+where
+    S: Send + Sync,
+    T: DeserializeOwned,
+{
+    type Rejection = Response;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let locale = Locale::negotiate(None, request.headers());
+        if is_json_content_type(&request) {
+            let Json(value) = Json::<T>::from_request(request, state)
+                .await
+                .map_err(|e| rejection_response(locale, e.status(), e.body_text()))?;
+            Ok(FormOrJson::Json(value))
+        } else {
+            // 其他类型交给表单解析，不支持的类型由它返回 415；
+            // 只有客户端声明接受 JSON 时才返回 JSON，普通表单提交仍然得到 axum 的纯文本错误
+            let accepts_json = accepts_json(&request);
+            let Form(value) = Form::<T>::from_request(request, state).await.map_err(|e| {
+                if accepts_json {
+                    rejection_response(locale, e.status(), e.body_text())
+                } else {
+                    e.into_response()
+                }
+            })?;
+            Ok(FormOrJson::Form(value))
+        }
+    }
+}
+
+/// JSON 格式的错误响应体
+#[derive(serde::Serialize)]
+pub struct JsonErrorBody {
+    pub message: String,
+    /// 出错的字段，其他错误时为空
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
+}
+
+#[derive(serde::Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+/// 请求体无法解析时返回与校验错误相同格式的 JSON，状态码沿用 axum 的判断
+fn rejection_response(locale: Locale, status: StatusCode, detail: String) -> Response {
+    tracing::warn!(%status, detail, "请求体解析失败");
+    let errors = missing_field(&detail)
+        .map(|field| FieldError {
+            field: field.to_string(),
+            message: locale.translate("request-error-missing-field", None),
+        })
+        .into_iter()
+        .collect();
+    let body = JsonErrorBody {
+        message: locale.translate("request-error-invalid-body", None),
+        errors,
+    };
+    (
+        status,
+        [(
+            header::CONTENT_LANGUAGE,
+            HeaderValue::from_static(locale.as_str()),
+        )],
+        Json(body),
+    )
+        .into_response()
+}
+
+/// 从 serde 的错误信息 ``missing field `email` `` 中取出字段名
+fn missing_field(detail: &str) -> Option<&str> {
+    let (_, rest) = detail.split_once("missing field `")?;
+    rest.split_once('`').map(|(field, _)| field)
+}
+
+fn is_json_content_type(request: &Request) -> bool {
+    request
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(is_json_media_type)
+}
+
+/// `Accept` 中列出了 JSON 类型；`*/*` 不算，浏览器提交表单时也会带上它
+fn accepts_json(request: &Request) -> bool {
+    request
+        .headers()
+        .get_all(header::ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(is_json_media_type)
+}
+
+/// `application/json` 以及 `application/*+json`，忽略 `;` 之后的参数
+fn is_json_media_type(value: &str) -> bool {
+    let mime = value.split(';').next().unwrap_or_default();
+    let mime = mime.trim().to_ascii_lowercase();
+    mime == "application/json" || (mime.starts_with("application/") && mime.ends_with("+json"))
+}
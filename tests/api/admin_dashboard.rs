@@ -0,0 +1,69 @@
+use my_zero2prod::entities::users;
+use sea_orm::{ActiveModelTrait, ActiveValue::Set};
+
+use crate::helpers::{assert_is_redirect_to, spawn_app};
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_access_the_admin_dashboard() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.get_admin_dashboard().await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn logout_clears_session_state() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act - Part 1 - Login
+    let login_body = serde_json::json!({
+        "username": &app.test_user.username,
+        "password": &app.test_user.password
+    });
+    let response = app.post_login(&login_body).await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+
+    // Act - Part 2 - Follow the redirect
+    let html_page = app.get_admin_dashboard_html().await;
+    assert!(html_page.contains(&format!("欢迎 {}", app.test_user.username)));
+
+    // Act - Part 3 - Logout
+    let response = app.post_logout().await;
+    assert_is_redirect_to(&response, "/login");
+
+    // Act - Part 4 - Follow the redirect
+    let html_page = app.get_login_html().await;
+    assert!(html_page.contains("<p><i>您已成功退出登录</i></p>"));
+
+    // Act - Part 5 - Attempt to load admin panel
+    let response = app.get_admin_dashboard().await;
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn the_username_is_html_escaped_on_the_dashboard() {
+    // Arrange
+    let mut app = spawn_app().await;
+    app.test_user.username = "<script>alert('x')</script>".into();
+    users::ActiveModel {
+        user_id: Set(app.test_user.user_id),
+        username: Set(app.test_user.username.clone()),
+        ..Default::default()
+    }
+    .update(&app.db)
+    .await
+    .unwrap();
+    app.test_user.login(&app).await;
+
+    // Act
+    let html_page = app.get_admin_dashboard_html().await;
+
+    // Assert
+    assert!(!html_page.contains("<script>"));
+    assert!(html_page.contains("欢迎 &lt;script&gt;"));
+}
@@ -0,0 +1,393 @@
+use chrono::{DateTime, Duration, Utc};
+use my_zero2prod::{
+    domain::SubscriptionStatus,
+    entities::{list_memberships, subscription_tokens, subscriptions},
+};
+use sea_orm::{ActiveModelTrait, ActiveValue::Set, ColumnTrait, EntityTrait, PaginatorTrait, QueryFilter};
+
+use crate::helpers::{TestApp, assert_is_redirect_to, spawn_app};
+
+/// 订阅时间由测试指定，分页、过滤和导出都依赖它
+async fn insert_subscriber(
+    app: &TestApp,
+    email: &str,
+    status: SubscriptionStatus,
+    subscribed_at: DateTime<Utc>,
+) -> uuid::Uuid {
+    let id = app.insert_subscriber(email, status).await;
+    subscriptions::ActiveModel {
+        id: Set(id),
+        subscribed_at: Set(subscribed_at),
+        ..Default::default()
+    }
+    .update(&app.db)
+    .await
+    .unwrap();
+    id
+}
+
+fn emails(page: &serde_json::Value) -> Vec<&str> {
+    page["subscribers"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|s| s["email"].as_str().unwrap())
+        .collect()
+}
+
+/// 过滤测试只关心命中哪些行，排序由分页测试覆盖
+fn sorted_emails(page: &serde_json::Value) -> Vec<&str> {
+    let mut emails = emails(page);
+    emails.sort_unstable();
+    emails
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_manage_subscribers() {
+    let app = spawn_app().await;
+
+    let list = app.get_admin_subscribers("").await;
+    let delete = app.delete_admin_subscriber(uuid::Uuid::new_v4()).await;
+
+    assert_is_redirect_to(&list, "/login");
+    assert_is_redirect_to(&delete, "/login");
+}
+
+#[tokio::test]
+async fn subscribers_are_paginated_newest_first_with_a_cursor() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let now = Utc::now();
+    for n in 0..3 {
+        insert_subscriber(&app, &format!("{}@gmail.com", n), SubscriptionStatus::Confirmed, now - Duration::hours(n)).await;
+    }
+    // 订阅时间相同的订阅者按 ID 排序，不会在翻页时重复或遗漏
+    insert_subscriber(&app, "tie-1@gmail.com", SubscriptionStatus::Confirmed, now - Duration::hours(5)).await;
+    insert_subscriber(&app, "tie-2@gmail.com", SubscriptionStatus::Confirmed, now - Duration::hours(5)).await;
+
+    let mut seen = vec![];
+    let mut query = "limit=2".to_string();
+    let mut n_pages = 0;
+    loop {
+        let response = app.get_admin_subscribers(&query).await;
+        assert_eq!(response.status().as_u16(), 200);
+        let page: serde_json::Value = response.json().await.unwrap();
+        n_pages += 1;
+        seen.extend(emails(&page).into_iter().map(String::from));
+        match page["next_cursor"].as_str() {
+            Some(cursor) => query = format!("limit=2&cursor={}", cursor),
+            None => break,
+        }
+    }
+
+    assert_eq!(n_pages, 3);
+    assert_eq!(seen.len(), 5);
+    assert_eq!(&seen[..3], ["0@gmail.com", "1@gmail.com", "2@gmail.com"]);
+    let mut ties = seen[3..].to_vec();
+    ties.sort();
+    assert_eq!(ties, ["tie-1@gmail.com", "tie-2@gmail.com"]);
+}
+
+#[tokio::test]
+async fn subscribers_can_be_filtered_by_status_email_and_date() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let now = Utc::now();
+    insert_subscriber(&app, "Ursula@Gmail.com", SubscriptionStatus::Confirmed, now).await;
+    insert_subscriber(&app, "ursula_100%@gmail.com", SubscriptionStatus::PendingConfirmation, now).await;
+    insert_subscriber(&app, "octavia@gmail.com", SubscriptionStatus::Confirmed, now - Duration::days(10)).await;
+
+    let page: serde_json::Value = app
+        .get_admin_subscribers("status=confirmed")
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(sorted_emails(&page), ["Ursula@Gmail.com", "octavia@gmail.com"]);
+
+    let page: serde_json::Value = app
+        .get_admin_subscribers("email=URSULA")
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(sorted_emails(&page), ["Ursula@Gmail.com", "ursula_100%@gmail.com"]);
+
+    let page: serde_json::Value = app
+        .get_admin_subscribers("email=100%25")
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(sorted_emails(&page), ["ursula_100%@gmail.com"]);
+
+    let from = (now - Duration::days(20)).to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+    let to = (now - Duration::days(1)).to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+    let page: serde_json::Value = app
+        .get_admin_subscribers(&format!("subscribed_from={}&subscribed_to={}", from, to))
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(sorted_emails(&page), ["octavia@gmail.com"]);
+}
+
+#[tokio::test]
+async fn an_invalid_cursor_is_rejected_with_a_400() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    let response = app.get_admin_subscribers("cursor=garbage").await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn a_single_subscriber_can_be_fetched() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let id = insert_subscriber(&app, "ursula@gmail.com", SubscriptionStatus::Confirmed, Utc::now()).await;
+
+    let response = app.get_admin_subscriber(id).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let subscriber: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(subscriber["id"], id.to_string());
+    assert_eq!(subscriber["email"], "ursula@gmail.com");
+    assert_eq!(subscriber["status"], "confirmed");
+
+    let response = app.get_admin_subscriber(uuid::Uuid::new_v4()).await;
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn provider_aliases_are_flagged_as_possible_duplicates() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let id = insert_subscriber(&app, "ursula.le.guin@gmail.com", SubscriptionStatus::Confirmed, Utc::now()).await;
+    let alias = insert_subscriber(&app, "ursulaleguin+books@googlemail.com", SubscriptionStatus::Confirmed, Utc::now()).await;
+    insert_subscriber(&app, "ursula.le.guin@example.com", SubscriptionStatus::Confirmed, Utc::now()).await;
+
+    let subscriber: serde_json::Value = app.get_admin_subscriber(id).await.json().await.unwrap();
+
+    assert_eq!(subscriber["possible_duplicates"], serde_json::json!([alias]));
+}
+
+#[tokio::test]
+async fn deleting_a_subscriber_removes_its_tokens() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    app.post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com".into())
+        .await;
+    let subscriber = subscriptions::Entity::find()
+        .one(&app.db)
+        .await
+        .unwrap()
+        .unwrap();
+
+    let response = app.delete_admin_subscriber(subscriber.id).await;
+
+    assert_eq!(response.status().as_u16(), 204);
+    assert_eq!(subscriptions::Entity::find().count(&app.db).await.unwrap(), 0);
+    assert_eq!(subscription_tokens::Entity::find().count(&app.db).await.unwrap(), 0);
+
+    let response = app.delete_admin_subscriber(subscriber.id).await;
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_import_or_export_subscribers() {
+    let app = spawn_app().await;
+
+    let export = app.get_admin_subscribers_export().await;
+    let import = app
+        .post_admin_subscribers_import("email,name\n".into(), "confirmed")
+        .await;
+
+    assert_is_redirect_to(&export, "/login");
+    assert_is_redirect_to(&import, "/login");
+}
+
+#[tokio::test]
+async fn subscribers_are_exported_as_csv() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let now = Utc::now();
+    let first = insert_subscriber(&app, "ursula@gmail.com", SubscriptionStatus::Confirmed, now - Duration::hours(1)).await;
+    let second = insert_subscriber(&app, "octavia@gmail.com", SubscriptionStatus::PendingConfirmation, now).await;
+
+    let response = app.get_admin_subscribers_export().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.headers()["Content-Type"], "text/csv; charset=utf-8");
+    let body = response.text().await.unwrap();
+    let lines: Vec<_> = body.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[0], "id,email,name,status,locale,subscribed_at");
+    assert!(lines[1].starts_with(&format!("{},ursula@gmail.com,le guin,confirmed,zh-CN,", first)));
+    assert!(lines[2].starts_with(&format!("{},octavia@gmail.com,le guin,pending_confirmation,zh-CN,", second)));
+}
+
+#[tokio::test]
+async fn import_reports_accepted_updated_duplicate_and_rejected_rows() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let existing_id =
+        insert_subscriber(&app, "existing@gmail.com", SubscriptionStatus::Unsubscribed, Utc::now()).await;
+    let pending_id =
+        insert_subscriber(&app, "pending@gmail.com", SubscriptionStatus::PendingConfirmation, Utc::now()).await;
+    let confirmed_id =
+        insert_subscriber(&app, "confirmed@gmail.com", SubscriptionStatus::Confirmed, Utc::now()).await;
+    let csv = "\
+email,name,locale
+ursula@gmail.com,Ursula Le Guin,en
+not-an-email,Someone,
+existing@gmail.com,Existing,
+octavia@gmail.com,\"Butler, Octavia\",
+ursula@gmail.com,Ursula Again,
+valid@gmail.com,<script>,
+pending@gmail.com,Pending,
+confirmed@gmail.com,Confirmed,
+";
+
+    let response = app.post_admin_subscribers_import(csv.into(), "confirmed").await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let report: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(report["accepted"], 2);
+    assert_eq!(report["updated"], 1);
+    assert_eq!(report["duplicates"], 3);
+    assert_eq!(report["rejected"], 2);
+    let mut rows: Vec<_> = report["rows"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|r| (r["line"].as_u64().unwrap(), r["outcome"].as_str().unwrap().to_string()))
+        .collect();
+    rows.sort();
+    assert_eq!(
+        rows,
+        [
+            (2, "accepted".into()),
+            (3, "rejected".into()),
+            (4, "duplicate".into()),
+            (5, "accepted".into()),
+            (6, "duplicate".into()),
+            (7, "rejected".into()),
+            (8, "duplicate".into()),
+            (9, "updated".into()),
+        ]
+    );
+
+    let ursula = subscriptions::Entity::find()
+        .all(&app.db)
+        .await
+        .unwrap()
+        .into_iter()
+        .find(|s| s.email == "ursula@gmail.com")
+        .unwrap();
+    // 文件中重复出现的邮箱以最后一行的姓名为准
+    assert_eq!(ursula.name, "Ursula Again");
+    assert_eq!(ursula.locale, "en");
+    assert_eq!(ursula.status, SubscriptionStatus::Confirmed);
+    // 已退订的订阅者不会被导入覆盖
+    let existing = subscriptions::Entity::find_by_id(existing_id)
+        .one(&app.db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(existing.status, SubscriptionStatus::Unsubscribed);
+    assert_eq!(existing.name, "le guin");
+    let membership = list_memberships::Entity::find()
+        .filter(list_memberships::Column::SubscriberId.eq(existing_id))
+        .one(&app.db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(membership.status, SubscriptionStatus::Unsubscribed);
+    // 导入不能替待确认的订阅者完成确认
+    let pending = subscriptions::Entity::find_by_id(pending_id)
+        .one(&app.db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(pending.status, SubscriptionStatus::PendingConfirmation);
+    assert_eq!(pending.name, "le guin");
+    let confirmed = subscriptions::Entity::find_by_id(confirmed_id)
+        .one(&app.db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(confirmed.name, "Confirmed");
+}
+
+#[tokio::test]
+async fn duplicates_split_across_batches_update_the_existing_subscriber() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let mut csv = String::from("email,name\nursula@gmail.com,Ursula Le Guin\n");
+    for n in 0..600 {
+        csv.push_str(&format!("subscriber-{}@gmail.com,Subscriber {}\n", n, n));
+    }
+    csv.push_str("Ursula@Gmail.com,Ursula K. Le Guin\n");
+
+    let response = app.post_admin_subscribers_import(csv, "confirmed").await;
+
+    let report: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(report["accepted"], 601);
+    assert_eq!(report["updated"], 1);
+    assert_eq!(report["duplicates"], 0);
+    let subscribers = subscriptions::Entity::find()
+        .filter(subscriptions::Column::CanonicalEmail.eq("ursula@gmail.com"))
+        .all(&app.db)
+        .await
+        .unwrap();
+    assert_eq!(subscribers.len(), 1);
+    assert_eq!(subscribers[0].name, "Ursula K. Le Guin");
+}
+
+#[tokio::test]
+async fn large_imports_are_written_in_batches() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let mut csv = String::from("email,name\n");
+    for n in 0..1234 {
+        csv.push_str(&format!("subscriber-{}@gmail.com,Subscriber {}\n", n, n));
+    }
+
+    let response = app.post_admin_subscribers_import(csv, "pending_confirmation").await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let report: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(report["accepted"], 1234);
+    assert_eq!(subscriptions::Entity::find().count(&app.db).await.unwrap(), 1234);
+}
+
+#[tokio::test]
+async fn an_import_without_the_required_columns_is_rejected() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    let response = app
+        .post_admin_subscribers_import("address,name\nursula@gmail.com,le guin\n".into(), "confirmed")
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(subscriptions::Entity::find().count(&app.db).await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn an_export_can_be_imported_into_another_instance() {
+    let source = spawn_app().await;
+    source.test_user.login(&source).await;
+    insert_subscriber(&source, "ursula@gmail.com", SubscriptionStatus::Confirmed, Utc::now()).await;
+    insert_subscriber(&source, "octavia@gmail.com", SubscriptionStatus::Confirmed, Utc::now()).await;
+    let csv = source.get_admin_subscribers_export().await.text().await.unwrap();
+    let target = spawn_app().await;
+    target.test_user.login(&target).await;
+
+    let response = target.post_admin_subscribers_import(csv, "confirmed").await;
+
+    let report: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(report["accepted"], 2);
+}
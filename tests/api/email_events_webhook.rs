@@ -0,0 +1,276 @@
+use my_zero2prod::{
+    domain::{SubscriptionStatus, WebhookEventKind},
+    entities::{email_webhook_events, list_memberships, subscriptions},
+};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::Set, ColumnTrait, EntityTrait, PaginatorTrait, QueryFilter,
+};
+
+use crate::helpers::{TestApp, spawn_app, spawn_app_with};
+
+const EMAIL: &str = "ursula_le_guin@gmail.com";
+
+fn bounce(bounce_type: &str) -> serde_json::Value {
+    serde_json::json!({
+        "RecordType": "Bounce",
+        "Type": bounce_type,
+        "Email": EMAIL,
+        "BouncedAt": "2025-10-19T09:00:00Z",
+        "Description": "The server was unable to deliver your message",
+    })
+}
+
+async fn subscriber_status(app: &TestApp, id: uuid::Uuid) -> SubscriptionStatus {
+    subscriptions::Entity::find_by_id(id)
+        .one(&app.db)
+        .await
+        .unwrap()
+        .unwrap()
+        .status
+}
+
+async fn set_status(app: &TestApp, id: uuid::Uuid, status: SubscriptionStatus) {
+    let mut subscriber: subscriptions::ActiveModel = subscriptions::Entity::find_by_id(id)
+        .one(&app.db)
+        .await
+        .unwrap()
+        .unwrap()
+        .into();
+    subscriber.status = Set(status);
+    subscriber.update(&app.db).await.unwrap();
+}
+
+async fn membership_status(app: &TestApp, id: uuid::Uuid) -> SubscriptionStatus {
+    list_memberships::Entity::find()
+        .filter(list_memberships::Column::SubscriberId.eq(id))
+        .one(&app.db)
+        .await
+        .unwrap()
+        .unwrap()
+        .status
+}
+
+async fn n_stored_events(app: &TestApp) -> u64 {
+    email_webhook_events::Entity::find()
+        .count(&app.db)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn a_hard_bounce_marks_the_subscriber_as_bounced() {
+    let app = spawn_app().await;
+    let id = app.insert_subscriber(EMAIL, SubscriptionStatus::Confirmed).await;
+
+    let response = app.post_email_event(&bounce("HardBounce")).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        subscriber_status(&app, id).await,
+        SubscriptionStatus::Bounced
+    );
+    assert_eq!(
+        membership_status(&app, id).await,
+        SubscriptionStatus::Bounced
+    );
+    let events = email_webhook_events::Entity::find()
+        .all(&app.db)
+        .await
+        .unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].kind, WebhookEventKind::HardBounce);
+    assert_eq!(events[0].subscriber_id, Some(id));
+    assert_eq!(events[0].payload, bounce("HardBounce"));
+}
+
+#[tokio::test]
+async fn bounced_subscribers_no_longer_receive_newsletters() {
+    let app = spawn_app().await;
+    app.insert_subscriber(EMAIL, SubscriptionStatus::Confirmed).await;
+    app.post_email_event(&bounce("HardBounce")).await;
+    app.test_user.login(&app).await;
+
+    app.post_publish_newsletter(&serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": uuid::Uuid::new_v4().to_string(),
+    }))
+    .await;
+    app.dispatch_all_pending_emails().await;
+
+    assert!(app.sent_emails().is_empty());
+}
+
+#[tokio::test]
+async fn a_complaint_marks_the_subscriber_as_complained() {
+    let app = spawn_app().await;
+    let id = app.insert_subscriber(EMAIL, SubscriptionStatus::Confirmed).await;
+
+    let response = app
+        .post_email_event(&serde_json::json!({
+            "RecordType": "SpamComplaint",
+            "Email": EMAIL,
+            "BouncedAt": "2025-10-19T09:00:00Z",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        subscriber_status(&app, id).await,
+        SubscriptionStatus::Complained
+    );
+    assert_eq!(
+        membership_status(&app, id).await,
+        SubscriptionStatus::Complained
+    );
+}
+
+#[tokio::test]
+async fn soft_bounces_mark_the_subscriber_as_bounced_only_at_the_threshold() {
+    let app = spawn_app_with(|c| c.email_events_webhook.soft_bounce_threshold = 2).await;
+    let id = app.insert_subscriber(EMAIL, SubscriptionStatus::Confirmed).await;
+
+    app.post_email_event(&bounce("SoftBounce")).await;
+    assert_eq!(
+        subscriber_status(&app, id).await,
+        SubscriptionStatus::Confirmed
+    );
+
+    app.post_email_event(&bounce("Transient")).await;
+    assert_eq!(
+        subscriber_status(&app, id).await,
+        SubscriptionStatus::Bounced
+    );
+    assert_eq!(n_stored_events(&app).await, 2);
+}
+
+#[tokio::test]
+async fn soft_bounces_are_counted_again_after_resubscribing() {
+    let app = spawn_app_with(|c| c.email_events_webhook.soft_bounce_threshold = 2).await;
+    let id = app.insert_subscriber(EMAIL, SubscriptionStatus::Confirmed).await;
+    app.post_email_event(&bounce("SoftBounce")).await;
+    app.post_email_event(&bounce("SoftBounce")).await;
+    assert_eq!(
+        subscriber_status(&app, id).await,
+        SubscriptionStatus::Bounced
+    );
+
+    // 订阅者重新订阅并确认
+    set_status(&app, id, SubscriptionStatus::Confirmed).await;
+    app.post_email_event(&bounce("SoftBounce")).await;
+
+    assert_eq!(
+        subscriber_status(&app, id).await,
+        SubscriptionStatus::Confirmed
+    );
+}
+
+#[tokio::test]
+async fn soft_bounces_before_unsubscribing_are_not_counted_after_resubscribing() {
+    let app = spawn_app_with(|c| c.email_events_webhook.soft_bounce_threshold = 2).await;
+    let id = app.insert_subscriber(EMAIL, SubscriptionStatus::Confirmed).await;
+    app.post_email_event(&bounce("SoftBounce")).await;
+
+    // 订阅者退订后重新订阅并确认，这些状态变化都不是由邮件事件引起的
+    set_status(&app, id, SubscriptionStatus::Unsubscribed).await;
+    set_status(&app, id, SubscriptionStatus::PendingConfirmation).await;
+    set_status(&app, id, SubscriptionStatus::Confirmed).await;
+    app.post_email_event(&bounce("SoftBounce")).await;
+
+    assert_eq!(
+        subscriber_status(&app, id).await,
+        SubscriptionStatus::Confirmed
+    );
+}
+
+#[tokio::test]
+async fn events_that_do_not_affect_delivery_are_stored_only() {
+    let app = spawn_app().await;
+    let id = app.insert_subscriber(EMAIL, SubscriptionStatus::Confirmed).await;
+
+    let response = app.post_email_event(&bounce("AutoResponder")).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        subscriber_status(&app, id).await,
+        SubscriptionStatus::Confirmed
+    );
+    assert_eq!(n_stored_events(&app).await, 1);
+}
+
+#[tokio::test]
+async fn events_for_unknown_addresses_are_stored_without_a_subscriber() {
+    let app = spawn_app().await;
+
+    let response = app.post_email_event(&bounce("HardBounce")).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let event = email_webhook_events::Entity::find()
+        .one(&app.db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(event.subscriber_id, None);
+    assert_eq!(event.email, EMAIL);
+}
+
+#[tokio::test]
+async fn events_match_subscribers_regardless_of_case() {
+    let app = spawn_app().await;
+    let id = app.insert_subscriber(EMAIL, SubscriptionStatus::Confirmed).await;
+
+    app.post_email_event(&serde_json::json!({
+        "RecordType": "Bounce",
+        "Type": "HardBounce",
+        "Email": "Ursula_Le_Guin@GMAIL.COM",
+    }))
+    .await;
+
+    assert_eq!(
+        subscriber_status(&app, id).await,
+        SubscriptionStatus::Bounced
+    );
+}
+
+#[tokio::test]
+async fn requests_without_a_valid_signature_are_rejected() {
+    let app = spawn_app().await;
+    let id = app.insert_subscriber(EMAIL, SubscriptionStatus::Confirmed).await;
+    let body = serde_json::to_vec(&bounce("HardBounce")).unwrap();
+
+    let test_cases = [
+        (None, "missing signature"),
+        (Some("not-hex"), "malformed signature"),
+        (Some("00112233"), "wrong signature"),
+    ];
+    for (signature, description) in test_cases {
+        let response = app
+            .post_email_event_with_signature(body.clone(), signature)
+            .await;
+        assert_eq!(
+            response.status().as_u16(),
+            401,
+            "The API did not reject a request with {}.",
+            description
+        );
+    }
+
+    assert_eq!(n_stored_events(&app).await, 0);
+    assert_eq!(
+        subscriber_status(&app, id).await,
+        SubscriptionStatus::Confirmed
+    );
+}
+
+#[tokio::test]
+async fn signed_but_malformed_events_are_rejected() {
+    let app = spawn_app().await;
+
+    let response = app
+        .post_email_event(&serde_json::json!({ "RecordType": "Bounce" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(n_stored_events(&app).await, 0);
+}
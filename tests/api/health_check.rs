@@ -7,7 +7,7 @@ async fn health_check_works() {
     let client = reqwest::Client::builder().no_proxy().build().unwrap();
 
     let response = client
-        .get(&format!("{}/health_check", test_app.address))
+        .get(format!("{}/health_check", test_app.address))
         .send()
         .await
         .expect("Failed to execute request.");
@@ -1,11 +1,29 @@
+use std::sync::Arc;
+
+use hmac::{Hmac, Mac};
 use migration::{Migrator, MigratorTrait};
 use my_zero2prod::{
-    configuration::{DatabaseSettings, get_configuration},
-    startup::Application,
+    authentication::compute_password_hash,
+    configuration::{DatabaseSettings, Settings, get_configuration},
+    email_client::{Email, EmailClient, InMemoryEmailTransport},
+    domain::{DEFAULT_LIST_SLUG, SubscriptionStatus},
+    entities::{list_memberships, lists, users},
+    i18n::Locale,
+    issue_delivery_worker::{DeliveryContext, ExecutionOutcome, try_execute_task},
+    newsletter_scheduler::enqueue_due_issues,
+    routes::subscriptions::new_subscription_row,
+    startup::{Application, ApplicationBaseUrl, HmacSecret},
+    suppression_list::SuppressionList,
     telemetry::{get_subscriber, init_subscriber},
+    templates::EmailTemplates,
 };
 use once_cell::sync::Lazy;
-use sea_orm::{ConnectionTrait, Database, DatabaseConnection};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::Set, ColumnTrait, ConnectionTrait, Database, DatabaseConnection,
+    EntityTrait, QueryFilter,
+};
+use secrecy::{ExposeSecret, SecretString};
+use sha2::Sha256;
 
 static TRACING: Lazy<()> = Lazy::new(|| {
     let default_filter_level = "info".to_string();
@@ -27,24 +45,429 @@ pub struct TestApp {
     pub port: u16,
     pub address: String,
     pub db: DatabaseConnection,
+    pub test_user: TestUser,
+    pub api_client: reqwest::Client,
+    pub delivery_context: DeliveryContext,
+    pub email_transport: InMemoryEmailTransport,
+    pub email_events_webhook_secret: SecretString,
+}
+
+/// 邮件中的确认链接
+pub struct ConfirmationLinks {
+    pub html: reqwest::Url,
+    pub plain_text: reqwest::Url,
+}
+
+pub struct TestUser {
+    pub user_id: uuid::Uuid,
+    pub username: String,
+    pub password: String,
+}
+
+impl TestUser {
+    pub fn generate() -> Self {
+        Self {
+            user_id: uuid::Uuid::new_v4(),
+            username: uuid::Uuid::new_v4().to_string(),
+            password: uuid::Uuid::new_v4().to_string(),
+        }
+    }
+
+    async fn store(&self, db: &DatabaseConnection) {
+        let password_hash = compute_password_hash(SecretString::from(self.password.clone())).unwrap();
+        users::ActiveModel {
+            user_id: Set(self.user_id),
+            username: Set(self.username.clone()),
+            password_hash: Set(password_hash.expose_secret().to_string()),
+        }
+        .insert(db)
+        .await
+        .expect("Failed to store test user.");
+    }
+
+    pub async fn login(&self, app: &TestApp) {
+        app.post_login(&serde_json::json!({
+            "username": &self.username,
+            "password": &self.password,
+        }))
+        .await;
+    }
 }
 
 impl TestApp {
+    pub fn sent_emails(&self) -> Vec<Email> {
+        self.email_transport.messages()
+    }
+
+    /// 从发送的确认邮件中提取确认链接，并把端口替换为测试应用的端口
+    pub fn get_confirmation_links(&self, email: &Email) -> ConfirmationLinks {
+        let html = self.get_link(&email.html_content, "/subscriptions/confirm");
+        let plain_text = self.get_link(&email.text_content, "/subscriptions/confirm");
+        ConfirmationLinks { html, plain_text }
+    }
+
+    /// 邮件页脚中的退订链接，HTML 和纯文本正文中的链接必须一致
+    pub fn get_unsubscribe_link(&self, email: &Email) -> reqwest::Url {
+        let html = self.get_link(&email.html_content, "/subscriptions/unsubscribe");
+        let plain_text = self.get_link(&email.text_content, "/subscriptions/unsubscribe");
+        assert_eq!(html, plain_text);
+        html
+    }
+
+    /// 新闻邮件 HTML 正文中的打开跟踪像素，未开启跟踪时为空
+    pub fn get_open_tracking_pixel(&self, email: &Email) -> Option<reqwest::Url> {
+        let mut pixels = linkify::LinkFinder::new()
+            .links(&email.html_content)
+            .filter(|l| *l.kind() == linkify::LinkKind::Url)
+            .map(|l| reqwest::Url::parse(l.as_str()).unwrap())
+            .filter(|l| l.path().starts_with("/t/o/"));
+        let mut pixel = pixels.next()?;
+        assert!(pixels.next().is_none());
+        pixel.set_port(Some(self.port)).unwrap();
+        Some(pixel)
+    }
+
+    /// 找出正文中唯一一个指向 `path` 的链接，并把端口替换为测试应用的端口
+    fn get_link(&self, s: &str, path: &str) -> reqwest::Url {
+        let links: Vec<_> = linkify::LinkFinder::new()
+            .links(s)
+            .filter(|l| *l.kind() == linkify::LinkKind::Url)
+            .map(|l| reqwest::Url::parse(l.as_str()).unwrap())
+            .filter(|l| l.path() == path)
+            .collect();
+        assert_eq!(links.len(), 1);
+        let mut link = links[0].clone();
+        assert_eq!(link.host_str().unwrap(), "127.0.0.1");
+        link.set_port(Some(self.port)).unwrap();
+        link
+    }
+
+    /// 直接在数据库中写入一个名为 `le guin` 的订阅者，并以相同的状态加入默认列表，绕过订阅和确认流程
+    ///
+    /// 不校验邮箱格式，用于模拟数据库中已经存在的无效地址
+    pub async fn insert_subscriber(&self, email: &str, status: SubscriptionStatus) -> uuid::Uuid {
+        let id = uuid::Uuid::new_v4();
+        new_subscription_row(id, email, "le guin", Locale::default(), status)
+            .insert(&self.db)
+            .await
+            .expect("Failed to insert subscriber.");
+        self.add_to_list(id, DEFAULT_LIST_SLUG, status).await;
+        id
+    }
+
+    /// 直接在数据库中把订阅者加入列表，绕过确认流程
+    pub async fn add_to_list(&self, subscriber_id: uuid::Uuid, slug: &str, status: SubscriptionStatus) {
+        let list = lists::Entity::find()
+            .filter(lists::Column::Slug.eq(slug))
+            .one(&self.db)
+            .await
+            .unwrap()
+            .expect("List does not exist.");
+        list_memberships::ActiveModel {
+            list_id: Set(list.list_id),
+            subscriber_id: Set(subscriber_id),
+            status: Set(status),
+            created_at: Set(chrono::Utc::now()),
+        }
+        .insert(&self.db)
+        .await
+        .expect("Failed to add subscriber to list.");
+    }
+
+    pub async fn dispatch_all_pending_emails(&self) {
+        loop {
+            if let ExecutionOutcome::EmptyQueue =
+                try_execute_task(&self.delivery_context).await.unwrap()
+            {
+                break;
+            }
+        }
+    }
+
+    /// 执行一轮调度，为到期的计划发送创建投递任务
+    pub async fn enqueue_due_newsletters(&self) -> usize {
+        enqueue_due_issues(&self.db).await.unwrap()
+    }
+
     pub async fn post_subscriptions(&self, body: String) -> reqwest::Response {
         reqwest::Client::builder()
             .no_proxy()
             .build()
             .unwrap()
-            .post(&format!("{}/subscriptions", &self.address))
+            .post(format!("{}/subscriptions", &self.address))
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .body(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_subscriptions_with_language(
+        &self,
+        body: String,
+        accept_language: &str,
+    ) -> reqwest::Response {
+        reqwest::Client::builder()
+            .no_proxy()
+            .build()
+            .unwrap()
+            .post(format!("{}/subscriptions", &self.address))
             .header("Content-Type", "application/x-www-form-urlencoded")
+            .header("Accept-Language", accept_language)
             .body(body)
             .send()
             .await
             .expect("Failed to execute request.")
     }
+
+    pub async fn post_subscriptions_json(&self, body: &serde_json::Value) -> reqwest::Response {
+        reqwest::Client::builder()
+            .no_proxy()
+            .build()
+            .unwrap()
+            .post(format!("{}/subscriptions", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_login<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.api_client
+            .post(format!("{}/login", &self.address))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_login_html(&self) -> String {
+        self.api_client
+            .get(format!("{}/login", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn get_admin_dashboard(&self) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/admin/dashboard", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_admin_dashboard_html(&self) -> String {
+        self.get_admin_dashboard().await.text().await.unwrap()
+    }
+
+    pub async fn post_logout(&self) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/admin/logout", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_publish_newsletter(&self) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/admin/newsletters", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_publish_newsletter_html(&self) -> String {
+        self.get_publish_newsletter().await.text().await.unwrap()
+    }
+
+    /// 以服务商的身份推送邮件事件，请求体用配置中的共享密钥签名
+    pub async fn post_email_event(&self, event: &serde_json::Value) -> reqwest::Response {
+        let body = serde_json::to_vec(event).unwrap();
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(self.email_events_webhook_secret.expose_secret().as_bytes())
+                .unwrap();
+        mac.update(&body);
+        let signature = hex::encode(mac.finalize().into_bytes());
+        self.post_email_event_with_signature(body, Some(&signature)).await
+    }
+
+    pub async fn post_email_event_with_signature(
+        &self,
+        body: Vec<u8>,
+        signature: Option<&str>,
+    ) -> reqwest::Response {
+        let mut request = self
+            .api_client
+            .post(format!("{}/webhooks/email-events", &self.address))
+            .header("Content-Type", "application/json")
+            .body(body);
+        if let Some(signature) = signature {
+            request = request.header("X-Webhook-Signature", signature);
+        }
+        request.send().await.expect("Failed to execute request.")
+    }
+
+    pub async fn get_newsletter_stats(&self, issue_id: uuid::Uuid) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/admin/newsletters/{}/stats", &self.address, issue_id))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_scheduled_newsletters(&self) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/admin/newsletters/scheduled", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn put_newsletter_schedule(
+        &self,
+        issue_id: uuid::Uuid,
+        body: &serde_json::Value,
+    ) -> reqwest::Response {
+        self.api_client
+            .put(format!("{}/admin/newsletters/{}/schedule", &self.address, issue_id))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn delete_newsletter_schedule(&self, issue_id: uuid::Uuid) -> reqwest::Response {
+        self.api_client
+            .delete(format!("{}/admin/newsletters/{}/schedule", &self.address, issue_id))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_admin_subscribers(&self, query: &str) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/admin/subscribers?{}", &self.address, query))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_admin_subscriber(&self, subscriber_id: uuid::Uuid) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/admin/subscribers/{}", &self.address, subscriber_id))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn delete_admin_subscriber(&self, subscriber_id: uuid::Uuid) -> reqwest::Response {
+        self.api_client
+            .delete(format!("{}/admin/subscribers/{}", &self.address, subscriber_id))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_admin_lists(&self) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/admin/lists", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_admin_lists(&self, body: &serde_json::Value) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/admin/lists", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_admin_suppressions(&self) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/admin/suppressions", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_admin_suppressions(&self, body: &serde_json::Value) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/admin/suppressions", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn delete_admin_suppression(&self, email: &str) -> reqwest::Response {
+        self.api_client
+            .delete(format!("{}/admin/suppressions/{}", &self.address, email))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_admin_subscribers_export(&self) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/admin/subscribers/export.csv", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_admin_subscribers_import(&self, csv: String, status: &str) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/admin/subscribers/import?status={}", &self.address, status))
+            .header("Content-Type", "text/csv")
+            .body(csv)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// 以测试用户的 Basic 凭证调用发布新闻邮件的 JSON 接口
+    pub async fn post_newsletters(&self, body: serde_json::Value) -> reqwest::Response {
+        reqwest::Client::builder()
+            .no_proxy()
+            .build()
+            .unwrap()
+            .post(format!("{}/newsletters", &self.address))
+            .basic_auth(&self.test_user.username, Some(&self.test_user.password))
+            .json(&body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_publish_newsletter<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.api_client
+            .post(format!("{}/admin/newsletters", &self.address))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
 }
 
 pub async fn spawn_app() -> TestApp {
+    spawn_app_with(|_| {}).await
+}
+
+/// 启动测试应用前，允许测试用例修改配置，例如调低限流阈值
+pub async fn spawn_app_with(customise: impl FnOnce(&mut Settings)) -> TestApp {
     dotenv::dotenv().ok();
     // 第一次执行会初始化Tracing，之后都会跳过
     Lazy::force(&TRACING);
@@ -53,25 +476,60 @@ pub async fn spawn_app() -> TestApp {
         let mut c = get_configuration().expect("Failed to read configuration");
         c.database.database_name = uuid::Uuid::new_v4().to_string();
         c.application.port = 0;
+        customise(&mut c);
         c
     };
 
     configure_database(&configuration.database).await;
 
-    let application = Application::build(configuration)
+    let email_transport = InMemoryEmailTransport::default();
+    let email_client = EmailClient::new(
+        configuration.email_client.sender_email.clone(),
+        Arc::new(email_transport.clone()),
+    );
+
+    let templates = EmailTemplates::new(&configuration.templates).unwrap();
+    let base_url = ApplicationBaseUrl(configuration.application.base_url.clone());
+    let hmac_secret = HmacSecret::derive(&configuration.application.hmac_secret);
+    let tracking = configuration.tracking;
+    let email_events_webhook_secret = configuration.email_events_webhook.secret.clone();
+
+    let application = Application::build_with_email_client(configuration, email_client.clone())
         .await
         .expect("Failed to build application");
 
     let port = application.port();
 
     let db = application.db();
-    let _ = tokio::spawn(application.run_until_stopped());
+    let delivery_context = DeliveryContext {
+        db: db.clone(),
+        email_client: email_client.with_suppression_list(SuppressionList::new(db.clone())),
+        templates,
+        base_url,
+        hmac_secret,
+        tracking,
+    };
+    drop(tokio::spawn(application.run_until_stopped()));
 
-    TestApp {
+    let api_client = reqwest::Client::builder()
+        .no_proxy()
+        .redirect(reqwest::redirect::Policy::none())
+        .cookie_store(true)
+        .build()
+        .unwrap();
+
+    let test_app = TestApp {
         port,
         address: format!("http://127.0.0.1:{}", port),
         db,
-    }
+        test_user: TestUser::generate(),
+        api_client,
+        delivery_context,
+        email_transport,
+        email_events_webhook_secret,
+    };
+    test_app.test_user.store(&test_app.db).await;
+    test_app
 }
 
 /// 为每次测试创建一个新的数据库，并返回该数据库的链接
@@ -87,3 +545,8 @@ pub async fn configure_database(config: &DatabaseSettings) -> DatabaseConnection
 
     db
 }
+
+pub fn assert_is_redirect_to(response: &reqwest::Response, location: &str) {
+    assert_eq!(response.status().as_u16(), 303);
+    assert_eq!(response.headers().get("Location").unwrap(), location);
+}
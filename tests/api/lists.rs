@@ -0,0 +1,268 @@
+use my_zero2prod::{
+    domain::{DEFAULT_LIST_SLUG, SubscriptionStatus},
+    entities::{issue_delivery_queue, list_memberships, lists, subscriptions},
+};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+
+use crate::helpers::{TestApp, assert_is_redirect_to, spawn_app};
+
+async fn create_list(app: &TestApp, slug: &str, name: &str) {
+    let response = app
+        .post_admin_lists(&serde_json::json!({ "slug": slug, "name": name }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+}
+
+/// 以列表标识为键返回订阅者在各个列表中的订阅状态
+async fn membership_statuses(app: &TestApp) -> Vec<(String, SubscriptionStatus)> {
+    let lists = lists::Entity::find().all(&app.db).await.unwrap();
+    let mut statuses: Vec<_> = list_memberships::Entity::find()
+        .all(&app.db)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|m| {
+            let list = lists.iter().find(|l| l.list_id == m.list_id).unwrap();
+            (list.slug.clone(), m.status)
+        })
+        .collect();
+    statuses.sort_by(|a, b| a.0.cmp(&b.0));
+    statuses
+}
+
+async fn publish(app: &TestApp, lists: &str) -> reqwest::Response {
+    app.post_publish_newsletter(&serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "lists": lists,
+        "idempotency_key": uuid::Uuid::new_v4().to_string(),
+    }))
+    .await
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_manage_lists() {
+    let app = spawn_app().await;
+
+    let list = app.get_admin_lists().await;
+    let create = app
+        .post_admin_lists(&serde_json::json!({ "slug": "weekly", "name": "Weekly" }))
+        .await;
+
+    assert_is_redirect_to(&list, "/login");
+    assert_is_redirect_to(&create, "/login");
+}
+
+#[tokio::test]
+async fn created_lists_are_listed_after_the_default_list() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    create_list(&app, "release-notes", "Release Notes").await;
+    let lists: serde_json::Value = app.get_admin_lists().await.json().await.unwrap();
+
+    let slugs: Vec<_> = lists
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|l| l["slug"].as_str().unwrap())
+        .collect();
+    assert_eq!(slugs, [DEFAULT_LIST_SLUG, "release-notes"]);
+    assert_eq!(lists[1]["name"], "Release Notes");
+}
+
+#[tokio::test]
+async fn invalid_or_duplicate_lists_are_rejected() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let test_cases = [
+        (serde_json::json!({ "slug": "Release Notes", "name": "Release Notes" }), 400),
+        (serde_json::json!({ "slug": "release-notes", "name": "  " }), 400),
+        (serde_json::json!({ "slug": DEFAULT_LIST_SLUG, "name": "Another" }), 409),
+    ];
+
+    for (body, expected_status) in test_cases {
+        let response = app.post_admin_lists(&body).await;
+
+        assert_eq!(response.status().as_u16(), expected_status, "{}", body);
+    }
+}
+
+#[tokio::test]
+async fn subscribing_to_a_list_mentions_it_in_the_confirmation_email() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    create_list(&app, "release-notes", "Release Notes").await;
+
+    let response = app
+        .post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com&list=release-notes".into())
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let email = &app.sent_emails()[0];
+    assert!(email.html_content.contains("Release Notes"));
+    assert!(email.text_content.contains("Release Notes"));
+    assert_eq!(
+        membership_statuses(&app).await,
+        [("release-notes".to_string(), SubscriptionStatus::PendingConfirmation)]
+    );
+}
+
+#[tokio::test]
+async fn subscribing_to_an_unknown_list_is_rejected() {
+    let app = spawn_app().await;
+
+    let response = app
+        .post_subscriptions_json(&serde_json::json!({
+            "name": "le guin",
+            "email": "ursula_le_guin@gmail.com",
+            "locale": "en",
+            "list": "does-not-exist",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["errors"][0]["field"], "list");
+    assert_eq!(body["message"], "The mailing list does-not-exist does not exist.");
+    assert!(app.sent_emails().is_empty());
+}
+
+#[tokio::test]
+async fn each_list_is_confirmed_independently() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    create_list(&app, "release-notes", "Release Notes").await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+    app.post_subscriptions(body.into()).await;
+    let newsletter_link = app.get_confirmation_links(&app.sent_emails()[0]).html;
+    reqwest::get(newsletter_link).await.unwrap().error_for_status().unwrap();
+
+    // 邮箱已经确认过，加入新列表时仍然需要确认
+    app.post_subscriptions(format!("{}&list=release-notes", body)).await;
+
+    assert_eq!(
+        membership_statuses(&app).await,
+        [
+            (DEFAULT_LIST_SLUG.to_string(), SubscriptionStatus::Confirmed),
+            ("release-notes".to_string(), SubscriptionStatus::PendingConfirmation),
+        ]
+    );
+    let release_notes_email = app.sent_emails().pop().unwrap();
+    assert!(release_notes_email.text_content.contains("Release Notes"));
+    let release_notes_link = app.get_confirmation_links(&release_notes_email).html;
+    reqwest::get(release_notes_link).await.unwrap().error_for_status().unwrap();
+
+    assert_eq!(
+        membership_statuses(&app).await,
+        [
+            (DEFAULT_LIST_SLUG.to_string(), SubscriptionStatus::Confirmed),
+            ("release-notes".to_string(), SubscriptionStatus::Confirmed),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn publishing_to_several_lists_delivers_one_email_per_subscriber() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    create_list(&app, "release-notes", "Release Notes").await;
+    create_list(&app, "events", "Events").await;
+    // 每个订阅者都已确认订阅默认列表，本次只发送到另外两个列表
+    let both = app.insert_subscriber("both@gmail.com", SubscriptionStatus::Confirmed).await;
+    app.add_to_list(both, "release-notes", SubscriptionStatus::Confirmed).await;
+    app.add_to_list(both, "events", SubscriptionStatus::Confirmed).await;
+    let pending = app.insert_subscriber("pending@gmail.com", SubscriptionStatus::Confirmed).await;
+    app.add_to_list(pending, "release-notes", SubscriptionStatus::PendingConfirmation).await;
+    app.insert_subscriber("newsletter-only@gmail.com", SubscriptionStatus::Confirmed).await;
+
+    let response = publish(&app, "release-notes, events").await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+
+    let recipients: Vec<_> = issue_delivery_queue::Entity::find()
+        .all(&app.db)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|task| task.subscriber_email)
+        .collect();
+    assert_eq!(recipients, ["both@gmail.com"]);
+    app.dispatch_all_pending_emails().await;
+    assert_eq!(app.sent_emails().len(), 1);
+}
+
+#[tokio::test]
+async fn publishing_to_an_unknown_list_is_rejected() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    let response = publish(&app, "newsletter,does-not-exist").await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    assert!(issue_delivery_queue::Entity::find().all(&app.db).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn unsubscribing_leaves_every_list() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    create_list(&app, "release-notes", "Release Notes").await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+    app.post_subscriptions(body.into()).await;
+    app.post_subscriptions(format!("{}&list=release-notes", body)).await;
+    let unsubscribe_link = app.get_unsubscribe_link(&app.sent_emails()[0]);
+    let token = unsubscribe_link
+        .query_pairs()
+        .find(|(key, _)| key == "token")
+        .map(|(_, value)| value.into_owned())
+        .unwrap();
+
+    app.api_client
+        .post(format!("{}/subscriptions/unsubscribe", &app.address))
+        .form(&[("token", token)])
+        .send()
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+
+    assert_eq!(
+        membership_statuses(&app).await,
+        [
+            (DEFAULT_LIST_SLUG.to_string(), SubscriptionStatus::Unsubscribed),
+            ("release-notes".to_string(), SubscriptionStatus::Unsubscribed),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn imported_subscribers_join_the_requested_list() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    create_list(&app, "release-notes", "Release Notes").await;
+
+    let response = app
+        .api_client
+        .post(format!(
+            "{}/admin/subscribers/import?status=confirmed&list=release-notes",
+            &app.address
+        ))
+        .header("Content-Type", "text/csv")
+        .body("email,name\nursula@gmail.com,Ursula Le Guin\n")
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        membership_statuses(&app).await,
+        [("release-notes".to_string(), SubscriptionStatus::Confirmed)]
+    );
+    let subscriber = subscriptions::Entity::find()
+        .filter(subscriptions::Column::Email.eq("ursula@gmail.com"))
+        .one(&app.db)
+        .await
+        .unwrap();
+    assert!(subscriber.is_some());
+}
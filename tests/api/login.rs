@@ -0,0 +1,64 @@
+use crate::helpers::{assert_is_redirect_to, spawn_app};
+
+#[tokio::test]
+async fn an_error_flash_message_is_set_on_failure() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act - Part 1 - Try to login
+    let login_body = serde_json::json!({
+        "username": "random-username",
+        "password": "random-password"
+    });
+    let response = app.post_login(&login_body).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+
+    // Act - Part 2 - Follow the redirect
+    let html_page = app.get_login_html().await;
+    assert!(html_page.contains("<p><i>用户名或密码错误</i></p>"));
+
+    // Act - Part 3 - Reload the login page
+    let html_page = app.get_login_html().await;
+    assert!(!html_page.contains("用户名或密码错误"));
+}
+
+#[tokio::test]
+async fn redirect_to_admin_dashboard_after_login_success() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act - Part 1 - Login
+    let login_body = serde_json::json!({
+        "username": &app.test_user.username,
+        "password": &app.test_user.password
+    });
+    let response = app.post_login(&login_body).await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+
+    // Act - Part 2 - Follow the redirect
+    let html_page = app.get_admin_dashboard_html().await;
+    assert!(html_page.contains(&format!("欢迎 {}", app.test_user.username)));
+}
+
+#[tokio::test]
+async fn the_session_cookie_is_not_readable_by_the_client() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let login_body = serde_json::json!({
+        "username": &app.test_user.username,
+        "password": &app.test_user.password
+    });
+    let response = app.post_login(&login_body).await;
+
+    // Assert
+    let session_cookie = response
+        .cookies()
+        .find(|c| c.name() == "id")
+        .expect("No session cookie was set.");
+    assert!(session_cookie.http_only());
+    assert!(!session_cookie.value().contains(&app.test_user.user_id.to_string()));
+}
@@ -1,4 +1,15 @@
+mod admin_dashboard;
+mod admin_subscribers;
+mod email_events_webhook;
 mod helpers;
 mod health_check;
+mod lists;
+mod login;
+mod newsletter_schedule;
+mod newsletters;
+mod rate_limit;
 mod subscriptions;
 mod subscription_confirm;
+mod suppressions;
+mod tracking;
+mod unsubscribe;
@@ -0,0 +1,261 @@
+use chrono::{Duration, Utc};
+use my_zero2prod::{
+    domain::{IssueStatus, SubscriptionStatus},
+    entities::{issue_delivery_queue, newsletter_issues},
+    newsletter_scheduler::SCHEDULER_LOCK_ID,
+};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::Set, ConnectionTrait, DbBackend, EntityTrait, PaginatorTrait,
+    Statement, TransactionTrait,
+};
+
+use crate::helpers::{TestApp, assert_is_redirect_to, spawn_app};
+
+/// 发布一封计划发送的新闻邮件，返回其 ID
+async fn schedule_newsletter(app: &TestApp, scheduled_at: chrono::DateTime<Utc>) -> uuid::Uuid {
+    let response = app
+        .post_publish_newsletter(&serde_json::json!({
+            "title": "Newsletter title",
+            "text_content": "Newsletter body as plain text",
+            "html_content": "<p>Newsletter body as HTML</p>",
+            "scheduled_at": scheduled_at.to_rfc3339(),
+            "idempotency_key": uuid::Uuid::new_v4().to_string(),
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    newsletter_issues::Entity::find()
+        .one(&app.db)
+        .await
+        .unwrap()
+        .unwrap()
+        .newsletter_issue_id
+}
+
+async fn saved_issue(app: &TestApp, issue_id: uuid::Uuid) -> newsletter_issues::Model {
+    newsletter_issues::Entity::find_by_id(issue_id)
+        .one(&app.db)
+        .await
+        .unwrap()
+        .unwrap()
+}
+
+/// 把计划发送时间改到过去，模拟时间已经到达
+async fn make_due(app: &TestApp, issue_id: uuid::Uuid) {
+    let mut issue: newsletter_issues::ActiveModel = saved_issue(app, issue_id).await.into();
+    issue.scheduled_at = Set(Some(Utc::now() - Duration::seconds(1)));
+    issue.update(&app.db).await.unwrap();
+}
+
+async fn n_tasks(app: &TestApp) -> u64 {
+    issue_delivery_queue::Entity::find()
+        .count(&app.db)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn a_scheduled_newsletter_is_not_delivered_before_its_time() {
+    let app = spawn_app().await;
+    app.insert_subscriber("ursula_le_guin@gmail.com", SubscriptionStatus::Confirmed).await;
+    app.test_user.login(&app).await;
+
+    let issue_id = schedule_newsletter(&app, Utc::now() + Duration::hours(1)).await;
+    app.enqueue_due_newsletters().await;
+
+    assert_eq!(n_tasks(&app).await, 0);
+    assert_eq!(
+        saved_issue(&app, issue_id).await.status,
+        IssueStatus::Scheduled
+    );
+    let html_page = app.get_publish_newsletter_html().await;
+    assert!(html_page.contains("新闻邮件已计划在"));
+}
+
+#[tokio::test]
+async fn a_due_newsletter_is_enqueued_exactly_once() {
+    let app = spawn_app().await;
+    app.insert_subscriber("ursula_le_guin@gmail.com", SubscriptionStatus::Confirmed).await;
+    app.test_user.login(&app).await;
+    let issue_id = schedule_newsletter(&app, Utc::now() + Duration::hours(1)).await;
+    make_due(&app, issue_id).await;
+
+    app.enqueue_due_newsletters().await;
+    app.enqueue_due_newsletters().await;
+
+    assert_eq!(n_tasks(&app).await, 1);
+    assert_eq!(
+        saved_issue(&app, issue_id).await.status,
+        IssueStatus::Enqueued
+    );
+    app.dispatch_all_pending_emails().await;
+    assert_eq!(app.sent_emails().len(), 1);
+}
+
+#[tokio::test]
+async fn concurrent_schedulers_do_not_enqueue_twice() {
+    let app = spawn_app().await;
+    app.insert_subscriber("ursula_le_guin@gmail.com", SubscriptionStatus::Confirmed).await;
+    app.test_user.login(&app).await;
+    let issue_id = schedule_newsletter(&app, Utc::now() + Duration::hours(1)).await;
+    make_due(&app, issue_id).await;
+
+    let (first, second) =
+        tokio::join!(app.enqueue_due_newsletters(), app.enqueue_due_newsletters());
+
+    assert!(first + second <= 1);
+    app.enqueue_due_newsletters().await;
+    assert_eq!(n_tasks(&app).await, 1);
+}
+
+#[tokio::test]
+async fn the_scheduler_skips_a_round_while_another_instance_holds_the_lock() {
+    let app = spawn_app().await;
+    app.insert_subscriber("ursula_le_guin@gmail.com", SubscriptionStatus::Confirmed).await;
+    app.test_user.login(&app).await;
+    let issue_id = schedule_newsletter(&app, Utc::now() + Duration::hours(1)).await;
+    make_due(&app, issue_id).await;
+    let other_instance = app.db.begin().await.unwrap();
+    other_instance
+        .execute(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            "SELECT pg_advisory_xact_lock($1)",
+            [SCHEDULER_LOCK_ID.into()],
+        ))
+        .await
+        .unwrap();
+
+    assert_eq!(app.enqueue_due_newsletters().await, 0);
+    assert_eq!(n_tasks(&app).await, 0);
+
+    other_instance.rollback().await.unwrap();
+    app.enqueue_due_newsletters().await;
+    assert_eq!(n_tasks(&app).await, 1);
+}
+
+#[tokio::test]
+async fn a_scheduled_newsletter_can_be_rescheduled() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let issue_id = schedule_newsletter(&app, Utc::now() + Duration::hours(1)).await;
+    let new_time = (Utc::now() + Duration::days(3)).to_rfc3339();
+
+    let response = app
+        .put_newsletter_schedule(issue_id, &serde_json::json!({ "scheduled_at": new_time }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["status"], "scheduled");
+    let scheduled: serde_json::Value = app.get_scheduled_newsletters().await.json().await.unwrap();
+    assert_eq!(scheduled[0]["id"], issue_id.to_string());
+    let saved_time = saved_issue(&app, issue_id).await.scheduled_at.unwrap();
+    assert_eq!(
+        saved_time.timestamp(),
+        chrono::DateTime::parse_from_rfc3339(&new_time)
+            .unwrap()
+            .timestamp()
+    );
+}
+
+#[tokio::test]
+async fn a_cancelled_newsletter_is_never_sent() {
+    let app = spawn_app().await;
+    app.insert_subscriber("ursula_le_guin@gmail.com", SubscriptionStatus::Confirmed).await;
+    app.test_user.login(&app).await;
+    let issue_id = schedule_newsletter(&app, Utc::now() + Duration::hours(1)).await;
+
+    let response = app.delete_newsletter_schedule(issue_id).await;
+    assert_eq!(response.status().as_u16(), 204);
+    make_due(&app, issue_id).await;
+    app.enqueue_due_newsletters().await;
+
+    assert_eq!(n_tasks(&app).await, 0);
+    assert_eq!(
+        saved_issue(&app, issue_id).await.status,
+        IssueStatus::Cancelled
+    );
+    let scheduled: serde_json::Value = app.get_scheduled_newsletters().await.json().await.unwrap();
+    assert!(scheduled.as_array().unwrap().is_empty());
+    let reschedule = app
+        .put_newsletter_schedule(issue_id, &serde_json::json!({ "scheduled_at": Utc::now() }))
+        .await;
+    assert_eq!(reschedule.status().as_u16(), 409);
+}
+
+#[tokio::test]
+async fn a_newsletter_cannot_be_changed_once_sending_has_started() {
+    let app = spawn_app().await;
+    app.insert_subscriber("ursula_le_guin@gmail.com", SubscriptionStatus::Confirmed).await;
+    app.test_user.login(&app).await;
+    let issue_id = schedule_newsletter(&app, Utc::now() + Duration::hours(1)).await;
+    make_due(&app, issue_id).await;
+    app.enqueue_due_newsletters().await;
+
+    let reschedule = app
+        .put_newsletter_schedule(
+            issue_id,
+            &serde_json::json!({ "scheduled_at": Utc::now() + Duration::hours(1) }),
+        )
+        .await;
+    let cancel = app.delete_newsletter_schedule(issue_id).await;
+
+    assert_eq!(reschedule.status().as_u16(), 409);
+    assert_eq!(cancel.status().as_u16(), 409);
+    assert_eq!(n_tasks(&app).await, 1);
+}
+
+#[tokio::test]
+async fn unknown_newsletters_cannot_be_rescheduled_or_cancelled() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let issue_id = uuid::Uuid::new_v4();
+
+    let reschedule = app
+        .put_newsletter_schedule(issue_id, &serde_json::json!({ "scheduled_at": Utc::now() }))
+        .await;
+    let cancel = app.delete_newsletter_schedule(issue_id).await;
+
+    assert_eq!(reschedule.status().as_u16(), 404);
+    assert_eq!(cancel.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn an_invalid_schedule_is_rejected() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    let response = app
+        .post_publish_newsletter(&serde_json::json!({
+            "title": "Newsletter title",
+            "text_content": "Newsletter body as plain text",
+            "html_content": "<p>Newsletter body as HTML</p>",
+            "scheduled_at": "next monday",
+            "idempotency_key": uuid::Uuid::new_v4().to_string(),
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(
+        newsletter_issues::Entity::find()
+            .count(&app.db)
+            .await
+            .unwrap(),
+        0
+    );
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_manage_scheduled_newsletters() {
+    let app = spawn_app().await;
+    let issue_id = uuid::Uuid::new_v4();
+
+    let list = app.get_scheduled_newsletters().await;
+    let reschedule = app
+        .put_newsletter_schedule(issue_id, &serde_json::json!({ "scheduled_at": Utc::now() }))
+        .await;
+    let cancel = app.delete_newsletter_schedule(issue_id).await;
+
+    assert_is_redirect_to(&list, "/login");
+    assert_is_redirect_to(&reschedule, "/login");
+    assert_is_redirect_to(&cancel, "/login");
+}
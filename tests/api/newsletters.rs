@@ -0,0 +1,489 @@
+use my_zero2prod::{
+    domain::SubscriptionStatus,
+    entities::{idempotency, issue_delivery_queue},
+};
+use sea_orm::{EntityTrait, PaginatorTrait};
+
+use crate::helpers::{assert_is_redirect_to, spawn_app};
+
+fn newsletter_request_body() -> serde_json::Value {
+    serde_json::json!({
+        "title": "Newsletter title",
+        "content": {
+            "text": "Newsletter body as plain text",
+            "html": "<p>Newsletter body as HTML</p>",
+        }
+    })
+}
+
+/// 插入一个订阅者，并以相同的状态加入默认列表
+#[tokio::test]
+async fn newsletters_are_not_delivered_to_unconfirmed_subscribers() {
+    // Arrange
+    let app = spawn_app().await;
+    app.insert_subscriber("ursula_le_guin@gmail.com", SubscriptionStatus::PendingConfirmation).await;
+
+    // Act
+    let response = app.post_newsletters(newsletter_request_body()).await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(app.sent_emails().is_empty());
+}
+
+#[tokio::test]
+async fn newsletters_published_through_the_api_are_delivered_to_confirmed_subscribers() {
+    // Arrange
+    let app = spawn_app().await;
+    app.insert_subscriber("ursula_le_guin@gmail.com", SubscriptionStatus::Confirmed).await;
+
+    // Act
+    let response = app.post_newsletters(newsletter_request_body()).await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let sent_emails = app.sent_emails();
+    assert_eq!(sent_emails.len(), 1);
+    assert_eq!(sent_emails[0].to, "ursula_le_guin@gmail.com");
+    assert_eq!(sent_emails[0].subject, "Newsletter title");
+}
+
+#[tokio::test]
+async fn confirmed_subscribers_with_invalid_stored_emails_are_skipped() {
+    // Arrange
+    let app = spawn_app().await;
+    app.insert_subscriber("not-an-email", SubscriptionStatus::Confirmed).await;
+
+    // Act
+    let response = app.post_newsletters(newsletter_request_body()).await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let n_tasks = issue_delivery_queue::Entity::find().count(&app.db).await.unwrap();
+    assert_eq!(n_tasks, 0);
+}
+
+#[tokio::test]
+async fn newsletters_returns_422_for_invalid_data() {
+    // Arrange
+    let app = spawn_app().await;
+    let test_cases = vec![
+        (
+            serde_json::json!({
+                "content": {
+                    "text": "Newsletter body as plain text",
+                    "html": "<p>Newsletter body as HTML</p>",
+                }
+            }),
+            "missing title",
+        ),
+        (
+            serde_json::json!({"title": "Newsletter!"}),
+            "missing content",
+        ),
+    ];
+
+    for (invalid_body, error_message) in test_cases {
+        // Act
+        let response = app.post_newsletters(invalid_body).await;
+
+        // Assert
+        assert_eq!(
+            422,
+            response.status().as_u16(),
+            "The API did not fail with 422 Unprocessable Entity when the payload was {}.",
+            error_message
+        );
+    }
+}
+
+#[tokio::test]
+async fn requests_missing_authorization_are_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = reqwest::Client::builder()
+        .no_proxy()
+        .build()
+        .unwrap()
+        .post(format!("{}/newsletters", &app.address))
+        .json(&newsletter_request_body())
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(401, response.status().as_u16());
+    assert_eq!(
+        r#"Basic realm="publish""#,
+        response.headers()["WWW-Authenticate"]
+    );
+}
+
+#[tokio::test]
+async fn non_existing_user_is_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    let username = uuid::Uuid::new_v4().to_string();
+    let password = uuid::Uuid::new_v4().to_string();
+
+    // Act
+    let response = reqwest::Client::builder()
+        .no_proxy()
+        .build()
+        .unwrap()
+        .post(format!("{}/newsletters", &app.address))
+        .basic_auth(username, Some(password))
+        .json(&newsletter_request_body())
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(401, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn invalid_password_is_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    let password = uuid::Uuid::new_v4().to_string();
+    assert_ne!(app.test_user.password, password);
+
+    // Act
+    let response = reqwest::Client::builder()
+        .no_proxy()
+        .build()
+        .unwrap()
+        .post(format!("{}/newsletters", &app.address))
+        .basic_auth(&app.test_user.username, Some(password))
+        .json(&newsletter_request_body())
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(401, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn the_api_does_not_publish_twice_with_the_same_idempotency_key() {
+    // Arrange
+    let app = spawn_app().await;
+    app.insert_subscriber("ursula_le_guin@gmail.com", SubscriptionStatus::Confirmed).await;
+    let idempotency_key = uuid::Uuid::new_v4().to_string();
+
+    // Act
+    for _ in 0..2 {
+        let response = reqwest::Client::builder()
+            .no_proxy()
+            .build()
+            .unwrap()
+            .post(format!("{}/newsletters", &app.address))
+            .basic_auth(&app.test_user.username, Some(&app.test_user.password))
+            .header("Idempotency-Key", &idempotency_key)
+            .json(&newsletter_request_body())
+            .send()
+            .await
+            .expect("Failed to execute request.");
+        assert_eq!(response.status().as_u16(), 200);
+    }
+
+    // Assert
+    let n_tasks = issue_delivery_queue::Entity::find().count(&app.db).await.unwrap();
+    assert_eq!(n_tasks, 1);
+}
+
+#[tokio::test]
+async fn newsletters_are_not_delivered_to_unconfirmed_subscribers_via_the_admin_form() {
+    // Arrange
+    let app = spawn_app().await;
+    app.insert_subscriber("ursula_le_guin@gmail.com", SubscriptionStatus::PendingConfirmation).await;
+    app.test_user.login(&app).await;
+
+    // Act - Part 1 - Submit newsletter form
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": uuid::Uuid::new_v4().to_string(),
+    });
+    let response = app.post_publish_newsletter(&newsletter_request_body).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+
+    // Act - Part 2 - Follow the redirect
+    let html_page = app.get_publish_newsletter_html().await;
+    assert!(html_page.contains("<p><i>新闻邮件已发布, 邮件将在后台陆续发送!</i></p>"));
+
+    // Assert
+    let n_tasks = issue_delivery_queue::Entity::find().count(&app.db).await.unwrap();
+    assert_eq!(n_tasks, 0);
+    app.dispatch_all_pending_emails().await;
+    assert!(app.sent_emails().is_empty());
+}
+
+#[tokio::test]
+async fn newsletters_are_delivered_to_confirmed_subscribers() {
+    // Arrange
+    let app = spawn_app().await;
+    app.insert_subscriber("ursula_le_guin@gmail.com", SubscriptionStatus::Confirmed).await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": uuid::Uuid::new_v4().to_string(),
+    });
+    let response = app.post_publish_newsletter(&newsletter_request_body).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let sent_emails = app.sent_emails();
+    assert_eq!(sent_emails.len(), 1);
+    assert_eq!(sent_emails[0].to, "ursula_le_guin@gmail.com");
+    assert_eq!(sent_emails[0].subject, "Newsletter title");
+    assert!(sent_emails[0].text_content.starts_with("Newsletter body as plain text"));
+    assert!(sent_emails[0].html_content.contains("<p>Newsletter body as HTML</p>"));
+    app.get_unsubscribe_link(&sent_emails[0]);
+}
+
+#[tokio::test]
+async fn publishing_enqueues_one_delivery_task_per_confirmed_subscriber() {
+    // Arrange
+    let app = spawn_app().await;
+    app.insert_subscriber("pending@gmail.com", SubscriptionStatus::PendingConfirmation).await;
+    app.insert_subscriber("confirmed-1@gmail.com", SubscriptionStatus::Confirmed).await;
+    app.insert_subscriber("confirmed-2@gmail.com", SubscriptionStatus::Confirmed).await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": uuid::Uuid::new_v4().to_string(),
+    });
+    let response = app.post_publish_newsletter(&newsletter_request_body).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+
+    // Assert
+    let mut recipients: Vec<String> = issue_delivery_queue::Entity::find()
+        .all(&app.db)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|task| task.subscriber_email)
+        .collect();
+    recipients.sort();
+    assert_eq!(recipients, vec!["confirmed-1@gmail.com", "confirmed-2@gmail.com"]);
+}
+
+#[tokio::test]
+async fn confirmed_subscribers_with_invalid_stored_emails_are_skipped_via_the_admin_form() {
+    // Arrange
+    let app = spawn_app().await;
+    app.insert_subscriber("not-an-email", SubscriptionStatus::Confirmed).await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": uuid::Uuid::new_v4().to_string(),
+    });
+    let response = app.post_publish_newsletter(&newsletter_request_body).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let n_tasks = issue_delivery_queue::Entity::find().count(&app.db).await.unwrap();
+    assert_eq!(n_tasks, 0);
+}
+
+#[tokio::test]
+async fn newsletters_returns_422_for_invalid_data_via_the_admin_form() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let test_cases = vec![
+        (
+            serde_json::json!({
+                "text_content": "Newsletter body as plain text",
+                "html_content": "<p>Newsletter body as HTML</p>",
+                "idempotency_key": uuid::Uuid::new_v4().to_string(),
+            }),
+            "missing title",
+        ),
+        (
+            serde_json::json!({
+                "title": "Newsletter!",
+                "idempotency_key": uuid::Uuid::new_v4().to_string(),
+            }),
+            "missing content",
+        ),
+    ];
+
+    for (invalid_body, error_message) in test_cases {
+        // Act
+        let response = app.post_publish_newsletter(&invalid_body).await;
+
+        // Assert
+        assert_eq!(
+            422,
+            response.status().as_u16(),
+            "The API did not fail with 422 Unprocessable Entity when the payload was {}.",
+            error_message
+        );
+    }
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_see_the_newsletter_form() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.get_publish_newsletter().await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_publish_a_newsletter() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": uuid::Uuid::new_v4().to_string(),
+    });
+    let response = app.post_publish_newsletter(&newsletter_request_body).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn newsletter_creation_is_idempotent() {
+    // Arrange
+    let app = spawn_app().await;
+    app.insert_subscriber("not-an-email", SubscriptionStatus::Confirmed).await;
+    app.test_user.login(&app).await;
+
+    // Act - Part 1 - Submit newsletter form
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": uuid::Uuid::new_v4().to_string(),
+    });
+    let response = app.post_publish_newsletter(&newsletter_request_body).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+
+    // Act - Part 2 - Follow the redirect
+    let html_page = app.get_publish_newsletter_html().await;
+    assert!(html_page.contains("<p><i>新闻邮件已发布, 邮件将在后台陆续发送!</i></p>"));
+
+    // Act - Part 3 - Submit newsletter form **again**
+    let response = app.post_publish_newsletter(&newsletter_request_body).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+
+    // Act - Part 4 - Follow the redirect
+    let html_page = app.get_publish_newsletter_html().await;
+    assert!(html_page.contains("<p><i>新闻邮件已发布, 邮件将在后台陆续发送!</i></p>"));
+
+    // Assert
+    let saved = idempotency::Entity::find().all(&app.db).await.unwrap();
+    assert_eq!(saved.len(), 1);
+    assert_eq!(saved[0].response_status_code, Some(303));
+    let n_tasks = issue_delivery_queue::Entity::find().count(&app.db).await.unwrap();
+    assert_eq!(n_tasks, 1);
+}
+
+#[tokio::test]
+async fn the_idempotency_key_can_be_sent_as_a_header() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let idempotency_key = uuid::Uuid::new_v4().to_string();
+
+    // Act
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+    });
+    let response = app
+        .api_client
+        .post(format!("{}/admin/newsletters", &app.address))
+        .header("Idempotency-Key", &idempotency_key)
+        .form(&newsletter_request_body)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    let saved = idempotency::Entity::find().one(&app.db).await.unwrap().unwrap();
+    assert_eq!(saved.idempotency_key, idempotency_key);
+    assert_eq!(saved.user_id, app.test_user.user_id);
+}
+
+#[tokio::test]
+async fn requests_without_an_idempotency_key_are_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+    });
+    let response = app.post_publish_newsletter(&newsletter_request_body).await;
+
+    // Assert
+    assert_eq!(400, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn concurrent_form_submission_is_handled_gracefully() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act - Submit two newsletter forms concurrently
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": uuid::Uuid::new_v4().to_string(),
+    });
+    let response1 = app.post_publish_newsletter(&newsletter_request_body);
+    let response2 = app.post_publish_newsletter(&newsletter_request_body);
+    let (response1, response2) = tokio::join!(response1, response2);
+
+    // Assert
+    assert_eq!(response1.status(), response2.status());
+    assert_eq!(
+        response1.headers().get("Location"),
+        response2.headers().get("Location")
+    );
+    assert_eq!(
+        response1.text().await.unwrap(),
+        response2.text().await.unwrap()
+    );
+}
@@ -0,0 +1,196 @@
+use std::time::Duration;
+
+use chrono::SubsecRound;
+use my_zero2prod::{
+    configuration::RateLimitStoreKind,
+    entities::rate_limit_buckets,
+    rate_limit::{PostgresRateLimitStore, RateLimit, RateLimitStore},
+};
+use sea_orm::{ActiveModelTrait, ActiveValue::Set, EntityTrait, PaginatorTrait};
+
+use crate::helpers::{TestApp, spawn_app_with};
+
+fn subscription_body(n: usize) -> String {
+    format!("name=le%20guin&email=ursula_le_guin_{}%40gmail.com", n)
+}
+
+async fn post_subscriptions_from(app: &TestApp, body: String, forwarded_for: &str) -> reqwest::Response {
+    reqwest::Client::builder()
+        .no_proxy()
+        .build()
+        .unwrap()
+        .post(format!("{}/subscriptions", &app.address))
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .header("X-Forwarded-For", forwarded_for)
+        .body(body)
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+#[tokio::test]
+async fn requests_over_the_per_ip_limit_are_rejected_with_a_429() {
+    let app = spawn_app_with(|c| {
+        c.rate_limit.per_ip_capacity = 2;
+        c.rate_limit.per_ip_period_seconds = 3600;
+    })
+    .await;
+
+    for n in 0..2 {
+        let response = app.post_subscriptions(subscription_body(n)).await;
+        assert_eq!(response.status().as_u16(), 200);
+    }
+    let response = app.post_subscriptions(subscription_body(2)).await;
+
+    assert_eq!(response.status().as_u16(), 429);
+    let retry_after: u64 = response.headers()["Retry-After"]
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!(retry_after > 0 && retry_after <= 1800);
+    assert_eq!(app.sent_emails().len(), 2);
+}
+
+#[tokio::test]
+async fn confirmation_emails_to_the_same_address_are_capped() {
+    let app = spawn_app_with(|c| {
+        c.rate_limit.per_email_capacity = 2;
+        c.rate_limit.per_email_period_seconds = 3600;
+    })
+    .await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    for _ in 0..2 {
+        let response = app.post_subscriptions(body.into()).await;
+        assert_eq!(response.status().as_u16(), 200);
+    }
+    // 地址大小写不同也算作同一个邮箱
+    let response = app
+        .post_subscriptions("name=le%20guin&email=Ursula_Le_Guin%40gmail.com".into())
+        .await;
+
+    assert_eq!(response.status().as_u16(), 429);
+    assert!(response.headers().contains_key("Retry-After"));
+    assert_eq!(app.sent_emails().len(), 2);
+}
+
+#[tokio::test]
+async fn the_429_body_is_localised() {
+    let app = spawn_app_with(|c| c.rate_limit.per_ip_capacity = 1).await;
+    app.post_subscriptions(subscription_body(0)).await;
+
+    let response = app
+        .post_subscriptions_with_language(subscription_body(1), "en")
+        .await;
+
+    assert_eq!(response.status().as_u16(), 429);
+    assert!(response.text().await.unwrap().starts_with("Too many requests."));
+}
+
+#[tokio::test]
+async fn forwarded_client_ips_are_limited_separately_when_trusted() {
+    let app = spawn_app_with(|c| {
+        c.rate_limit.per_ip_capacity = 1;
+        c.rate_limit.trust_forwarded_for = true;
+    })
+    .await;
+
+    let first = post_subscriptions_from(&app, subscription_body(0), "203.0.113.1").await;
+    let second = post_subscriptions_from(&app, subscription_body(1), "203.0.113.2").await;
+    // 客户端自己填写的前几项不可信，只看代理追加的最后一项
+    let third = post_subscriptions_from(&app, subscription_body(2), "198.51.100.7, 203.0.113.1").await;
+
+    assert_eq!(first.status().as_u16(), 200);
+    assert_eq!(second.status().as_u16(), 200);
+    assert_eq!(third.status().as_u16(), 429);
+}
+
+#[tokio::test]
+async fn forwarded_for_is_ignored_unless_trusted() {
+    let app = spawn_app_with(|c| c.rate_limit.per_ip_capacity = 1).await;
+
+    post_subscriptions_from(&app, subscription_body(0), "203.0.113.1").await;
+    let response = post_subscriptions_from(&app, subscription_body(1), "203.0.113.2").await;
+
+    assert_eq!(response.status().as_u16(), 429);
+}
+
+#[tokio::test]
+async fn the_postgres_store_shares_limits_through_the_database() {
+    let app = spawn_app_with(|c| {
+        c.rate_limit.kind = RateLimitStoreKind::Postgres;
+        c.rate_limit.per_ip_capacity = 1;
+    })
+    .await;
+
+    let first = app.post_subscriptions(subscription_body(0)).await;
+    let second = app.post_subscriptions(subscription_body(1)).await;
+
+    assert_eq!(first.status().as_u16(), 200);
+    assert_eq!(second.status().as_u16(), 429);
+    // 一个 IP 桶和一个邮箱桶
+    let n_buckets = rate_limit_buckets::Entity::find().count(&app.db).await.unwrap();
+    assert_eq!(n_buckets, 2);
+}
+
+#[tokio::test]
+async fn a_bucket_written_with_a_later_clock_is_not_refilled_or_moved_back() {
+    let app = spawn_app_with(|c| c.rate_limit.kind = RateLimitStoreKind::Postgres).await;
+    let store = PostgresRateLimitStore::new(app.db.clone());
+    let limit = RateLimit {
+        capacity: 1,
+        period: Duration::from_secs(60),
+    };
+    // 另一个实例的时钟快了一小时，刚刚取走了最后一个令牌；Postgres 只保存到微秒
+    let ahead = (chrono::Utc::now() + chrono::Duration::hours(1)).trunc_subsecs(6);
+    rate_limit_buckets::ActiveModel {
+        key: Set("skewed".into()),
+        tokens: Set(0.0),
+        updated_at: Set(ahead),
+    }
+    .insert(&app.db)
+    .await
+    .unwrap();
+
+    let outcome = store.acquire("skewed", &limit).await.unwrap();
+
+    assert!(outcome.is_err());
+    let bucket = rate_limit_buckets::Entity::find_by_id("skewed")
+        .one(&app.db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(bucket.updated_at, ahead);
+}
+
+#[tokio::test]
+async fn idle_buckets_are_deleted_by_the_database_clock() {
+    let app = spawn_app_with(|c| c.rate_limit.kind = RateLimitStoreKind::Postgres).await;
+    let store = PostgresRateLimitStore::new(app.db.clone());
+    let now = chrono::Utc::now();
+    for (key, updated_at) in [
+        ("idle", now - chrono::Duration::hours(2)),
+        ("recent", now),
+    ] {
+        rate_limit_buckets::ActiveModel {
+            key: Set(key.into()),
+            tokens: Set(0.0),
+            updated_at: Set(updated_at),
+        }
+        .insert(&app.db)
+        .await
+        .unwrap();
+    }
+
+    store.delete_idle(Duration::from_secs(3600)).await.unwrap();
+
+    let keys: Vec<_> = rate_limit_buckets::Entity::find()
+        .all(&app.db)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|bucket| bucket.key)
+        .collect();
+    assert_eq!(keys, ["recent"]);
+}
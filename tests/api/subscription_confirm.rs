@@ -1,3 +1,10 @@
+use my_zero2prod::{
+    domain::SubscriptionStatus,
+    entities::{subscription_tokens, subscriptions},
+    i18n::Locale,
+};
+use sea_orm::{ConnectionTrait, EntityTrait, PaginatorTrait};
+
 use crate::helpers::spawn_app;
 
 #[tokio::test]
@@ -12,4 +19,263 @@ async fn confirmation_without_token_are_rejected_with_a_400() {
 
     // Assert
     assert_eq!(response.status().as_u16(), 400);
-}
\ No newline at end of file
+}
+
+#[tokio::test]
+async fn the_link_returned_by_subscribe_returns_a_200_if_called() {
+    // Arrange
+    let app = spawn_app().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+    app.post_subscriptions(body.into()).await;
+    let email = &app.sent_emails()[0];
+    let confirmation_links = app.get_confirmation_links(email);
+
+    // Act
+    let response = reqwest::get(confirmation_links.html).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn clicking_on_the_confirmation_link_confirms_a_subscriber() {
+    // Arrange
+    let app = spawn_app().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+    app.post_subscriptions(body.into()).await;
+    let email = &app.sent_emails()[0];
+    let confirmation_links = app.get_confirmation_links(email);
+
+    // Act
+    reqwest::get(confirmation_links.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+
+    // Assert
+    let saved = subscriptions::Entity::find()
+        .one(&app.db)
+        .await
+        .expect("Failed to fetch saved subscription.")
+        .unwrap();
+    assert_eq!(saved.email, "ursula_le_guin@gmail.com");
+    assert_eq!(saved.name, "le guin");
+    assert_eq!(saved.status, SubscriptionStatus::Confirmed);
+}
+
+#[tokio::test]
+async fn confirming_a_subscription_sends_a_welcome_email() {
+    // Arrange
+    let app = spawn_app().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+    app.post_subscriptions(body.into()).await;
+    let email = &app.sent_emails()[0];
+    let confirmation_links = app.get_confirmation_links(email);
+
+    // Act
+    reqwest::get(confirmation_links.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+
+    // Assert
+    let sent_emails = app.sent_emails();
+    assert_eq!(sent_emails.len(), 2);
+    assert_eq!(sent_emails[1].to, "ursula_le_guin@gmail.com");
+    assert_ne!(sent_emails[1].subject, sent_emails[0].subject);
+    assert!(sent_emails[1].html_content.contains("le guin"));
+}
+
+#[tokio::test]
+async fn clicking_the_confirmation_link_twice_at_once_sends_a_single_welcome_email() {
+    // Arrange
+    let app = spawn_app().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+    app.post_subscriptions(body.into()).await;
+    let confirmation_links = app.get_confirmation_links(&app.sent_emails()[0]);
+
+    // Act
+    let (first, second) = tokio::join!(
+        reqwest::get(confirmation_links.html.clone()),
+        reqwest::get(confirmation_links.html)
+    );
+
+    // Assert
+    let mut statuses = [
+        first.unwrap().status().as_u16(),
+        second.unwrap().status().as_u16(),
+    ];
+    statuses.sort();
+    assert_eq!(statuses, [200, 401]);
+    assert_eq!(app.sent_emails().len(), 2);
+    assert_eq!(saved_status(&app).await, SubscriptionStatus::Confirmed);
+}
+
+async fn expire_all_tokens(app: &crate::helpers::TestApp) {
+    app.db
+        .execute_unprepared(
+            "UPDATE subscription_tokens SET expires_at = now() - interval '1 minute';",
+        )
+        .await
+        .unwrap();
+}
+
+async fn saved_status(app: &crate::helpers::TestApp) -> SubscriptionStatus {
+    subscriptions::Entity::find()
+        .one(&app.db)
+        .await
+        .unwrap()
+        .unwrap()
+        .status
+}
+
+#[tokio::test]
+async fn the_token_is_deleted_once_the_subscription_is_confirmed() {
+    // Arrange
+    let app = spawn_app().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+    app.post_subscriptions(body.into()).await;
+    let confirmation_links = app.get_confirmation_links(&app.sent_emails()[0]);
+
+    // Act
+    let first = reqwest::get(confirmation_links.html.clone()).await.unwrap();
+    let second = reqwest::get(confirmation_links.html).await.unwrap();
+
+    // Assert
+    assert_eq!(first.status().as_u16(), 200);
+    assert_eq!(second.status().as_u16(), 401);
+    let n_tokens = subscription_tokens::Entity::find()
+        .count(&app.db)
+        .await
+        .unwrap();
+    assert_eq!(n_tokens, 0);
+}
+
+#[tokio::test]
+async fn an_expired_token_is_rejected_with_a_410_and_an_offer_to_resend() {
+    // Arrange
+    let app = spawn_app().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+    app.post_subscriptions(body.into()).await;
+    let confirmation_links = app.get_confirmation_links(&app.sent_emails()[0]);
+    expire_all_tokens(&app).await;
+
+    // Act
+    let response = reqwest::get(confirmation_links.html).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 410);
+    let html = response.text().await.unwrap();
+    assert!(html.contains(r#"action="/subscriptions/confirm/resend""#));
+    assert_eq!(saved_status(&app).await, SubscriptionStatus::PendingConfirmation);
+}
+
+#[tokio::test]
+async fn resending_replaces_an_expired_token_with_a_working_one() {
+    // Arrange
+    let app = spawn_app().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+    app.post_subscriptions(body.into()).await;
+    let expired_links = app.get_confirmation_links(&app.sent_emails()[0]);
+    let expired_token = expired_links
+        .html
+        .query_pairs()
+        .find(|(key, _)| key == "subscription_token")
+        .map(|(_, value)| value.into_owned())
+        .unwrap();
+    expire_all_tokens(&app).await;
+
+    // Act
+    let response = app
+        .api_client
+        .post(format!("{}/subscriptions/confirm/resend", app.address))
+        .form(&[("subscription_token", expired_token)])
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let sent_emails = app.sent_emails();
+    assert_eq!(sent_emails.len(), 2);
+    let new_links = app.get_confirmation_links(&sent_emails[1]);
+    assert_ne!(new_links.html, expired_links.html);
+
+    let old = reqwest::get(expired_links.html).await.unwrap();
+    assert_eq!(old.status().as_u16(), 401);
+    let new = reqwest::get(new_links.html).await.unwrap();
+    assert_eq!(new.status().as_u16(), 200);
+    assert_eq!(saved_status(&app).await, SubscriptionStatus::Confirmed);
+}
+
+#[tokio::test]
+async fn resending_with_an_unknown_token_is_rejected_with_a_401() {
+    let app = spawn_app().await;
+
+    let response = app
+        .api_client
+        .post(format!("{}/subscriptions/confirm/resend", app.address))
+        .form(&[("subscription_token", "unknown")])
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status().as_u16(), 401);
+    assert!(app.sent_emails().is_empty());
+}
+
+#[tokio::test]
+async fn confirmation_errors_are_reported_in_the_requested_language() {
+    let app = spawn_app().await;
+
+    let response = app
+        .api_client
+        .get(format!(
+            "{}/subscriptions/confirm?subscription_token=unknown",
+            app.address
+        ))
+        .header("Accept-Language", "en")
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(response.headers()["content-language"], "en");
+    assert_eq!(
+        response.text().await.unwrap(),
+        Locale::En.translate("confirm-error-unknown-token", None)
+    );
+}
+
+#[tokio::test]
+async fn an_unsubscribed_subscriber_cannot_be_confirmed_with_an_old_link() {
+    let app = spawn_app().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+    app.post_subscriptions(body.into()).await;
+    let confirmation_link = app.get_confirmation_links(&app.sent_emails()[0]).html;
+    app.db
+        .execute_unprepared("UPDATE subscriptions SET status = 'unsubscribed';")
+        .await
+        .unwrap();
+
+    let response = reqwest::get(confirmation_link).await.unwrap();
+
+    assert_eq!(response.status().as_u16(), 409);
+    assert_eq!(saved_status(&app).await, SubscriptionStatus::Unsubscribed);
+}
+
+#[tokio::test]
+async fn the_database_rejects_unknown_statuses() {
+    let app = spawn_app().await;
+    app.post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com".into())
+        .await;
+
+    let result = app
+        .db
+        .execute_unprepared("UPDATE subscriptions SET status = 'whatever';")
+        .await;
+
+    assert!(result.is_err());
+}
@@ -1,5 +1,5 @@
-use my_zero2prod::entities::subscriptions;
-use sea_orm::{ConnectionTrait, EntityTrait};
+use my_zero2prod::{domain::SubscriptionStatus, entities::subscriptions};
+use sea_orm::{ConnectionTrait, EntityTrait, PaginatorTrait};
 
 use crate::helpers::spawn_app;
 
@@ -20,6 +20,7 @@ async fn subscribe_returns_a_200_for_valid_form_data() {
     let saved = saved.unwrap();
     assert_eq!(saved.email, "ursula_le_guin@gmail.com");
     assert_eq!(saved.name, "le guin");
+    assert_eq!(saved.locale, "zh-CN");
 }
 
 #[tokio::test]
@@ -78,3 +79,314 @@ async fn subscribe_fails_if_there_is_a_fatal_database_error() {
     let response = app.post_subscriptions(body.into()).await;
     assert_eq!(500, response.status().as_u16());
 }
+
+#[tokio::test]
+async fn subscribe_sends_a_confirmation_email_for_valid_data() {
+    let app = spawn_app().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    app.post_subscriptions(body.into()).await;
+
+    let sent_emails = app.sent_emails();
+    assert_eq!(sent_emails.len(), 1);
+    assert_eq!(sent_emails[0].to, "ursula_le_guin@gmail.com");
+}
+
+#[tokio::test]
+async fn subscribe_sends_a_confirmation_email_with_a_link() {
+    let app = spawn_app().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    app.post_subscriptions(body.into()).await;
+
+    let email = &app.sent_emails()[0];
+    let confirmation_links = app.get_confirmation_links(email);
+    assert_eq!(confirmation_links.html, confirmation_links.plain_text);
+}
+
+#[tokio::test]
+async fn subscribe_stores_the_locale_from_accept_language() {
+    let app = spawn_app().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    let response = app
+        .post_subscriptions_with_language(body.into(), "en-US,en;q=0.9,zh-CN;q=0.8")
+        .await;
+
+    assert_eq!(200, response.status().as_u16());
+    let saved = subscriptions::Entity::find()
+        .one(&app.db)
+        .await
+        .expect("Failed to fetch saved subscription.")
+        .unwrap();
+    assert_eq!(saved.locale, "en");
+    let email = &app.sent_emails()[0];
+    assert_eq!(email.subject, "Please confirm your subscription");
+    assert!(email.text_content.contains("Welcome to our newsletter, le guin!"));
+}
+
+#[tokio::test]
+async fn the_locale_form_field_takes_precedence_over_accept_language() {
+    let app = spawn_app().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com&locale=zh-CN";
+
+    app.post_subscriptions_with_language(body.into(), "en").await;
+
+    let saved = subscriptions::Entity::find()
+        .one(&app.db)
+        .await
+        .expect("Failed to fetch saved subscription.")
+        .unwrap();
+    assert_eq!(saved.locale, "zh-CN");
+    assert_eq!(app.sent_emails()[0].subject, "请确认您的订阅");
+}
+
+#[tokio::test]
+async fn validation_errors_are_returned_in_the_requested_language() {
+    let app = spawn_app().await;
+
+    let response = app
+        .post_subscriptions_with_language("name=abc&email=123".into(), "en")
+        .await;
+
+    assert_eq!(400, response.status().as_u16());
+    assert_eq!(response.headers()["Content-Language"], "en");
+    let body = response.text().await.unwrap();
+    assert_eq!(body, "Validation error: 123 is not a valid email address.");
+
+    let response = app.post_subscriptions("name=abc&email=123".into()).await;
+
+    assert_eq!(400, response.status().as_u16());
+    let body = response.text().await.unwrap();
+    assert_eq!(body, "验证错误: 123 不是一个有效的邮箱");
+}
+
+#[tokio::test]
+async fn subscribing_twice_while_pending_rotates_the_token_and_resends_the_confirmation() {
+    let app = spawn_app().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    let first = app.post_subscriptions(body.into()).await;
+    let second = app.post_subscriptions(body.into()).await;
+
+    assert_eq!(200, first.status().as_u16());
+    assert_eq!(200, second.status().as_u16());
+    let n_subscribers = subscriptions::Entity::find().count(&app.db).await.unwrap();
+    assert_eq!(n_subscribers, 1);
+
+    let sent_emails = app.sent_emails();
+    assert_eq!(sent_emails.len(), 2);
+    let old_link = app.get_confirmation_links(&sent_emails[0]).html;
+    let new_link = app.get_confirmation_links(&sent_emails[1]).html;
+    assert_eq!(reqwest::get(old_link).await.unwrap().status().as_u16(), 401);
+    assert_eq!(reqwest::get(new_link).await.unwrap().status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn subscribing_again_after_confirming_succeeds_without_sending_anything() {
+    let app = spawn_app().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+    app.post_subscriptions(body.into()).await;
+    let confirmation_link = app.get_confirmation_links(&app.sent_emails()[0]).html;
+    reqwest::get(confirmation_link)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+    let n_sent_before = app.sent_emails().len();
+
+    let response = app.post_subscriptions(body.into()).await;
+
+    // 与新订阅的响应完全相同，不泄露邮箱是否已订阅
+    assert_eq!(200, response.status().as_u16());
+    assert!(response.text().await.unwrap().is_empty());
+    assert_eq!(app.sent_emails().len(), n_sent_before);
+    let saved = subscriptions::Entity::find()
+        .all(&app.db)
+        .await
+        .unwrap();
+    assert_eq!(saved.len(), 1);
+    assert_eq!(saved[0].status, SubscriptionStatus::Confirmed);
+}
+
+#[tokio::test]
+async fn subscribing_again_after_unsubscribing_restarts_the_confirmation() {
+    let app = spawn_app().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+    app.post_subscriptions(body.into()).await;
+    app.db
+        .execute_unprepared("UPDATE subscriptions SET status = 'unsubscribed';")
+        .await
+        .unwrap();
+
+    let response = app.post_subscriptions(body.into()).await;
+
+    assert_eq!(200, response.status().as_u16());
+    let saved = subscriptions::Entity::find()
+        .one(&app.db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(saved.status, SubscriptionStatus::PendingConfirmation);
+    assert_eq!(app.sent_emails().len(), 2);
+}
+
+#[tokio::test]
+async fn addresses_differing_only_in_case_or_whitespace_are_one_subscriber() {
+    let app = spawn_app().await;
+
+    for email in [
+        "ursula_le_guin@gmail.com",
+        "Ursula_Le_Guin@GMAIL.com",
+        "  ursula_le_guin@Gmail.Com ",
+    ] {
+        let response = app
+            .post_subscriptions_json(&serde_json::json!({ "name": "le guin", "email": email }))
+            .await;
+        assert_eq!(200, response.status().as_u16());
+    }
+
+    let saved = subscriptions::Entity::find().all(&app.db).await.unwrap();
+    assert_eq!(saved.len(), 1);
+    assert_eq!(saved[0].email, "ursula_le_guin@gmail.com");
+    assert_eq!(saved[0].canonical_email, "ursula_le_guin@gmail.com");
+}
+
+#[tokio::test]
+async fn the_domain_is_stored_lowercase_and_in_punycode() {
+    let app = spawn_app().await;
+
+    app.post_subscriptions_json(&serde_json::json!({
+        "name": "le guin",
+        "email": " Ursula@Bücher.Example ",
+    }))
+    .await;
+
+    let saved = subscriptions::Entity::find()
+        .one(&app.db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(saved.email, "Ursula@xn--bcher-kva.example");
+    assert_eq!(saved.canonical_email, "ursula@xn--bcher-kva.example");
+    assert_eq!(app.sent_emails()[0].to, "Ursula@xn--bcher-kva.example");
+}
+
+#[tokio::test]
+async fn subscribe_accepts_a_json_body() {
+    let app = spawn_app().await;
+
+    let response = app
+        .post_subscriptions_json(&serde_json::json!({
+            "name": "le guin",
+            "email": "ursula_le_guin@gmail.com",
+            "locale": "en",
+        }))
+        .await;
+
+    assert_eq!(200, response.status().as_u16());
+    let saved = subscriptions::Entity::find()
+        .one(&app.db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(saved.email, "ursula_le_guin@gmail.com");
+    assert_eq!(saved.locale, "en");
+    assert_eq!(app.sent_emails().len(), 1);
+}
+
+#[tokio::test]
+async fn json_validation_errors_list_every_invalid_field() {
+    let app = spawn_app().await;
+
+    let response = app
+        .post_subscriptions_json(&serde_json::json!({
+            "name": "",
+            "email": "definitely-not-an-email",
+            "locale": "en",
+        }))
+        .await;
+
+    assert_eq!(400, response.status().as_u16());
+    assert_eq!(response.headers()["Content-Type"], "application/json");
+    assert_eq!(response.headers()["Content-Language"], "en");
+    let body: serde_json::Value = response.json().await.unwrap();
+    let fields: Vec<_> = body["errors"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| e["field"].as_str().unwrap())
+        .collect();
+    assert_eq!(fields, ["name", "email"]);
+    assert_eq!(
+        body["errors"][1]["message"],
+        "definitely-not-an-email is not a valid email address."
+    );
+    assert!(body["message"].as_str().unwrap().starts_with("Validation error:"));
+    assert_eq!(subscriptions::Entity::find().count(&app.db).await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn json_bodies_with_missing_fields_are_rejected_with_a_422() {
+    let app = spawn_app().await;
+
+    let response = app
+        .post_subscriptions_json(&serde_json::json!({ "name": "le guin" }))
+        .await;
+
+    assert_eq!(422, response.status().as_u16());
+    assert_eq!(response.headers()["Content-Type"], "application/json");
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["message"], "请求体格式无效");
+    assert_eq!(body["errors"][0]["field"], "email");
+    assert_eq!(body["errors"][0]["message"], "缺少该字段");
+}
+
+#[tokio::test]
+async fn form_bodies_with_missing_fields_get_a_plain_text_rejection() {
+    let app = spawn_app().await;
+
+    let response = app.post_subscriptions("name=le%20guin".into()).await;
+
+    assert_eq!(422, response.status().as_u16());
+    assert!(
+        response.headers()["Content-Type"]
+            .to_str()
+            .unwrap()
+            .starts_with("text/plain")
+    );
+}
+
+#[tokio::test]
+async fn form_bodies_get_a_json_rejection_when_the_client_accepts_json() {
+    let app = spawn_app().await;
+
+    let response = reqwest::Client::new()
+        .post(format!("{}/subscriptions", &app.address))
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .header("Accept", "application/json")
+        .body("name=le%20guin")
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(422, response.status().as_u16());
+    assert_eq!(response.headers()["Content-Type"], "application/json");
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["errors"][0]["field"], "email");
+}
+
+#[tokio::test]
+async fn unsupported_content_types_are_rejected_with_a_415() {
+    let app = spawn_app().await;
+
+    let response = reqwest::Client::new()
+        .post(format!("{}/subscriptions", &app.address))
+        .header("Content-Type", "text/plain")
+        .body("le guin <ursula_le_guin@gmail.com>")
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(415, response.status().as_u16());
+}
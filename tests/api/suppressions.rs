@@ -0,0 +1,193 @@
+use my_zero2prod::{
+    domain::{SubscriptionStatus, SuppressionSource},
+    entities::suppressions,
+};
+use sea_orm::EntityTrait;
+
+use crate::helpers::{TestApp, assert_is_redirect_to, spawn_app};
+
+const EMAIL: &str = "ursula_le_guin@gmail.com";
+
+async fn suppress(app: &TestApp, email: &str) -> reqwest::Response {
+    app.post_admin_suppressions(&serde_json::json!({
+        "email": email,
+        "reason": "legal request",
+    }))
+    .await
+}
+
+#[tokio::test]
+async fn suppressed_addresses_do_not_receive_a_confirmation_email() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    suppress(&app, EMAIL).await;
+
+    let response = app
+        .post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com".into())
+        .await;
+
+    // 与正常订阅的响应相同，不暴露地址是否被抑制
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(app.sent_emails().is_empty());
+}
+
+#[tokio::test]
+async fn suppressed_addresses_do_not_receive_newsletters() {
+    let app = spawn_app().await;
+    app.insert_subscriber(EMAIL, SubscriptionStatus::Confirmed).await;
+    app.insert_subscriber("octavia_butler@gmail.com", SubscriptionStatus::Confirmed).await;
+    app.test_user.login(&app).await;
+    suppress(&app, EMAIL).await;
+
+    app.post_publish_newsletter(&serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": uuid::Uuid::new_v4().to_string(),
+    }))
+    .await;
+    app.dispatch_all_pending_emails().await;
+
+    let sent = app.sent_emails();
+    assert_eq!(sent.len(), 1);
+    assert_eq!(sent[0].to, "octavia_butler@gmail.com");
+}
+
+#[tokio::test]
+async fn suppressions_match_addresses_case_insensitively() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    suppress(&app, " Ursula_Le_Guin@Gmail.com ").await;
+
+    app.post_subscriptions("name=le%20guin&email=ursula_le_guin%40GMAIL.COM".into())
+        .await;
+
+    assert!(app.sent_emails().is_empty());
+    let entry = suppressions::Entity::find_by_id(EMAIL)
+        .one(&app.db)
+        .await
+        .unwrap()
+        .expect("The address was not stored in normalized form.");
+    assert_eq!(entry.source, SuppressionSource::Admin);
+}
+
+#[tokio::test]
+async fn complaints_add_the_address_to_the_suppression_list() {
+    let app = spawn_app().await;
+
+    app.post_email_event(&serde_json::json!({
+        "RecordType": "SpamComplaint",
+        "Email": EMAIL,
+        "BouncedAt": "2025-10-21T09:00:00Z",
+    }))
+    .await;
+
+    let entry = suppressions::Entity::find_by_id(EMAIL)
+        .one(&app.db)
+        .await
+        .unwrap()
+        .expect("The address was not suppressed.");
+    assert_eq!(entry.source, SuppressionSource::Webhook);
+    assert_eq!(entry.reason, "complaint");
+}
+
+#[tokio::test]
+async fn soft_bounces_do_not_add_the_address_to_the_suppression_list() {
+    let app = spawn_app().await;
+
+    app.post_email_event(&serde_json::json!({
+        "RecordType": "Bounce",
+        "Type": "SoftBounce",
+        "Email": EMAIL,
+        "BouncedAt": "2025-10-21T09:00:00Z",
+    }))
+    .await;
+
+    assert!(
+        suppressions::Entity::find_by_id(EMAIL)
+            .one(&app.db)
+            .await
+            .unwrap()
+            .is_none()
+    );
+}
+
+#[tokio::test]
+async fn admins_can_list_and_remove_suppressions() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    let response = suppress(&app, EMAIL).await;
+    assert_eq!(response.status().as_u16(), 201);
+    let list: serde_json::Value = app.get_admin_suppressions().await.json().await.unwrap();
+    assert_eq!(list.as_array().unwrap().len(), 1);
+    assert_eq!(list[0]["email"], EMAIL);
+    assert_eq!(list[0]["reason"], "legal request");
+    assert_eq!(list[0]["source"], "admin");
+
+    let response = app.delete_admin_suppression(EMAIL).await;
+    assert_eq!(response.status().as_u16(), 204);
+    let list: serde_json::Value = app.get_admin_suppressions().await.json().await.unwrap();
+    assert!(list.as_array().unwrap().is_empty());
+
+    // 移出后可以重新订阅并收到确认邮件
+    app.post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com".into())
+        .await;
+    assert_eq!(app.sent_emails().len(), 1);
+}
+
+#[tokio::test]
+async fn adding_an_address_twice_is_a_conflict() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    suppress(&app, EMAIL).await;
+
+    let response = suppress(&app, "URSULA_LE_GUIN@gmail.com").await;
+
+    assert_eq!(response.status().as_u16(), 409);
+}
+
+#[tokio::test]
+async fn invalid_suppressions_are_rejected() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    let test_cases = [
+        (
+            serde_json::json!({ "email": "not-an-email", "reason": "x" }),
+            "invalid email",
+        ),
+        (
+            serde_json::json!({ "email": EMAIL, "reason": "  " }),
+            "empty reason",
+        ),
+    ];
+    for (body, description) in test_cases {
+        let response = app.post_admin_suppressions(&body).await;
+        assert_eq!(
+            response.status().as_u16(),
+            400,
+            "The API did not reject a suppression with {}.",
+            description
+        );
+    }
+}
+
+#[tokio::test]
+async fn removing_an_unknown_address_returns_404() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    let response = app.delete_admin_suppression(EMAIL).await;
+
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_manage_suppressions() {
+    let app = spawn_app().await;
+
+    assert_is_redirect_to(&app.get_admin_suppressions().await, "/login");
+    assert_is_redirect_to(&suppress(&app, EMAIL).await, "/login");
+    assert_is_redirect_to(&app.delete_admin_suppression(EMAIL).await, "/login");
+}
@@ -0,0 +1,258 @@
+use my_zero2prod::{
+    domain::{EmailEventKind, SubscriptionStatus},
+    entities::{email_deliveries, email_events, newsletter_issues},
+};
+use sea_orm::{EntityTrait, PaginatorTrait};
+
+use crate::helpers::{TestApp, spawn_app, spawn_app_with};
+
+/// 不跟随跳转的客户端，用于检查跳转目标
+fn client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .no_proxy()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap()
+}
+
+/// HTML 正文中被改写的点击跟踪链接
+fn click_links(app: &TestApp) -> Vec<String> {
+    let html = &app.sent_emails()[0].html_content;
+    linkify::LinkFinder::new()
+        .links(html)
+        .map(|l| l.as_str().to_string())
+        .filter(|l| l.contains("/t/c/"))
+        .map(|l| l.replace("127.0.0.1/", &format!("127.0.0.1:{}/", app.port)))
+        .collect()
+}
+
+/// 发布并投递一封新闻邮件，`switches` 中的跟踪选项会被勾选，返回新闻邮件 ID
+async fn publish_and_deliver(app: &TestApp, switches: &[&str]) -> uuid::Uuid {
+    let mut body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": r#"<p>Read <a href="https://example.com/post?id=1&amp;ref=mail">the post</a> or <a href="mailto:editor@example.com">reply</a></p>"#,
+        "idempotency_key": uuid::Uuid::new_v4().to_string(),
+    });
+    for switch in switches {
+        body[*switch] = "on".into();
+    }
+    app.post_publish_newsletter(&body).await;
+    app.dispatch_all_pending_emails().await;
+    newsletter_issues::Entity::find()
+        .one(&app.db)
+        .await
+        .unwrap()
+        .unwrap()
+        .newsletter_issue_id
+}
+
+async fn n_events(app: &TestApp) -> u64 {
+    email_events::Entity::find().count(&app.db).await.unwrap()
+}
+
+async fn only_delivery_id(app: &TestApp) -> uuid::Uuid {
+    let deliveries = email_deliveries::Entity::find().all(&app.db).await.unwrap();
+    assert_eq!(deliveries.len(), 1);
+    deliveries[0].delivery_id
+}
+
+#[tokio::test]
+async fn opening_a_tracked_newsletter_is_recorded_once_per_delivery() {
+    let app = spawn_app().await;
+    app.insert_subscriber("ursula_le_guin@gmail.com", SubscriptionStatus::Confirmed).await;
+    app.test_user.login(&app).await;
+    let issue_id = publish_and_deliver(&app, &["track_opens"]).await;
+
+    let email = &app.sent_emails()[0];
+    let pixel = app
+        .get_open_tracking_pixel(email)
+        .expect("No tracking pixel.");
+    assert_eq!(
+        pixel.path(),
+        format!("/t/o/{}.gif", only_delivery_id(&app).await)
+    );
+    for _ in 0..2 {
+        let response = reqwest::get(pixel.clone()).await.unwrap();
+        assert_eq!(response.status().as_u16(), 200);
+        assert_eq!(response.headers()["Content-Type"], "image/gif");
+    }
+
+    let events = email_events::Entity::find().all(&app.db).await.unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].kind, EmailEventKind::Open);
+    let stats: serde_json::Value = app
+        .get_newsletter_stats(issue_id)
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(stats["deliveries"], 1);
+    assert_eq!(stats["unique_opens"], 1);
+}
+
+#[tokio::test]
+async fn newsletters_without_open_tracking_have_no_pixel() {
+    let app = spawn_app().await;
+    app.insert_subscriber("ursula_le_guin@gmail.com", SubscriptionStatus::Confirmed).await;
+    app.test_user.login(&app).await;
+    publish_and_deliver(&app, &[]).await;
+
+    assert!(app.get_open_tracking_pixel(&app.sent_emails()[0]).is_none());
+    // 即使有人猜到了地址，也不会记录打开
+    let pixel = format!("{}/t/o/{}.gif", app.address, only_delivery_id(&app).await);
+    let response = reqwest::get(pixel).await.unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(n_events(&app).await, 0);
+}
+
+#[tokio::test]
+async fn open_tracking_can_be_turned_off_globally() {
+    let app = spawn_app_with(|c| c.tracking.open_tracking_enabled = false).await;
+    app.insert_subscriber("ursula_le_guin@gmail.com", SubscriptionStatus::Confirmed).await;
+    app.test_user.login(&app).await;
+    publish_and_deliver(&app, &["track_opens"]).await;
+
+    assert!(app.get_open_tracking_pixel(&app.sent_emails()[0]).is_none());
+    let pixel = format!("{}/t/o/{}.gif", app.address, only_delivery_id(&app).await);
+    let response = reqwest::get(pixel).await.unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(n_events(&app).await, 0);
+}
+
+#[tokio::test]
+async fn unknown_deliveries_get_the_same_pixel_and_are_not_recorded() {
+    let app = spawn_app().await;
+
+    let response = reqwest::get(format!("{}/t/o/{}.gif", app.address, uuid::Uuid::new_v4()))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.headers()["Content-Type"], "image/gif");
+    assert_eq!(n_events(&app).await, 0);
+}
+
+#[tokio::test]
+async fn malformed_pixel_paths_are_rejected() {
+    let app = spawn_app().await;
+
+    for path in ["not-a-uuid.gif", &uuid::Uuid::new_v4().to_string()] {
+        let response = reqwest::get(format!("{}/t/o/{}", app.address, path))
+            .await
+            .unwrap();
+        assert_eq!(response.status().as_u16(), 404, "path: {}", path);
+    }
+}
+
+#[tokio::test]
+async fn stats_of_an_unknown_newsletter_are_not_found() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    let response = app.get_newsletter_stats(uuid::Uuid::new_v4()).await;
+
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_see_newsletter_stats() {
+    let app = spawn_app().await;
+
+    let response = app.get_newsletter_stats(uuid::Uuid::new_v4()).await;
+
+    crate::helpers::assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn clicking_a_tracked_link_is_recorded_and_redirects_to_the_original_url() {
+    let app = spawn_app().await;
+    app.insert_subscriber("ursula_le_guin@gmail.com", SubscriptionStatus::Confirmed).await;
+    app.test_user.login(&app).await;
+    let issue_id = publish_and_deliver(&app, &["track_clicks"]).await;
+
+    let html = &app.sent_emails()[0].html_content;
+    assert!(!html.contains("https://example.com/post"));
+    assert!(html.contains("mailto:editor@example.com"));
+    let links = click_links(&app);
+    assert_eq!(links.len(), 1);
+    for _ in 0..2 {
+        let response = client().get(&links[0]).send().await.unwrap();
+        assert_eq!(response.status().as_u16(), 302);
+        assert_eq!(
+            response.headers()["Location"],
+            "https://example.com/post?id=1&ref=mail"
+        );
+    }
+
+    let stats: serde_json::Value = app
+        .get_newsletter_stats(issue_id)
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(
+        stats["links"],
+        serde_json::json!([{
+            "url": "https://example.com/post?id=1&ref=mail",
+            "clicks": 2,
+            "unique_clicks": 1,
+        }])
+    );
+}
+
+#[tokio::test]
+async fn links_are_not_rewritten_without_click_tracking() {
+    let app = spawn_app().await;
+    app.insert_subscriber("ursula_le_guin@gmail.com", SubscriptionStatus::Confirmed).await;
+    app.test_user.login(&app).await;
+    publish_and_deliver(&app, &[]).await;
+
+    assert!(click_links(&app).is_empty());
+    assert!(
+        app.sent_emails()[0]
+            .html_content
+            .contains("https://example.com/post?id=1&amp;ref=mail")
+    );
+}
+
+#[tokio::test]
+async fn click_tracking_can_be_turned_off_globally() {
+    let app = spawn_app_with(|c| c.tracking.click_tracking_enabled = false).await;
+    app.insert_subscriber("ursula_le_guin@gmail.com", SubscriptionStatus::Confirmed).await;
+    app.test_user.login(&app).await;
+    publish_and_deliver(&app, &["track_clicks"]).await;
+
+    assert!(click_links(&app).is_empty());
+}
+
+#[tokio::test]
+async fn tampered_click_tokens_are_not_redirected() {
+    let app = spawn_app().await;
+    app.insert_subscriber("ursula_le_guin@gmail.com", SubscriptionStatus::Confirmed).await;
+    app.test_user.login(&app).await;
+    publish_and_deliver(&app, &["track_clicks"]).await;
+    let link = click_links(&app).remove(0);
+    let (prefix, token) = link.rsplit_once('/').unwrap();
+    let (delivery_id, rest) = token.split_once('.').unwrap();
+    let (_, signature) = rest.split_once('.').unwrap();
+    // 把目标地址换成 https://evil.example，保留原来的签名
+    let forged = format!(
+        "{}/{}.aHR0cHM6Ly9ldmlsLmV4YW1wbGU.{}",
+        prefix, delivery_id, signature
+    );
+
+    for url in [
+        forged,
+        format!("{}/t/c/https%3A%2F%2Fevil.example", app.address),
+        format!(
+            "{}/t/c/{}.aHR0cHM6Ly9ldmlsLmV4YW1wbGU",
+            app.address, delivery_id
+        ),
+    ] {
+        let response = client().get(&url).send().await.unwrap();
+        assert_eq!(response.status().as_u16(), 404, "url: {}", url);
+        assert!(response.headers().get("Location").is_none());
+    }
+    assert_eq!(n_events(&app).await, 0);
+}
@@ -0,0 +1,166 @@
+use my_zero2prod::{domain::SubscriptionStatus, entities::subscriptions};
+use sea_orm::{ConnectionTrait, EntityTrait};
+
+use crate::helpers::{TestApp, assert_is_redirect_to, spawn_app};
+
+/// 创建一个已确认的订阅者，返回发给他的欢迎邮件中的退订链接
+async fn create_confirmed_subscriber(app: &TestApp) -> reqwest::Url {
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+    app.post_subscriptions(body.into())
+        .await
+        .error_for_status()
+        .unwrap();
+    let confirmation_links = app.get_confirmation_links(&app.sent_emails()[0]);
+    reqwest::get(confirmation_links.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+
+    let welcome_email = app.sent_emails().pop().unwrap();
+    app.get_unsubscribe_link(&welcome_email)
+}
+
+fn token(link: &reqwest::Url) -> String {
+    link.query_pairs()
+        .find(|(key, _)| key == "token")
+        .map(|(_, value)| value.into_owned())
+        .unwrap()
+}
+
+async fn post_unsubscribe(app: &TestApp, token: &str) -> reqwest::Response {
+    app.api_client
+        .post(format!("{}/subscriptions/unsubscribe", &app.address))
+        .form(&[("token", token)])
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+async fn saved_status(app: &TestApp) -> SubscriptionStatus {
+    subscriptions::Entity::find()
+        .one(&app.db)
+        .await
+        .unwrap()
+        .unwrap()
+        .status
+}
+
+#[tokio::test]
+async fn the_confirmation_email_contains_an_unsubscribe_link() {
+    let app = spawn_app().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    app.post_subscriptions(body.into()).await;
+
+    app.get_unsubscribe_link(&app.sent_emails()[0]);
+}
+
+#[tokio::test]
+async fn the_unsubscribe_link_shows_a_confirmation_form() {
+    let app = spawn_app().await;
+    let link = create_confirmed_subscriber(&app).await;
+
+    let response = reqwest::get(link.clone()).await.unwrap();
+
+    assert_eq!(response.status().as_u16(), 200);
+    let html = response.text().await.unwrap();
+    assert!(html.contains(r#"action="/subscriptions/unsubscribe" method="post""#));
+    assert!(html.contains(&format!(r#"value="{}""#, token(&link))));
+    // 只是打开页面不会退订
+    assert_eq!(saved_status(&app).await, SubscriptionStatus::Confirmed);
+}
+
+#[tokio::test]
+async fn an_invalid_token_is_rejected_with_a_401() {
+    let app = spawn_app().await;
+    let link = create_confirmed_subscriber(&app).await;
+    let token = token(&link);
+    let (subscriber_id, _) = token.split_once('.').unwrap();
+    let forged = format!("{}.{}", subscriber_id, "0".repeat(64));
+
+    for token in ["", "abc", forged.as_str()] {
+        let response = reqwest::get(format!(
+            "{}/subscriptions/unsubscribe?token={}",
+            app.address, token
+        ))
+        .await
+        .unwrap();
+        assert_eq!(response.status().as_u16(), 401);
+
+        let response = post_unsubscribe(&app, token).await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+    assert_eq!(saved_status(&app).await, SubscriptionStatus::Confirmed);
+}
+
+#[tokio::test]
+async fn unsubscribing_marks_the_subscriber_as_unsubscribed() {
+    let app = spawn_app().await;
+    let link = create_confirmed_subscriber(&app).await;
+    let n_sent_before = app.sent_emails().len();
+
+    let response = post_unsubscribe(&app, &token(&link)).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(saved_status(&app).await, SubscriptionStatus::Unsubscribed);
+    let sent_emails = app.sent_emails();
+    assert_eq!(sent_emails.len(), n_sent_before + 1);
+    let confirmation = sent_emails.last().unwrap();
+    assert_eq!(confirmation.to, "ursula_le_guin@gmail.com");
+    assert!(!confirmation.html_content.contains("/subscriptions/unsubscribe"));
+}
+
+#[tokio::test]
+async fn unsubscribing_twice_sends_a_single_confirmation_email() {
+    let app = spawn_app().await;
+    let link = create_confirmed_subscriber(&app).await;
+    let n_sent_before = app.sent_emails().len();
+
+    post_unsubscribe(&app, &token(&link)).await;
+    let response = post_unsubscribe(&app, &token(&link)).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(app.sent_emails().len(), n_sent_before + 1);
+}
+
+#[tokio::test]
+async fn a_complaint_is_not_overwritten_by_unsubscribing() {
+    let app = spawn_app().await;
+    let link = create_confirmed_subscriber(&app).await;
+    app.db
+        .execute_unprepared("UPDATE subscriptions SET status = 'complained';")
+        .await
+        .unwrap();
+    let n_sent_before = app.sent_emails().len();
+
+    let response = post_unsubscribe(&app, &token(&link)).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(saved_status(&app).await, SubscriptionStatus::Complained);
+    assert_eq!(app.sent_emails().len(), n_sent_before);
+}
+
+#[tokio::test]
+async fn newsletters_are_not_delivered_to_subscribers_who_unsubscribed_after_publishing() {
+    // Arrange
+    let app = spawn_app().await;
+    let link = create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": uuid::Uuid::new_v4().to_string(),
+    });
+    let response = app.post_publish_newsletter(&newsletter_request_body).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+
+    // Act
+    post_unsubscribe(&app, &token(&link)).await;
+    let n_sent_before = app.sent_emails().len();
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    assert_eq!(app.sent_emails().len(), n_sent_before);
+}